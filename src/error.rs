@@ -1,10 +1,16 @@
-use crate::exchanges::binance::FilterError;
+use crate::exchanges::live::FilterError;
 use openlimits::errors::OpenLimitsError;
 
 #[derive(Debug)]
 pub enum Error {
     OpenLimits(OpenLimitsError),
     Filter(FilterError),
+    /// The venue doesn't support a feature.
+    Unsupported(&'static str),
+    /// The venue left out a field its response has to contain.
+    Missing(&'static str),
+    /// The venue refused a request, e.g. for lack of funds.
+    Rejected(String),
 }
 
 impl From<OpenLimitsError> for Error {
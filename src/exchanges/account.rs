@@ -0,0 +1,67 @@
+use super::{Order, Trail};
+use crate::Market;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Something a strategy wants the exchange to do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Intent {
+    /// Opens a new position.
+    Place(Order),
+    /// Cancels the entry orders in a market that haven't been filled yet.
+    Cancel(Market),
+    /// Moves the stop loss of the position in a market.
+    AmendStop { market: Market, stop_loss: Decimal },
+    /// Lets the stop loss of the position in a market follow the price. The stop loss is only
+    /// ever moved up.
+    TrailStop { market: Market, trail: Trail },
+    /// Moves the stop loss of the position in a market to its entry price once the price has
+    /// risen by `after`, 0.01 is 1%.
+    BreakEven { market: Market, after: Decimal },
+    /// Sells a fraction of the position in a market, 1 closes it completely.
+    Close { market: Market, fraction: Decimal },
+}
+
+impl Intent {
+    pub fn market(&self) -> &Market {
+        match self {
+            Self::Place(Order { market, .. })
+            | Self::Cancel(market)
+            | Self::AmendStop { market, .. }
+            | Self::TrailStop { market, .. }
+            | Self::BreakEven { market, .. }
+            | Self::Close { market, .. } => market,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenPosition {
+    pub market: Market,
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub take_profit: Option<Decimal>,
+    pub stop_loss: Option<Decimal>,
+    pub timestamp: i64,
+}
+
+/// What a strategy can see of its account when it receives an event.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Account {
+    pub positions: Vec<OpenPosition>,
+    pub orders: Vec<Order>,
+}
+
+impl Account {
+    pub fn position(&self, market: &Market) -> Option<&OpenPosition> {
+        self.positions
+            .iter()
+            .find(|position| position.market == *market)
+    }
+
+    pub fn orders<'a>(&'a self, market: &'a Market) -> impl Iterator<Item = &'a Order> {
+        self.orders
+            .iter()
+            .filter(move |order| order.market == *market)
+    }
+}
@@ -0,0 +1,116 @@
+use super::{merge, Account, Exchange, MarketEvent, Strategy, Trade};
+use async_trait::async_trait;
+use std::{fs, path::Path};
+
+/// Replays the trades of several venues as one stream ordered by timestamp, each tagged with the
+/// venue it came from, so strategies can compare venues. Like `Historical` it only passes market
+/// data, orders are simulated by the strategy, e.g. with `Simulated`.
+#[derive(Default)]
+pub struct Aggregate {
+    sources: Vec<(String, Vec<Trade>)>,
+}
+
+impl Aggregate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the trades of `venue`, e.g. from `Historical::trades`.
+    pub fn with_trades(mut self, venue: &str, mut trades: Vec<Trade>) -> Self {
+        trades.sort_by_key(|trade| trade.timestamp);
+        self.sources.push((venue.to_owned(), trades));
+        self
+    }
+
+    /// Adds the trades of `venue` recorded in a file like the cache of `Historical`.
+    pub fn with_recording<P: AsRef<Path>>(self, venue: &str, path: P) -> Self {
+        let bin = fs::read(path).expect("Couldn't read recorded trades.");
+        let trades = bincode::deserialize(&bin[..]).expect("Couldn't read recorded trades.");
+        self.with_trades(venue, trades)
+    }
+}
+
+/// Merges the trades of all sources in the order of `merge::merge`, the markets of every source
+/// in separate streams, and tags them with their venue.
+pub fn merge_venues(sources: Vec<(String, Vec<Trade>)>) -> impl Iterator<Item = Trade> {
+    merge::merge(
+        sources
+            .into_iter()
+            .flat_map(|(venue, trades)| {
+                merge::by_market(
+                    trades
+                        .into_iter()
+                        .map(|mut trade| {
+                            trade.venue = Some(venue.clone());
+                            trade
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[async_trait]
+impl<S: Strategy + 'static> Exchange<S> for Aggregate {
+    async fn run(self, strategy: &mut S) {
+        for trade in merge_venues(self.sources) {
+            strategy.event(MarketEvent::Trade(trade), &Account::default());
+        }
+        strategy.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn trades(market: &str, timestamps: &[i64]) -> Vec<Trade> {
+        timestamps
+            .iter()
+            .enumerate()
+            .map(|(i, timestamp)| Trade {
+                market: market.to_owned(),
+                quantity: Decimal::new(1, 0),
+                price: Decimal::new(i as i64, 0),
+                timestamp: *timestamp,
+                venue: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merge_by_timestamp() {
+        let merged = merge_venues(vec![
+            ("Binance".to_owned(), trades("BTCUSDT", &[1000, 3000, 3000])),
+            (
+                "Coinbase".to_owned(),
+                trades("BTC-USD", &[2000, 3000, 4000]),
+            ),
+            ("Empty".to_owned(), Vec::new()),
+        ])
+        .map(|trade| {
+            format!(
+                "{} {} {} {}",
+                trade.timestamp,
+                trade.venue.unwrap(),
+                trade.market,
+                trade.price
+            )
+        })
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            merged,
+            vec![
+                "1000 Binance BTCUSDT 0",
+                "2000 Coinbase BTC-USD 0",
+                "3000 Coinbase BTC-USD 1",
+                "3000 Binance BTCUSDT 1",
+                "3000 Binance BTCUSDT 2",
+                "4000 Coinbase BTC-USD 2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{exchanges::live::adapter::AccountRequests, Market};
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use openlimits::{
+    binance::{
+        model::{websocket::BinanceSubscription, Prices, Symbol, SymbolPrice},
+        Binance as OpenLimitsBinance, BinanceParameters, BinanceWebsocket,
+    },
+    exchange::{Exchange as OpenLimitsExchange, ExchangeMarketData},
+    exchange_info::ExchangeInfoRetrieval,
+    exchange_ws::{ExchangeWs, OpenLimitsWs},
+    model::{websocket::WebSocketResponse, OrderBookRequest, OrderBookResponse, TimeInForce},
+    shared::Result as OpenLimitsResult,
+};
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Aggregated trades in a range of time, or from an ID on.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggTradesRequest {
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<i64>,
+    pub limit: usize,
+}
+
+/// A taker order and all the maker orders it filled at the same price.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggTrade {
+    #[serde(rename = "a")]
+    pub id: u64,
+    #[serde(rename = "p")]
+    pub price: Decimal,
+    #[serde(rename = "q")]
+    pub quantity: Decimal,
+    #[serde(rename = "T")]
+    pub timestamp: i64,
+    #[serde(rename = "m")]
+    pub buyer_is_maker: bool,
+}
+
+pub type Messages = BoxStream<
+    'static,
+    OpenLimitsResult<WebSocketResponse<<BinanceWebsocket as ExchangeWs>::Response>>,
+>;
+
+/// The requests `Binance` makes, in the types openlimits returns them in. Everything it converts
+/// from them can be tested against a mock this way.
+#[async_trait]
+pub trait Client: AccountRequests + 'static {
+    async fn server_time(&self) -> OpenLimitsResult<i64>;
+
+    async fn exchange_info(&self) -> OpenLimitsResult<Vec<Symbol>>;
+
+    async fn all_prices(&self) -> OpenLimitsResult<Vec<SymbolPrice>>;
+
+    async fn agg_trades(&self, request: &AggTradesRequest) -> OpenLimitsResult<Vec<AggTrade>>;
+
+    async fn order_book(&self, request: &OrderBookRequest) -> OpenLimitsResult<OrderBookResponse>;
+
+    /// Connects to the websocket of the sandbox or of production and subscribes to
+    /// `subscriptions`.
+    async fn subscribe(
+        &self,
+        subscriptions: &[BinanceSubscription],
+        sandbox: bool,
+    ) -> OpenLimitsResult<Messages>;
+
+    /// Sells `quantity` at `price`, or at `stop_limit_price` once the price fell to `stop_price`,
+    /// until it is cancelled.
+    async fn oco_sell(
+        &self,
+        market: &Market,
+        quantity: Decimal,
+        price: Decimal,
+        stop_price: Decimal,
+        stop_limit_price: Decimal,
+    ) -> OpenLimitsResult<()>;
+}
+
+#[async_trait]
+impl Client for OpenLimitsBinance {
+    async fn server_time(&self) -> OpenLimitsResult<i64> {
+        let inner = self.inner_client().unwrap();
+        Ok(inner.get_server_time().await?.server_time as i64)
+    }
+
+    async fn exchange_info(&self) -> OpenLimitsResult<Vec<Symbol>> {
+        let inner = self.inner_client().unwrap();
+        Ok(inner.get_exchange_info().await?.symbols)
+    }
+
+    async fn all_prices(&self) -> OpenLimitsResult<Vec<SymbolPrice>> {
+        let inner = self.inner_client().unwrap();
+        let Prices::AllPrices(prices) = inner.get_all_prices().await?;
+        Ok(prices)
+    }
+
+    async fn agg_trades(&self, request: &AggTradesRequest) -> OpenLimitsResult<Vec<AggTrade>> {
+        let inner = self.inner_client().unwrap();
+        inner
+            .transport
+            .get("/api/v3/aggTrades", Some(request))
+            .await
+    }
+
+    async fn order_book(&self, request: &OrderBookRequest) -> OpenLimitsResult<OrderBookResponse> {
+        ExchangeMarketData::order_book(self, request).await
+    }
+
+    async fn subscribe(
+        &self,
+        subscriptions: &[BinanceSubscription],
+        sandbox: bool,
+    ) -> OpenLimitsResult<Messages> {
+        OpenLimitsWs {
+            websocket: BinanceWebsocket::new(if sandbox {
+                BinanceParameters::sandbox()
+            } else {
+                BinanceParameters::prod()
+            })
+            .await?,
+        }
+        .create_stream(subscriptions)
+        .await
+    }
+
+    async fn oco_sell(
+        &self,
+        market: &Market,
+        quantity: Decimal,
+        price: Decimal,
+        stop_price: Decimal,
+        stop_limit_price: Decimal,
+    ) -> OpenLimitsResult<()> {
+        let inner = self.inner_client().unwrap();
+        let pair = self.get_pair(market).await?.read()?;
+
+        inner
+            .oco_sell(
+                pair,
+                quantity,
+                price,
+                stop_price,
+                Some(stop_limit_price),
+                Some(TimeInForce::GoodTillCancelled.into()),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use super::client::{AggTrade, AggTradesRequest, Client, Messages};
+use crate::{exchanges::live::adapter::AccountRequests, Market};
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use openlimits::{
+    binance::model::{websocket::BinanceSubscription, Symbol, SymbolPrice},
+    model::{
+        websocket::{OpenLimitsWebSocketMessage, WebSocketResponse},
+        Balance, CancelAllOrdersRequest, CancelOrderRequest, GetOrderRequest,
+        OpenLimitOrderRequest, OpenMarketOrderRequest, Order as OpenLimitsOrder, OrderBookRequest,
+        OrderBookResponse, OrderStatus, OrderType, Side, Trade as OpenLimitsTrade,
+    },
+    shared::Result as OpenLimitsResult,
+};
+use rust_decimal::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::time::{sleep, Duration};
+
+/// Answers the requests of `Binance` like its endpoints would, from a script, so its conversions
+/// are covered down to the types openlimits returns. Exchange info and aggregated trades are given
+/// as the JSON of the REST API, orders are answered like the openlimits client does. Market orders
+/// fill right away at the last streamed price. Order requests are logged as they arrive.
+pub struct Mock {
+    symbols: Vec<Symbol>,
+    prices: Vec<SymbolPrice>,
+    balances: Vec<Balance>,
+    trades: Mutex<Option<Vec<OpenLimitsTrade>>>,
+    agg_trades: Vec<AggTrade>,
+    orders: Mutex<HashMap<String, OpenLimitsOrder>>,
+    price: Arc<Mutex<Decimal>>,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Mock {
+    /// Lists the symbols of an `exchangeInfo` response.
+    pub fn new(exchange_info: &str) -> Self {
+        let mut exchange_info: serde_json::Value = serde_json::from_str(exchange_info).unwrap();
+
+        Self {
+            symbols: serde_json::from_value(exchange_info["symbols"].take()).unwrap(),
+            prices: Vec::new(),
+            balances: Vec::new(),
+            trades: Mutex::new(None),
+            agg_trades: Vec::new(),
+            orders: Mutex::new(HashMap::new()),
+            price: Arc::new(Mutex::new(Decimal::zero())),
+            log: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn with_price(mut self, market: &str, price: i64) -> Self {
+        self.prices.push(SymbolPrice {
+            symbol: market.to_owned(),
+            price: Decimal::new(price, 0),
+        });
+        self
+    }
+
+    pub fn with_balance(mut self, asset: &str, free: i64) -> Self {
+        self.balances.push(Balance {
+            asset: asset.to_owned(),
+            total: Decimal::new(free, 0),
+            free: Decimal::new(free, 0),
+        });
+        self
+    }
+
+    /// Streams trades of `market` at these prices on the first connection, a second apart, taker
+    /// buys and sells in turn. The connection stays open afterwards.
+    pub fn with_trades(self, market: &str, prices: &[i64]) -> Self {
+        let trades = prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| OpenLimitsTrade {
+                id: i.to_string(),
+                buyer_order_id: None,
+                seller_order_id: None,
+                market_pair: market.to_owned(),
+                price: Decimal::new(*price, 0),
+                qty: Decimal::one(),
+                fees: None,
+                side: if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                liquidity: None,
+                created_at: (i as u64 + 1) * 1000,
+            })
+            .collect();
+        *self.trades.lock().unwrap() = Some(trades);
+        self
+    }
+
+    /// Serves the trades of an `aggTrades` response, by time or from an ID on like the endpoint.
+    pub fn with_agg_trades(mut self, agg_trades: &str) -> Self {
+        self.agg_trades = serde_json::from_str(agg_trades).unwrap();
+        self
+    }
+
+    /// Answers requests for the order with the ID of `order` with it.
+    pub fn with_order(self, order: OpenLimitsOrder) -> Self {
+        self.orders.lock().unwrap().insert(order.id.clone(), order);
+        self
+    }
+
+    /// Stays accessible after the client was moved into `Binance`.
+    pub fn log(&self) -> Arc<Mutex<Vec<String>>> {
+        self.log.clone()
+    }
+
+    fn push(&self, entry: String) {
+        self.log.lock().unwrap().push(entry);
+    }
+
+    /// An order that was filled completely at the last price.
+    fn filled(&self, market: &Market, side: Side, size: Decimal) -> OpenLimitsOrder {
+        let mut orders = self.orders.lock().unwrap();
+        let order = OpenLimitsOrder {
+            id: (orders.len() + 1).to_string(),
+            market_pair: market.clone(),
+            client_order_id: None,
+            created_at: None,
+            order_type: OrderType::Market,
+            side,
+            status: OrderStatus::Filled,
+            size,
+            price: Some(*self.price.lock().unwrap()),
+            remaining: Some(Decimal::zero()),
+            trades: Vec::new(),
+        };
+        orders.insert(order.id.clone(), order.clone());
+        order
+    }
+}
+
+#[async_trait]
+impl AccountRequests for Mock {
+    async fn balances(&self) -> OpenLimitsResult<Vec<Balance>> {
+        Ok(self.balances.clone())
+    }
+
+    async fn market_buy(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        self.push(format!(
+            "market buy {} {}",
+            request.size, request.market_pair
+        ));
+        Ok(self.filled(&request.market_pair, Side::Buy, request.size))
+    }
+
+    /// Limit orders never fill.
+    async fn limit_buy(
+        &self,
+        request: &OpenLimitOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        self.push(format!(
+            "limit buy {} {} at {}",
+            request.size, request.market_pair, request.price
+        ));
+        let mut orders = self.orders.lock().unwrap();
+        let order = OpenLimitsOrder {
+            id: (orders.len() + 1).to_string(),
+            market_pair: request.market_pair.clone(),
+            client_order_id: None,
+            created_at: None,
+            order_type: OrderType::Limit,
+            side: Side::Buy,
+            status: OrderStatus::New,
+            size: request.size,
+            price: Some(request.price),
+            remaining: Some(request.size),
+            trades: Vec::new(),
+        };
+        orders.insert(order.id.clone(), order.clone());
+        Ok(order)
+    }
+
+    async fn market_sell(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        self.push(format!(
+            "market sell {} {}",
+            request.size, request.market_pair
+        ));
+        Ok(self.filled(&request.market_pair, Side::Sell, request.size))
+    }
+
+    async fn get_order(&self, request: &GetOrderRequest) -> OpenLimitsResult<OpenLimitsOrder> {
+        Ok(self.orders.lock().unwrap()[&request.id].clone())
+    }
+
+    async fn cancel_order(&self, request: &CancelOrderRequest) -> OpenLimitsResult<()> {
+        self.push(format!("cancel {}", request.id));
+        Ok(())
+    }
+
+    async fn cancel_all(&self, request: &CancelAllOrdersRequest) -> OpenLimitsResult<()> {
+        self.push(format!(
+            "cancel all {}",
+            request.market_pair.clone().unwrap_or_default()
+        ));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Client for Mock {
+    async fn server_time(&self) -> OpenLimitsResult<i64> {
+        Ok(0)
+    }
+
+    async fn exchange_info(&self) -> OpenLimitsResult<Vec<Symbol>> {
+        Ok(self.symbols.clone())
+    }
+
+    async fn all_prices(&self) -> OpenLimitsResult<Vec<SymbolPrice>> {
+        Ok(self.prices.clone())
+    }
+
+    async fn agg_trades(&self, request: &AggTradesRequest) -> OpenLimitsResult<Vec<AggTrade>> {
+        self.push(
+            match (request.from_id, request.start_time, request.end_time) {
+                (Some(from_id), _, _) => format!("agg trades {} from {}", request.symbol, from_id),
+                (None, Some(start), Some(end)) => {
+                    format!("agg trades {} {}..{}", request.symbol, start, end)
+                }
+                _ => format!("agg trades {}", request.symbol),
+            },
+        );
+
+        Ok(self
+            .agg_trades
+            .iter()
+            .filter(|trade| {
+                request.from_id.map_or(true, |id| trade.id >= id)
+                    && request
+                        .start_time
+                        .map_or(true, |start| trade.timestamp >= start)
+                    && request.end_time.map_or(true, |end| trade.timestamp <= end)
+            })
+            .take(request.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn order_book(&self, _request: &OrderBookRequest) -> OpenLimitsResult<OrderBookResponse> {
+        unimplemented!("order books aren't scripted")
+    }
+
+    /// Only the first connection streams trades.
+    async fn subscribe(
+        &self,
+        _subscriptions: &[BinanceSubscription],
+        _sandbox: bool,
+    ) -> OpenLimitsResult<Messages> {
+        let trades = self.trades.lock().unwrap().take().unwrap_or_default();
+        let price = self.price.clone();
+
+        Ok(stream::iter(trades)
+            .then(move |trade| {
+                let price = price.clone();
+                async move {
+                    sleep(Duration::from_secs(1)).await;
+                    *price.lock().unwrap() = trade.price;
+                    Ok(WebSocketResponse::Generic(
+                        OpenLimitsWebSocketMessage::Trades(vec![trade]),
+                    ))
+                }
+            })
+            .chain(stream::pending())
+            .boxed())
+    }
+
+    async fn oco_sell(
+        &self,
+        market: &Market,
+        quantity: Decimal,
+        price: Decimal,
+        stop_price: Decimal,
+        stop_limit_price: Decimal,
+    ) -> OpenLimitsResult<()> {
+        self.push(format!(
+            "oco sell {} {} at {} or {}/{}",
+            quantity, market, price, stop_limit_price, stop_price
+        ));
+        Ok(())
+    }
+}
@@ -1,594 +1,666 @@
-mod wallet;
-mod positions;
-
-pub use positions::Position;
-use positions::Positions;
-use super::{Exchange, Order, Strategy, Trade};
-use crate::{
-    loggers::{Logger, Database},
-    Error, Market, Number,
+mod client;
+#[cfg(test)]
+mod mock;
+
+pub use client::Client;
+
+use super::{
+    live::{adapter, AssetBalance, BookDiff, BookSnapshot, Exit, Rules, Venue, VenueOrder},
+    Entry, Fees, Pair, Trade,
 };
+use crate::{Error, Market};
 use async_trait::async_trait;
-use futures::{stream::BoxStream, StreamExt};
+use client::AggTradesRequest;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use openlimits::{
     binance::{
-        model::{websocket::BinanceSubscription, SymbolFilter},
-        Binance as OpenLimitsBinance, BinanceCredentials, BinanceParameters, BinanceWebsocket,
+        model::{
+            websocket::{BinanceSubscription, BinanceWebsocketMessage},
+            SymbolFilter, SymbolPrice,
+        },
+        Binance as OpenLimitsBinance, BinanceCredentials, BinanceParameters,
     },
-    exchange::{Exchange as OpenLimitsExchange, ExchangeAccount},
-    exchange_info::ExchangeInfoRetrieval,
-    exchange_ws::{ExchangeWs, OpenLimitsWs},
+    exchange::Exchange as OpenLimitsExchange,
     model::{
         websocket::{OpenLimitsWebSocketMessage, WebSocketResponse},
-        Side,
+        OrderBookRequest,
     },
-    model::{OpenMarketOrderRequest, OrderStatus, TimeInForce},
-    shared::Result as OpenLimitsResult,
 };
 use rust_decimal::prelude::*;
-use std::{
-    collections::HashMap,
-    sync::atomic::{AtomicU64, AtomicU8, Ordering},
-};
-use tokio::{
-    sync::{
-        mpsc::{self, UnboundedReceiver, UnboundedSender},
-    },
-    time::{sleep, timeout, Duration},
-};
-use wallet::Wallet;
-use chrono::Utc;
-
-#[derive(Debug)]
-struct FilteredOrder {
-    market: Market,
-    buy_price: Decimal,
-    take_profit_price: Decimal,
-    stop_price: Decimal,
-    stop_limit_price: Decimal,
-    quote_quantity: Decimal,
-}
-
-impl FilteredOrder {
-    #[cfg(feature = "stop-orders")]
-    async fn order(self, exchange: &OpenLimitsBinance) -> OpenLimitsResult<Option<Position>> {
-        Ok(Some(Position {
-            market: self.market,
-            quantity: self.quote_quantity,
-            buy_price: self.buy_price,
-            take_profit: self.take_profit_price,
-            stop_loss: self.stop_price,
-            profitable: None,
-            timestamp: Utc::now(),
-        }))
-    }
-
-    #[cfg(not(feature = "stop-orders"))]
-    async fn order(self, exchange: &OpenLimitsBinance) -> OpenLimitsResult<Option<Position>> {
-        log::info!("FilteredOrder: {:#?}", self);
-
-        Ok(if self.quote_quantity > Decimal::zero() {
-            log::info!("Placing entry order.");
-
-            let base_quantity = self.quote_quantity / self.buy_price;
 
-            let buy_order = exchange
-                .market_buy(&OpenMarketOrderRequest {
-                    market_pair: self.market.clone(),
-                    size: base_quantity * Decimal::new(999, 3),
-                    //price: self.buy_price,
-                    //time_in_force: TimeInForce::ImmediateOrCancelled,
-                    //post_only: false,
-                })
-                .await?;
-
-            log::info!("Placing entry order was successful!");
-
-            if buy_order.status == OrderStatus::Filled {
-                log::info!("Entry order was filled.");
-
-                let inner = exchange.inner_client().unwrap();
-                let pair = exchange.get_pair(&self.market).await?.read()?;
-
-                log::info!("Placing OCO order.");
-
-                inner
-                    .oco_sell(
-                        pair,
-                        buy_order.size,
-                        self.take_profit_price,
-                        self.stop_price,
-                        Some(self.stop_limit_price),
-                        Some(TimeInForce::GoodTillCancelled.into()),
-                    )
-                    .await?;
-
-                //let take_profit_id = result.order_reports.iter().filter(|order| order.type_name == "LIMIT_MAKER").map(|order| order.order_id).next().unwrap();
-                //let stop_loss_id = result.order_reports.iter().filter(|order| order.type_name == "STOP_LOSS_LIMIT").map(|order| order.order_id).next().unwrap();
+/// Binance spot, with exits placed as OCO orders. Requests go through openlimits unless another
+/// client is given.
+pub struct Binance<C = OpenLimitsBinance> {
+    sandbox: bool,
+    client: C,
+}
 
-                log::info!("Placing OCO order was successful!");
+impl Binance {
+    pub async fn new(sandbox: bool) -> Self {
+        log::info!("Connecting to exchange.");
 
-                Some(Position {
-                    market: self.market,
-                    quantity: buy_order.size,
-                    buy_price: self.buy_price,
-                    take_profit: self.take_profit_price,
-                    stop_loss: self.stop_limit_price,
-                    profitable: None,
-                    timestamp: Utc::now()
-                })
-            } else {
-                log::info!("Entry order was killed.");
-                None
-            }
-        } else {
-            log::info!("Balance not sufficient.");
-            None
+        let exchange = OpenLimitsBinance::new(BinanceParameters {
+            sandbox,
+            credentials: Some(BinanceCredentials {
+                api_key: std::env::var("BINANCE_API_KEY").expect("Couldn't get BINANCE_API_KEY."),
+                api_secret: std::env::var("BINANCE_API_SECRET")
+                    .expect("Couldn't get BINANCE_API_SECRET."),
+            }),
         })
+        .await
+        .expect("Failed to create Client");
+
+        Self::with_client(exchange, sandbox)
     }
 }
 
-#[derive(Debug)]
-pub enum FilterError {
-    MinQty,
-    MaxQty,
-    MinPrice,
-    MaxPrice,
-    MinNotional,
-    NoTickSize,
-}
+impl<C: Client> Binance<C> {
+    pub const FEE_ASSET: &'static str = "BNB";
+    /// Most aggregated trades a request returns.
+    const AGG_TRADES_LIMIT: usize = 1000;
+    /// Longest range of aggregated trades a request may ask for, the end must be less than an hour
+    /// after the start.
+    const AGG_TRADES_RANGE: i64 = 1000 * 60 * 60 - 1;
 
-struct Filters(Vec<SymbolFilter>);
-
-impl Filters {
-    fn apply(&self, order: Order, quantity: Decimal) -> Result<FilteredOrder, Error> {
-        let quantity = self.quantity(quantity)?;
-        let stop_limit_price = self.price(order.stop_loss.unwrap(), quantity)?;
-        let tick = self.tick_size()?;
-
-        Ok(FilteredOrder {
-            market: order.market,
-            buy_price: self.price(order.price, quantity)? + Decimal::new(2, 0) * tick,
-            take_profit_price: self.price(order.take_profit.unwrap(), quantity)?,
-            stop_price: stop_limit_price - Decimal::new(2, 0) * tick,
-            stop_limit_price,
-            quote_quantity: quantity,
-        })
+    pub fn with_client(client: C, sandbox: bool) -> Self {
+        Self { sandbox, client }
     }
 
-    fn tick_size(&self) -> Result<Decimal, Error> {
-        for filter in &self.0 {
-            match filter {
-                SymbolFilter::PriceFilter { tick_size, .. } => {
-                    return Ok(*tick_size);
-                }
-                _ => (),
-            }
-        }
-
-        Err(Error::Filter(FilterError::NoTickSize))
-    }
+    /// Lot size rules apply to both limit and market orders, so the strictest of them is kept.
+    fn rules(filters: Vec<SymbolFilter>) -> Rules {
+        let mut rules = Rules::default();
 
-    fn quantity(&self, mut quantity: Decimal) -> Result<Decimal, Error> {
-        for filter in &self.0 {
+        for filter in filters {
             match filter {
-                &SymbolFilter::LotSize {
+                SymbolFilter::PriceFilter {
+                    min_price,
+                    max_price,
+                    tick_size,
+                } if tick_size > Decimal::zero() => {
+                    rules.tick_size = Some(tick_size);
+                    rules.min_price = min_price;
+                    // A maximum of zero means there is none.
+                    rules.max_price = Some(max_price).filter(|max_price| !max_price.is_zero());
+                }
+                SymbolFilter::LotSize {
                     min_qty,
                     max_qty,
                     step_size,
-                } if step_size > Decimal::zero() => {
-                    if quantity < min_qty {
-                        return Err(Error::Filter(FilterError::MinQty));
-                    }
-                    if quantity > max_qty {
-                        return Err(Error::Filter(FilterError::MaxQty));
-                    }
-                    quantity = (quantity / step_size)
-                        .round_dp_with_strategy(0, RoundingStrategy::RoundDown)
-                        * step_size;
                 }
-                &SymbolFilter::MarketLotSize {
+                | SymbolFilter::MarketLotSize {
                     min_qty,
                     max_qty,
                     step_size,
                 } if step_size > Decimal::zero() => {
-                    if quantity < min_qty {
-                        return Err(Error::Filter(FilterError::MinQty));
-                    }
-                    if quantity > max_qty {
-                        return Err(Error::Filter(FilterError::MaxQty));
-                    }
-                    quantity = (quantity / step_size)
-                        .round_dp_with_strategy(0, RoundingStrategy::RoundDown)
-                        * step_size;
-                }
-                _ => (),
-            }
-        }
-
-        Ok(quantity)
-    }
-
-    fn price(&self, price: Number, quantity: Decimal) -> Result<Decimal, Error> {
-        let mut price = Decimal::from_f32(price).unwrap();
-
-        for filter in &self.0 {
-            match filter {
-                &SymbolFilter::PriceFilter {
-                    min_price,
-                    max_price,
-                    tick_size,
-                } if tick_size > Decimal::zero() => {
-                    if price < min_price {
-                        return Err(Error::Filter(FilterError::MinPrice));
-                    }
-                    if price > max_price {
-                        return Err(Error::Filter(FilterError::MaxPrice));
-                    }
-                    price = (price / tick_size).round() * tick_size;
+                    rules.step_size = Some(
+                        rules
+                            .step_size
+                            .map_or(step_size, |step| step.max(step_size)),
+                    );
+                    rules.min_quantity = rules.min_quantity.max(min_qty);
+                    rules.max_quantity =
+                        Some(rules.max_quantity.map_or(max_qty, |max| max.min(max_qty)));
                 }
                 SymbolFilter::MinNotional { min_notional } => {
-                    if price * quantity < *min_notional {
-                        return Err(Error::Filter(FilterError::MinNotional));
-                    }
+                    rules.min_notional = min_notional;
                 }
                 _ => (),
             }
         }
 
-        Ok(price)
+        rules
     }
 }
 
-pub struct Binance {
-    sandbox: bool,
-    wallet: Wallet,
-    positions: Positions,
-    markets: Vec<Market>,
-    exchange: OpenLimitsBinance,
-    //filters: HashMap<Market, Filters>,
-    consecutive_losses: AtomicU8,
-    wait_until: AtomicU64,
-    //start: u64,
-}
+#[async_trait]
+impl<C: Client> Venue for Binance<C> {
+    fn name(&self) -> &str {
+        "Binance"
+    }
 
-impl Binance {
-    async fn get_filters(&self) -> HashMap<Market, Filters> {
-        let inner = self.exchange.inner_client().unwrap();
-        let info = inner.get_exchange_info().await.unwrap();
-        info
-            .symbols
-            .into_iter()
-            .map(
-                |openlimits::binance::model::Symbol {
-                     symbol, filters, ..
-                 }| { (symbol, Filters(filters)) },
-            )
-            .collect()
+    fn is_sandbox(&self) -> bool {
+        self.sandbox
     }
 
-    pub async fn new(markets: &Vec<&str>, sandbox: bool) -> Self {
-        log::info!("Connecting to exchange.");
+    /// VIP tier 0, paid in BNB.
+    fn fees(&self) -> Fees {
+        Fees::default().paid_in_bnb()
+    }
 
-        let exchange = OpenLimitsBinance::new(BinanceParameters {
-            sandbox,
-            credentials: Some(BinanceCredentials {
-                api_key: std::env::var("BINANCE_API_KEY").expect("Couldn't get BINANCE_API_KEY."),
-                api_secret: std::env::var("BINANCE_API_SECRET")
-                    .expect("Couldn't get BINANCE_API_SECRET."),
-            }),
-        })
-        .await
-        .expect("Failed to create Client");
+    fn fee_asset(&self) -> Option<&str> {
+        Some(Self::FEE_ASSET)
+    }
+
+    fn stop_orders(&self) -> bool {
+        true
+    }
 
-        log::info!("Getting exchange info.");
+    async fn server_time(&self) -> Result<i64, Error> {
+        Ok(self.client.server_time().await?)
+    }
 
-        let inner = exchange.inner_client().unwrap();
-        let start = inner.get_server_time().await.unwrap().server_time;
-        //let info = inner.get_exchange_info().await.unwrap();
-        /*let filters = info
-            .symbols
+    async fn markets(&self) -> Result<Vec<(Pair, Rules)>, Error> {
+        Ok(self
+            .client
+            .exchange_info()
+            .await?
             .into_iter()
             .map(
                 |openlimits::binance::model::Symbol {
-                     symbol, filters, ..
-                 }| { (symbol, Filters(filters)) },
+                     symbol,
+                     base_asset,
+                     quote_asset,
+                     filters,
+                     ..
+                 }| {
+                    (
+                        Pair {
+                            market: symbol,
+                            base: base_asset,
+                            quote: quote_asset,
+                        },
+                        Self::rules(filters),
+                    )
+                },
             )
-            .collect();*/
-
-        let (logger, sender) = Database::new();
-        tokio::task::spawn(async move {
-            logger.run().await;
-        });
-
-        Self {
-            sandbox,
-            wallet: Wallet::new(),
-            positions: Positions::new(sender),
-            markets: markets.clone().into_iter().map(String::from).collect(),
-            exchange,
-            //filters,
-            consecutive_losses: AtomicU8::new(0),
-            wait_until: AtomicU64::new(start),
-            //start,
-        }
+            .collect())
     }
-}
 
-#[async_trait]
-impl<S: Strategy + 'static> Exchange<S> for Binance {
-    async fn run(mut self, strategy: &mut S) {
-        let (tx, rx) = mpsc::unbounded_channel();
+    async fn balances(&self) -> Result<Vec<AssetBalance>, Error> {
+        adapter::balances(&self.client).await
+    }
 
-        tokio::join!(self.produce_trades(tx), self.consume_trades(rx, strategy),);
+    /// Always returns the prices of all markets, they come in a single request.
+    async fn prices(&self, _markets: &[Market]) -> Result<Vec<(Market, Decimal)>, Error> {
+        Ok(self
+            .client
+            .all_prices()
+            .await?
+            .into_iter()
+            .map(|SymbolPrice { symbol, price }| (symbol, price))
+            .collect())
     }
-}
 
-impl Binance {
-    async fn connect_websocket(
+    async fn trades(
         &self,
-    ) -> OpenLimitsResult<
-        BoxStream<
-            'static,
-            OpenLimitsResult<WebSocketResponse<<BinanceWebsocket as ExchangeWs>::Response>>,
-        >,
-    > {
-        let subscriptions = self
-            .markets
+        markets: &[Market],
+    ) -> Result<BoxStream<'static, Result<Trade, Error>>, Error> {
+        let subscriptions = markets
             .iter()
-            .map(|symbol| BinanceSubscription::Trade(symbol.to_lowercase().to_string()))
+            .map(|symbol| BinanceSubscription::Trade(symbol.to_lowercase()))
             .collect::<Vec<BinanceSubscription>>();
 
-        //let inner = self.exchange.inner_client().unwrap();
-        //let user_data = inner.user_stream_start().await?;
+        Ok(self
+            .client
+            .subscribe(&subscriptions, self.sandbox)
+            .await?
+            .flat_map(|message| {
+                stream::iter(match message {
+                    Ok(WebSocketResponse::Generic(OpenLimitsWebSocketMessage::Trades(trades))) => {
+                        trades
+                            .into_iter()
+                            .map(|trade| Ok(adapter::trade(trade)))
+                            .collect()
+                    }
+                    Ok(_) => Vec::new(),
+                    Err(err) => vec![Err(err.into())],
+                })
+            })
+            .boxed())
+    }
+
+    /// Aggregated trades, requested an hour or a thousand trades at a time.
+    async fn trades_between(
+        &self,
+        market: &Market,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Trade>, Error> {
+        let mut trades = Vec::new();
+        let mut from_id = None;
+        // Both ends of a request are inclusive.
+        let mut start = from + 1;
+
+        // Ranges are requested until one fills a page, then the trades after the last one, since
+        // the rest of them may be in the same millisecond.
+        while from_id.is_some() || start < to {
+            let end = (start + Self::AGG_TRADES_RANGE).min(to - 1);
+            let page = self
+                .client
+                .agg_trades(&AggTradesRequest {
+                    symbol: market.clone(),
+                    from_id,
+                    // Trades from an ID on can't be limited in time.
+                    start_time: Some(start).filter(|_| from_id.is_none()),
+                    end_time: Some(end).filter(|_| from_id.is_none()),
+                    limit: Self::AGG_TRADES_LIMIT,
+                })
+                .await?;
 
-        //subscriptions.push(BinanceSubscription::UserData(user_data.listen_key.clone()));
+            let full = page.len() == Self::AGG_TRADES_LIMIT;
+            let next = page.last().map(|trade| trade.id + 1);
+            let mut after = false;
+            for trade in page {
+                if trade.timestamp >= to {
+                    after = true;
+                    break;
+                }
+                trades.push(Trade {
+                    market: market.clone(),
+                    // Taker sells are positive, like in the trade stream.
+                    quantity: if trade.buyer_is_maker {
+                        trade.quantity
+                    } else {
+                        -trade.quantity
+                    },
+                    price: trade.price,
+                    timestamp: trade.timestamp,
+                    venue: None,
+                });
+            }
 
-        let stream = OpenLimitsWs {
-            websocket: BinanceWebsocket::new(if self.sandbox {
-                BinanceParameters::sandbox()
+            if full && !after {
+                from_id = next;
+            } else if from_id.is_some() {
+                break;
             } else {
-                BinanceParameters::prod()
-            })
-            .await?,
+                start = end + 1;
+            }
         }
-        .create_stream(&subscriptions)
-        .await?;
 
-        Ok(stream)
+        Ok(trades)
     }
 
-    async fn produce_trades(&self, tx: UnboundedSender<Trade>) {
-        loop {
-            if let Ok(mut stream) = self.connect_websocket().await {
-                /*
-                tokio::select! {
-                    _ = async {
-                        let mut interval = tokio::time::interval(Duration::from_secs(60 * 30));
-                        loop {
-                            interval.tick().await;
-                            if let Err(err) = self.exchange.inner_client().unwrap().user_stream_keep_alive(&listen_key).await {
-                                println!("{}\n {:#?}", listen_key, err);
-                                break;
-                            }
-                        }
-                        log::warn!("User data stream timeout, trying to reconnect.");
-                    } => {}
-                    _ = async {
-                        */
-                log::info!("Trade stream started!");
-                while let Ok(Some(Ok(message))) = timeout(
-                    Duration::from_secs(if self.sandbox { 500 } else { 5 }),
-                    stream.next(),
-                )
-                .await
-                {
-                    match message {
-                        WebSocketResponse::Generic(OpenLimitsWebSocketMessage::Trades(trades)) => {
-                            for trade in trades {
-                                let market = trade.market_pair;
-                                let quantity = match trade.side {
-                                    Side::Buy => -trade.qty,
-                                    Side::Sell => trade.qty,
-                                };
-                                let price = trade.price;
-                                let timestamp = trade.created_at as i64;
-
-                                if let Some(profitable) = self.positions.check(&market, price).await
-                                {
-                                    if profitable {
-                                        log::info!("Last trade was profitable.");
-                                        self.consecutive_losses.store(0, Ordering::Relaxed);
-                                    } else {
-                                        log::info!("Last trade was unprofitable.");
-                                        self.consecutive_losses.fetch_add(1, Ordering::Relaxed);
-                                        if self.consecutive_losses.load(Ordering::Relaxed) >= 2 {
-                                            self.wait_until.store(
-                                                timestamp as u64 + 1000 * 60 * 60 * 24,
-                                                Ordering::Relaxed,
-                                            );
-                                            self.consecutive_losses.store(0, Ordering::Relaxed);
-                                        }
-                                    }
-                                }
-
-                                let trade = Trade {
-                                    market,
-                                    quantity: quantity.to_f32().unwrap(),
-                                    price: price.to_f32().unwrap(),
-                                    timestamp,
-                                };
-
-                                tx.send(trade).unwrap();
-                            }
-                        }
-                        /*
-                        WebSocketResponse::Raw(BinanceWebsocketMessage::UserOrderUpdate(
-                            UserOrderUpdate { trade_id, order_status, event_time, .. },
-                        )) => {
-                            if order_status == openlimits::binance::model::OrderStatus::Filled || order_status == openlimits::binance::model::OrderStatus::PartiallyFilled {
-                                if let Some(profitable) = self.positions.terminate(trade_id as u64).await {
-                                    if profitable {
-                                        log::info!("Last trade was profitable.");
-                                        self.consecutive_losses.store(0, Ordering::Relaxed);
-                                    } else {
-                                        log::info!("Last trade was unprofitable.");
-                                        self.consecutive_losses.fetch_add(1, Ordering::Relaxed);
-                                        if self.consecutive_losses.load(Ordering::Relaxed) >= 2 {
-                                            self.wait_until.store(event_time + 1000 * 60 * 60 * 12, Ordering::Relaxed);
-                                            self.consecutive_losses.store(0, Ordering::Relaxed);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        */
-                        _ => (),
+    async fn order_book(&self, market: &Market) -> Result<BookSnapshot, Error> {
+        let snapshot = self
+            .client
+            .order_book(&OrderBookRequest {
+                market_pair: market.clone(),
+            })
+            .await?;
+
+        Ok(BookSnapshot {
+            last_update_id: snapshot
+                .last_update_id
+                .ok_or(Error::Missing("last update ID"))?,
+            bids: adapter::levels(snapshot.bids),
+            asks: adapter::levels(snapshot.asks),
+        })
+    }
+
+    async fn depth(
+        &self,
+        market: &Market,
+    ) -> Result<BoxStream<'static, Result<BookDiff, Error>>, Error> {
+        // Depth updates don't contain the market, so every market gets its own stream.
+        let subscriptions = [BinanceSubscription::Depth(market.to_lowercase(), Some(100))];
+
+        Ok(self
+            .client
+            .subscribe(&subscriptions, self.sandbox)
+            .await?
+            .filter_map(|message| async move {
+                match message {
+                    // The raw depth event keeps its event time, the generic order book diff
+                    // doesn't.
+                    Ok(WebSocketResponse::Raw(BinanceWebsocketMessage::Depth(depth))) => {
+                        Some(Ok(BookDiff {
+                            first_update_id: depth.first_update_id,
+                            last_update_id: depth.final_update_id,
+                            timestamp: depth.event_time as i64,
+                            bids: adapter::levels(depth.bids),
+                            asks: adapter::levels(depth.asks),
+                        }))
                     }
+                    Ok(_) => None,
+                    Err(err) => Some(Err(err.into())),
                 }
-                log::warn!("Message timeout, trying to reconnect.");
-                //} => {}
-                //}
-            } else {
-                log::warn!("Unable to reach websocket, trying to reconnect.");
-                sleep(Duration::from_secs(5)).await;
-            }
-        }
+            })
+            .boxed())
     }
 
-    async fn consume_trades<S: Strategy + 'static>(
+    async fn buy(
         &self,
-        mut rx: UnboundedReceiver<Trade>,
-        strategy: &mut S,
-    ) {
-        while let Some(trade) = rx.recv().await {
-            log::trace!("Receiving trade: {:?}", trade);
-
-            let timestamp = trade.timestamp;
-
-            self.wallet
-                .update_price(
-                    trade.market.clone(),
-                    Decimal::from_f32(trade.price).unwrap(),
-                )
-                .await;
-
-            if let Some(order) = strategy.run(trade) {
-                log::info!("Processing order.");
-                //if timestamp as u64 >= self.start + 1000 * 60 * 60 * 4 {
-                if let Err(err) = self.order(order, timestamp as u64).await {
-                    log::error!("Error occured during order: {:#?}", err);
-                }
-                //} else {
-                //    log::warn!("Too early to order something!");
-                //}
-            }
-        }
+        market: &Market,
+        size: Decimal,
+        price: Decimal,
+        entry: Entry,
+    ) -> Result<VenueOrder, Error> {
+        adapter::buy(&self.client, market, size, price, entry).await
     }
 
-    async fn order(&self, order: Order, timestamp: u64) -> Result<(), Error> {
-        log::info!("Requesting order {}.", order);
-
-        //let filters = self.filters.get(&order.market).unwrap();
-        //filters.apply(order, quantity).unwrap();
-
-        self.get_filters().await;
-        self.wallet.update(&self.exchange).await?;
-        log::trace!("Wallet: {:#?}", self.wallet);
-        let pair = self.exchange.get_pair(&order.market).await?.read()?;
-
-        let base_quantity = self.wallet.value(pair.base.clone()).await;
-        let not_invested = (pair.base == Wallet::FEE_ASSET && base_quantity < Decimal::new(60, 0))
-            || base_quantity < Decimal::new(10, 0);
-        if not_invested {
-            if self.wait_until.load(Ordering::Relaxed) < timestamp {
-                let total = self.wallet.total_value().await;
-                let want =
-                    (total - Decimal::new(50, 0)) / Decimal::new(2, 0);
-                let available = self.wallet.value(Wallet::QUOTE_ASSET).await;
-                    log::info!("Total value is {}, want {}, available {}", total, want, available);
-                let quantity = determine_investment_amount(
-                    want,
-                    available,
-                ) * Decimal::new(99, 2);
-                log::info!("Placing order of size {}", quantity);
-                let filtered_order = self.get_filters()
-                    .await
-                    .get(&order.market)
-                    .unwrap()
-                    .apply(order, quantity)?;
-
-                if let Some(position) = filtered_order.order(&self.exchange).await? {
-                    self.positions.open(position).await;
-                }
-            } else {
-                log::warn!("Backoff still in place.")
-            }
-        } else {
-            log::info!("Already invested in this asset.");
-        }
+    async fn market_sell(&self, market: &Market, size: Decimal) -> Result<VenueOrder, Error> {
+        adapter::market_sell(&self.client, market, size).await
+    }
 
-        Ok(())
+    async fn get_order(&self, order: &VenueOrder) -> Result<VenueOrder, Error> {
+        adapter::get_order(&self.client, order).await
     }
-}
 
-fn determine_investment_amount(want: Decimal, available: Decimal) -> Decimal {
-    assert!(want > Decimal::zero());
-
-    let fraction_investment = available / want;
-    if fraction_investment >= Decimal::new(2, 0) {
-        want
-    } else if fraction_investment >= Decimal::new(1, 0) {
-        available
-    } else if fraction_investment >= Decimal::new(5, 1) {
-        available
-    } else {
-        Decimal::zero()
+    async fn cancel_order(&self, order: &VenueOrder) -> Result<(), Error> {
+        adapter::cancel_order(&self.client, order).await
+    }
+
+    async fn cancel_all(&self, market: &Market) -> Result<(), Error> {
+        adapter::cancel_all(&self.client, market).await
+    }
+
+    async fn place_exit(&self, exit: &Exit) -> Result<(), Error> {
+        self.client
+            .oco_sell(
+                &exit.market,
+                exit.quantity,
+                exit.take_profit,
+                exit.stop_price,
+                exit.stop_limit_price,
+            )
+            .await?;
+
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rust_decimal::Decimal;
+    use crate::{Account, Exchange, Intent, Live, MarketEvent, Order, Side, Strategy};
+    use mock::Mock;
+    use openlimits::model::{
+        Order as OpenLimitsOrder, OrderStatus, OrderType, Side as OpenLimitsSide,
+    };
+    use std::fmt;
+    use tokio::{
+        sync::mpsc,
+        time::{timeout, Duration},
+    };
+
+    /// BTCUSDT as the `exchangeInfo` endpoint lists it.
+    const EXCHANGE_INFO: &str = r#"{
+        "timezone": "UTC",
+        "serverTime": 1620000000000,
+        "rateLimits": [],
+        "exchangeFilters": [],
+        "symbols": [{
+            "symbol": "BTCUSDT",
+            "status": "TRADING",
+            "baseAsset": "BTC",
+            "baseAssetPrecision": 8,
+            "quoteAsset": "USDT",
+            "quotePrecision": 8,
+            "quoteAssetPrecision": 8,
+            "baseCommissionPrecision": 8,
+            "quoteCommissionPrecision": 8,
+            "orderTypes": ["LIMIT", "LIMIT_MAKER", "MARKET", "STOP_LOSS_LIMIT", "TAKE_PROFIT_LIMIT"],
+            "icebergAllowed": true,
+            "ocoAllowed": true,
+            "quoteOrderQtyMarketAllowed": true,
+            "isSpotTradingAllowed": true,
+            "isMarginTradingAllowed": true,
+            "filters": [
+                {
+                    "filterType": "PRICE_FILTER",
+                    "minPrice": "0.01000000",
+                    "maxPrice": "1000000.00000000",
+                    "tickSize": "0.01000000"
+                },
+                {
+                    "filterType": "LOT_SIZE",
+                    "minQty": "0.00100000",
+                    "maxQty": "9000.00000000",
+                    "stepSize": "0.00100000"
+                },
+                {
+                    "filterType": "MIN_NOTIONAL",
+                    "minNotional": "10.00000000",
+                    "applyToMarket": true,
+                    "avgPriceMins": 5
+                },
+                {
+                    "filterType": "MARKET_LOT_SIZE",
+                    "minQty": "0.00000000",
+                    "maxQty": "100.00000000",
+                    "stepSize": "0.00000000"
+                }
+            ],
+            "permissions": ["SPOT", "MARGIN"]
+        }]
+    }"#;
+
+    /// Buys on the first trade with a take profit 20% and a stop loss 10% away, and records the
+    /// trades, fills and closed positions it is told about.
+    #[derive(Default)]
+    struct Once {
+        placed: bool,
+        log: Vec<String>,
+    }
 
-    #[test]
-    fn investment_amount() {
-        assert_eq!(
-            determine_investment_amount(Decimal::new(1, 0), Decimal::new(2, 0)),
-            Decimal::new(1, 0)
-        );
-        assert_eq!(
-            determine_investment_amount(Decimal::new(10, 0), Decimal::new(1, 0)),
-            Decimal::new(0, 0)
-        );
-        assert_eq!(
-            determine_investment_amount(Decimal::new(1, 0), Decimal::new(1, 0)),
-            Decimal::new(1, 0)
-        );
-        assert_eq!(
-            determine_investment_amount(Decimal::new(15, 0), Decimal::new(20, 0)),
-            Decimal::new(20, 0)
-        );
-        assert_eq!(
-            determine_investment_amount(Decimal::new(75, 0), Decimal::new(160, 0)),
-            Decimal::new(75, 0)
-        );
+    impl Strategy for Once {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            None
+        }
+
+        fn event(&mut self, event: MarketEvent, _account: &Account) -> Vec<Intent> {
+            match event {
+                MarketEvent::Trade(trade) => {
+                    self.log.push(format!(
+                        "{} {} at {}: {}",
+                        trade.market, trade.price, trade.timestamp, trade.quantity
+                    ));
+                    if self.placed {
+                        return Vec::new();
+                    }
+                    self.placed = true;
+                    vec![Intent::Place(Order {
+                        market: trade.market,
+                        price: trade.price,
+                        take_profit: Some(trade.price * Decimal::new(12, 1)),
+                        stop_loss: Some(trade.price * Decimal::new(9, 1)),
+                        tranches: Vec::new(),
+                        size: None,
+                        side: Side::Buy,
+                    })]
+                }
+                MarketEvent::OrderFilled(fill) => {
+                    self.log
+                        .push(format!("filled {} at {}", fill.market, fill.price));
+                    Vec::new()
+                }
+                MarketEvent::PositionClosed(position) => {
+                    self.log.push(format!(
+                        "closed {} at {}",
+                        position.market, position.sell_price
+                    ));
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            }
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Once {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "once")
+        }
+    }
+
+    fn order(id: &str, status: OrderStatus, remaining: Option<i64>) -> OpenLimitsOrder {
+        OpenLimitsOrder {
+            id: id.to_owned(),
+            market_pair: "BTCUSDT".to_owned(),
+            client_order_id: None,
+            created_at: None,
+            order_type: OrderType::Limit,
+            side: OpenLimitsSide::Buy,
+            status,
+            size: Decimal::new(2, 0),
+            price: Some(Decimal::new(100, 0)),
+            remaining: remaining.map(|remaining| Decimal::new(remaining, 1)),
+            trades: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn markets() {
+        let venue = Binance::with_client(Mock::new(EXCHANGE_INFO), false);
+
+        let markets = venue.markets().await.unwrap();
+
+        assert_eq!(markets.len(), 1);
+        let (pair, rules) = &markets[0];
         assert_eq!(
-            determine_investment_amount(Decimal::new(70, 0), Decimal::new(50, 0)),
-            Decimal::new(50, 0)
+            (
+                pair.market.as_str(),
+                pair.base.as_str(),
+                pair.quote.as_str()
+            ),
+            ("BTCUSDT", "BTC", "USDT")
         );
+        assert_eq!(rules.tick_size, Some(Decimal::new(1, 2)));
+        assert_eq!(rules.max_price, Some(Decimal::new(1000000, 0)));
+        assert_eq!(rules.step_size, Some(Decimal::new(1, 3)));
+        assert_eq!(rules.min_quantity, Decimal::new(1, 3));
+        assert_eq!(rules.max_quantity, Some(Decimal::new(9000, 0)));
+        assert_eq!(rules.min_notional, Decimal::new(10, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn live() {
+        let client = Mock::new(EXCHANGE_INFO)
+            .with_price("BTCUSDT", 100)
+            .with_balance("USDT", 1000)
+            .with_trades("BTCUSDT", &[100, 110, 121]);
+        let log = client.log();
+        let (sender, _rx) = mpsc::unbounded_channel();
+        let live = Live::with_sender(
+            Binance::with_client(client, true),
+            &["BTCUSDT"],
+            sender.into(),
+        )
+        .await;
+        let mut strategy = Once::default();
+
+        let _ = timeout(Duration::from_secs(60), live.run(&mut strategy)).await;
+
+        // Taker buys are negative, and the exit is a single OCO order.
         assert_eq!(
-            determine_investment_amount(Decimal::new(80, 0), Decimal::new(230, 0)),
-            Decimal::new(80, 0)
+            *log.lock().unwrap(),
+            vec![
+                "market buy 3.95604000000 BTCUSDT",
+                "oco sell 3.95600000 BTCUSDT at 120.00000000 or 90.00000000/89.98000000",
+            ]
         );
         assert_eq!(
-            determine_investment_amount(Decimal::new(80, 0), Decimal::new(160, 0)),
-            Decimal::new(80, 0)
+            strategy.log,
+            vec![
+                "BTCUSDT 100 at 1000: -1",
+                "filled BTCUSDT at 100.02000000",
+                "BTCUSDT 110 at 2000: 1",
+                "BTCUSDT 121 at 3000: -1",
+                "closed BTCUSDT at 121",
+            ]
         );
+    }
+
+    #[tokio::test]
+    async fn trades_between() {
+        let hour = 1000 * 60 * 60;
+        let agg_trade = |id: u64, timestamp: i64| {
+            format!(
+                r#"{{"a":{},"p":"100.00000000","q":"0.50000000","f":{},"l":{},"T":{},"m":{},"M":true}}"#,
+                id,
+                id,
+                id,
+                timestamp,
+                id % 2 == 0
+            )
+        };
+        // The first hour has no trades, the second more than a page in the same millisecond.
+        let agg_trades = (0..1001)
+            .map(|id| agg_trade(id, hour + 5))
+            .chain(vec![agg_trade(1001, hour * 3)])
+            .collect::<Vec<_>>()
+            .join(",");
+        let client = Mock::new(EXCHANGE_INFO).with_agg_trades(&format!("[{}]", agg_trades));
+        let log = client.log();
+        let venue = Binance::with_client(client, false);
+
+        let trades = venue
+            .trades_between(&"BTCUSDT".to_owned(), 0, hour * 3)
+            .await
+            .unwrap();
+
         assert_eq!(
-            determine_investment_amount(Decimal::new(80, 0), Decimal::new(40, 0)),
-            Decimal::new(40, 0)
+            *log.lock().unwrap(),
+            vec![
+                format!("agg trades BTCUSDT 1..{}", hour),
+                format!("agg trades BTCUSDT {}..{}", hour + 1, hour * 2),
+                "agg trades BTCUSDT from 1000".to_owned(),
+            ]
         );
+        // The trade at the end of the range is left out. Taker sells are positive.
+        assert_eq!(trades.len(), 1001);
+        assert_eq!(trades[0].quantity, Decimal::new(5, 1));
+        assert_eq!(trades[1].quantity, Decimal::new(-5, 1));
+        assert_eq!(trades[1000].timestamp, hour + 5);
+    }
+
+    #[tokio::test]
+    async fn orders() {
+        let client = Mock::new(EXCHANGE_INFO)
+            .with_order(order("1", OrderStatus::PartiallyFilled, Some(5)))
+            .with_order(order("2", OrderStatus::Filled, None));
+        let venue = Binance::with_client(client, false);
+        let placed = |id: &str| VenueOrder {
+            id: id.to_owned(),
+            market: "BTCUSDT".to_owned(),
+            size: Decimal::new(2, 0),
+            filled: Decimal::zero(),
+            price: None,
+            open: true,
+        };
+
+        let partial = venue.get_order(&placed("1")).await.unwrap();
+        assert_eq!((partial.filled, partial.open), (Decimal::new(15, 1), true));
+        assert_eq!(partial.price, Some(Decimal::new(100, 0)));
+        // Filled orders may not report what remains.
+        let filled = venue.get_order(&placed("2")).await.unwrap();
+        assert_eq!((filled.filled, filled.open), (Decimal::new(2, 0), false));
+    }
+
+    #[test]
+    fn rules() {
+        let rules = Binance::<Mock>::rules(vec![
+            SymbolFilter::PriceFilter {
+                min_price: Decimal::new(1, 2),
+                max_price: Decimal::zero(),
+                tick_size: Decimal::new(1, 2),
+            },
+            SymbolFilter::LotSize {
+                min_qty: Decimal::new(1, 5),
+                max_qty: Decimal::new(9000, 0),
+                step_size: Decimal::new(1, 5),
+            },
+            SymbolFilter::MarketLotSize {
+                min_qty: Decimal::zero(),
+                max_qty: Decimal::new(100, 0),
+                step_size: Decimal::zero(),
+            },
+            SymbolFilter::MinNotional {
+                min_notional: Decimal::new(10, 0),
+            },
+        ]);
+
+        assert_eq!(rules.tick_size, Some(Decimal::new(1, 2)));
+        assert_eq!(rules.max_price, None);
+        assert_eq!(rules.step_size, Some(Decimal::new(1, 5)));
+        // Lot sizes without a step size don't apply.
+        assert_eq!(rules.max_quantity, Some(Decimal::new(9000, 0)));
+        assert_eq!(rules.min_notional, Decimal::new(10, 0));
     }
 }
@@ -0,0 +1,302 @@
+use super::Side;
+use crate::Market;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+/// The top levels of an order book at a point in time, best prices first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookUpdate {
+    pub market: Market,
+    pub timestamp: i64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+impl BookUpdate {
+    pub fn best_bid(&self) -> Option<&Level> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&Level> {
+        self.asks.first()
+    }
+
+    pub fn mid(&self) -> Option<Decimal> {
+        Some((self.best_bid()?.price + self.best_ask()?.price) / Decimal::new(2, 0))
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    /// The spread relative to the mid price.
+    pub fn relative_spread(&self) -> Option<Decimal> {
+        let mid = self.mid()?;
+        if mid.is_zero() {
+            None
+        } else {
+            Some(self.spread()? / mid)
+        }
+    }
+
+    /// Average price of an immediate fill of `quantity` base asset against the visible depth, or
+    /// `None` if the visible depth isn't sufficient.
+    pub fn fill_price(&self, side: Side, quantity: Decimal) -> Option<Decimal> {
+        let levels = match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        };
+
+        let mut remaining = quantity;
+        let mut cost = Decimal::zero();
+        for level in levels {
+            let filled = remaining.min(level.quantity);
+            cost += filled * level.price;
+            remaining -= filled;
+            if remaining.is_zero() {
+                return Some(cost / quantity);
+            }
+        }
+
+        None
+    }
+
+    /// Estimated slippage of an immediate fill relative to the best price, as a fraction.
+    pub fn slippage(&self, side: Side, quantity: Decimal) -> Option<Decimal> {
+        let best = match side {
+            Side::Buy => self.best_ask()?.price,
+            Side::Sell => self.best_bid()?.price,
+        };
+
+        Some((self.fill_price(side, quantity)? - best).abs() / best)
+    }
+}
+
+#[derive(Debug)]
+pub struct BookGap {
+    pub expected: u64,
+    pub received: u64,
+}
+
+/// Local order book, built from a snapshot and kept up to date with incremental updates.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    market: Market,
+    last_update_id: Option<u64>,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    pub fn new(market: Market) -> Self {
+        Self {
+            market,
+            last_update_id: None,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.last_update_id.is_some()
+    }
+
+    pub fn snapshot(&mut self, last_update_id: u64, bids: Vec<Level>, asks: Vec<Level>) {
+        self.bids.clear();
+        self.asks.clear();
+        Self::apply_levels(&mut self.bids, bids);
+        Self::apply_levels(&mut self.asks, asks);
+        self.last_update_id = Some(last_update_id);
+    }
+
+    /// Applies an update covering the update IDs `first_update_id` to `final_update_id`.
+    /// Updates that are already contained in the book are ignored. If updates were missed, the
+    /// book is out of sync and has to be rebuilt from a new snapshot.
+    pub fn update(
+        &mut self,
+        first_update_id: u64,
+        final_update_id: u64,
+        bids: Vec<Level>,
+        asks: Vec<Level>,
+    ) -> Result<(), BookGap> {
+        let last_update_id = match self.last_update_id {
+            Some(last_update_id) => last_update_id,
+            None => {
+                return Err(BookGap {
+                    expected: 0,
+                    received: first_update_id,
+                })
+            }
+        };
+
+        if final_update_id <= last_update_id {
+            return Ok(());
+        }
+        if first_update_id > last_update_id + 1 {
+            self.last_update_id = None;
+            return Err(BookGap {
+                expected: last_update_id + 1,
+                received: first_update_id,
+            });
+        }
+
+        Self::apply_levels(&mut self.bids, bids);
+        Self::apply_levels(&mut self.asks, asks);
+        self.last_update_id = Some(final_update_id);
+
+        Ok(())
+    }
+
+    pub fn top(&self, levels: usize, timestamp: i64) -> BookUpdate {
+        BookUpdate {
+            market: self.market.clone(),
+            timestamp,
+            bids: self
+                .bids
+                .iter()
+                .rev()
+                .take(levels)
+                .map(|(&price, &quantity)| Level { price, quantity })
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(&price, &quantity)| Level { price, quantity })
+                .collect(),
+        }
+    }
+
+    fn apply_levels(side: &mut BTreeMap<Decimal, Decimal>, levels: Vec<Level>) {
+        for Level { price, quantity } in levels {
+            if quantity.is_zero() {
+                side.remove(&price);
+            } else {
+                side.insert(price, quantity);
+            }
+        }
+    }
+}
+
+/// Appends book updates to a file, so they can be replayed later.
+pub struct BookRecorder(BufWriter<File>);
+
+impl BookRecorder {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self(BufWriter::new(file)))
+    }
+
+    pub fn record(&mut self, update: &BookUpdate) -> io::Result<()> {
+        bincode::serialize_into(&mut self.0, update)
+            .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+        self.0.flush()
+    }
+}
+
+/// Reads all book updates a `BookRecorder` has written.
+pub fn read_book_updates<P: AsRef<Path>>(path: P) -> io::Result<Vec<BookUpdate>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut updates = Vec::new();
+
+    loop {
+        match bincode::deserialize_from(&mut reader) {
+            Ok(update) => updates.push(update),
+            Err(err) => match *err {
+                bincode::ErrorKind::Io(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                err => return Err(io::Error::new(ErrorKind::InvalidData, err)),
+            },
+        }
+    }
+
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: i64, quantity: i64) -> Level {
+        Level {
+            price: Decimal::new(price, 0),
+            quantity: Decimal::new(quantity, 0),
+        }
+    }
+
+    #[test]
+    fn update() {
+        let mut book = OrderBook::new("BTCUSDT".to_owned());
+        assert!(book.update(1, 2, Vec::new(), Vec::new()).is_err());
+
+        book.snapshot(10, vec![level(99, 1), level(98, 2)], vec![level(101, 1)]);
+        // Already contained in the snapshot.
+        book.update(5, 10, vec![level(99, 0)], Vec::new()).unwrap();
+        book.update(9, 11, vec![level(100, 3)], vec![level(101, 0), level(102, 5)])
+            .unwrap();
+
+        let top = book.top(5, 0);
+        assert_eq!(top.bids, vec![level(100, 3), level(99, 1), level(98, 2)]);
+        assert_eq!(top.asks, vec![level(102, 5)]);
+        assert_eq!(top.spread(), Some(Decimal::new(2, 0)));
+        assert_eq!(top.mid(), Some(Decimal::new(101, 0)));
+
+        assert!(book.update(13, 14, Vec::new(), Vec::new()).is_err());
+        assert!(!book.is_synced());
+    }
+
+    #[test]
+    fn fill_price() {
+        let update = BookUpdate {
+            market: "BTCUSDT".to_owned(),
+            timestamp: 0,
+            bids: vec![level(99, 1)],
+            asks: vec![level(100, 1), level(110, 1)],
+        };
+
+        assert_eq!(
+            update.fill_price(Side::Buy, Decimal::new(1, 0)),
+            Some(Decimal::new(100, 0))
+        );
+        assert_eq!(
+            update.fill_price(Side::Buy, Decimal::new(2, 0)),
+            Some(Decimal::new(105, 0))
+        );
+        assert_eq!(
+            update.slippage(Side::Buy, Decimal::new(2, 0)),
+            Some(Decimal::new(5, 2))
+        );
+        assert_eq!(update.fill_price(Side::Sell, Decimal::new(2, 0)), None);
+    }
+
+    #[test]
+    fn record() {
+        let path = std::env::temp_dir().join(format!("book-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut book = OrderBook::new("ETHUSDT".to_owned());
+        book.snapshot(1, vec![level(10, 1)], vec![level(11, 1)]);
+
+        let mut recorder = BookRecorder::new(&path).unwrap();
+        recorder.record(&book.top(10, 1000)).unwrap();
+        recorder.record(&book.top(10, 2000)).unwrap();
+
+        let updates = read_book_updates(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[1].timestamp, 2000);
+        assert_eq!(updates[1].asks, vec![level(11, 1)]);
+    }
+}
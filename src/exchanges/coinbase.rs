@@ -0,0 +1,224 @@
+use super::{
+    live::{adapter, AssetBalance, Exit, Rules, Venue, VenueOrder},
+    Entry, Fees, Pair, Trade,
+};
+use crate::{Error, Market};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use openlimits::{
+    coinbase::{
+        model::Product, Coinbase as OpenLimitsCoinbase, CoinbaseCredentials, CoinbaseParameters,
+        CoinbaseWebsocket,
+    },
+    exchange::{Exchange as OpenLimitsExchange, ExchangeAccount, ExchangeMarketData},
+    exchange_ws::OpenLimitsWs,
+    model::{
+        websocket::{OpenLimitsWebSocketMessage, Subscription, WebSocketResponse},
+        GetPriceTickerRequest, OpenLimitOrderRequest, TimeInForce,
+    },
+};
+use rust_decimal::prelude::*;
+
+/// Coinbase Pro. It has no OCO orders, so only take profits rest in the book and stop losses are
+/// sold at market once a trade reaches them. Markets are named like `BTC-USD`, so balances are
+/// valued in USD.
+pub struct Coinbase {
+    sandbox: bool,
+    exchange: OpenLimitsCoinbase,
+}
+
+impl Coinbase {
+    /// Most ticker requests in flight at once, the public endpoints allow a few per second.
+    const TICKER_REQUESTS: usize = 3;
+
+    pub async fn new(sandbox: bool) -> Self {
+        log::info!("Connecting to exchange.");
+
+        let exchange = OpenLimitsCoinbase::new(CoinbaseParameters {
+            sandbox,
+            credentials: Some(CoinbaseCredentials {
+                api_key: std::env::var("COINBASE_API_KEY").expect("Couldn't get COINBASE_API_KEY."),
+                api_secret: std::env::var("COINBASE_API_SECRET")
+                    .expect("Couldn't get COINBASE_API_SECRET."),
+                passphrase: std::env::var("COINBASE_PASSPHRASE")
+                    .expect("Couldn't get COINBASE_PASSPHRASE."),
+            }),
+        })
+        .await
+        .expect("Failed to create Client");
+
+        Self { sandbox, exchange }
+    }
+
+    fn rules(product: &Product) -> Rules {
+        Rules {
+            tick_size: Some(product.quote_increment),
+            min_price: Decimal::zero(),
+            max_price: None,
+            step_size: Some(product.base_increment),
+            min_quantity: product.base_min_size,
+            max_quantity: Some(product.base_max_size),
+            min_notional: product.min_market_funds,
+        }
+    }
+}
+
+#[async_trait]
+impl Venue for Coinbase {
+    fn name(&self) -> &str {
+        "Coinbase"
+    }
+
+    fn is_sandbox(&self) -> bool {
+        self.sandbox
+    }
+
+    /// The lowest tier, 0.4% maker and 0.6% taker.
+    fn fees(&self) -> Fees {
+        Fees::new(Decimal::new(4, 3), Decimal::new(6, 3))
+    }
+
+    fn stop_orders(&self) -> bool {
+        false
+    }
+
+    fn valuation(&self) -> &str {
+        "USD"
+    }
+
+    /// The server time isn't exposed, so the local clock is used instead.
+    async fn server_time(&self) -> Result<i64, Error> {
+        Ok(Utc::now().timestamp_millis())
+    }
+
+    async fn markets(&self) -> Result<Vec<(Pair, Rules)>, Error> {
+        let inner = self.exchange.inner_client().unwrap();
+        Ok(inner
+            .products()
+            .await?
+            .into_iter()
+            .filter(|product| !product.trading_disabled)
+            .map(|product| {
+                let rules = Self::rules(&product);
+                (
+                    Pair {
+                        market: product.id,
+                        base: product.base_currency,
+                        quote: product.quote_currency,
+                    },
+                    rules,
+                )
+            })
+            .collect())
+    }
+
+    async fn balances(&self) -> Result<Vec<AssetBalance>, Error> {
+        adapter::balances(&self.exchange).await
+    }
+
+    /// One ticker request per market, a few at a time. Markets without a price or whose request
+    /// failed are left out.
+    async fn prices(&self, markets: &[Market]) -> Result<Vec<(Market, Decimal)>, Error> {
+        Ok(stream::iter(markets)
+            .map(|market| async move {
+                let ticker = self
+                    .exchange
+                    .get_price_ticker(&GetPriceTickerRequest {
+                        market_pair: market.clone(),
+                    })
+                    .await;
+                (market, ticker)
+            })
+            .buffer_unordered(Self::TICKER_REQUESTS)
+            .filter_map(|(market, ticker)| async move {
+                match ticker {
+                    Ok(ticker) => ticker.price.map(|price| (market.clone(), price)),
+                    Err(err) => {
+                        log::warn!("Couldn't get the price of {}: {:?}", market, err);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await)
+    }
+
+    async fn trades(
+        &self,
+        markets: &[Market],
+    ) -> Result<BoxStream<'static, Result<Trade, Error>>, Error> {
+        let subscriptions = markets
+            .iter()
+            .map(|market| Subscription::Trades(market.clone()))
+            .collect::<Vec<Subscription>>();
+
+        Ok(OpenLimitsWs {
+            websocket: CoinbaseWebsocket::new(if self.sandbox {
+                CoinbaseParameters::sandbox()
+            } else {
+                CoinbaseParameters::prod()
+            })
+            .await?,
+        }
+        .create_stream(&subscriptions)
+        .await?
+        .flat_map(|message| {
+            stream::iter(match message {
+                Ok(WebSocketResponse::Generic(OpenLimitsWebSocketMessage::Trades(trades))) => {
+                    trades
+                        .into_iter()
+                        .map(|trade| Ok(adapter::trade(trade)))
+                        .collect()
+                }
+                Ok(_) => Vec::new(),
+                Err(err) => vec![Err(err.into())],
+            })
+        })
+        .boxed())
+    }
+
+    async fn buy(
+        &self,
+        market: &Market,
+        size: Decimal,
+        price: Decimal,
+        entry: Entry,
+    ) -> Result<VenueOrder, Error> {
+        adapter::buy(&self.exchange, market, size, price, entry).await
+    }
+
+    async fn market_sell(&self, market: &Market, size: Decimal) -> Result<VenueOrder, Error> {
+        adapter::market_sell(&self.exchange, market, size).await
+    }
+
+    async fn get_order(&self, order: &VenueOrder) -> Result<VenueOrder, Error> {
+        adapter::get_order(&self.exchange, order).await
+    }
+
+    async fn cancel_order(&self, order: &VenueOrder) -> Result<(), Error> {
+        adapter::cancel_order(&self.exchange, order).await
+    }
+
+    async fn cancel_all(&self, market: &Market) -> Result<(), Error> {
+        adapter::cancel_all(&self.exchange, market).await
+    }
+
+    /// Only the take profit is placed, the stop loss is watched by `Live`.
+    async fn place_exit(&self, exit: &Exit) -> Result<(), Error> {
+        self.exchange
+            .limit_sell(&OpenLimitOrderRequest {
+                market_pair: exit.market.clone(),
+                size: exit.quantity,
+                price: exit.take_profit,
+                time_in_force: TimeInForce::GoodTillCancelled,
+                post_only: false,
+            })
+            .await?;
+
+        Ok(())
+    }
+}
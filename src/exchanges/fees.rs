@@ -0,0 +1,119 @@
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Whether an order added liquidity to the book or took it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// Maker and taker rates of a Binance spot account, 0.001 is 0.1%.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fees {
+    maker: Decimal,
+    taker: Decimal,
+    /// Fraction taken off the fees when they are paid in BNB.
+    discount: Decimal,
+}
+
+impl Fees {
+    /// Maker and taker rates in millionths, by VIP tier.
+    const VIP: [(i64, i64); 10] = [
+        (1000, 1000),
+        (900, 1000),
+        (800, 1000),
+        (700, 1000),
+        (700, 900),
+        (600, 800),
+        (500, 700),
+        (400, 600),
+        (300, 500),
+        (200, 400),
+    ];
+
+    pub fn new(maker: Decimal, taker: Decimal) -> Self {
+        Self {
+            maker,
+            taker,
+            discount: Decimal::zero(),
+        }
+    }
+
+    /// The rates of a VIP tier from 0 to 9, higher tiers are capped at 9.
+    pub fn vip(tier: usize) -> Self {
+        let (maker, taker) = Self::VIP[tier.min(Self::VIP.len() - 1)];
+        Self::new(Decimal::new(maker, 6), Decimal::new(taker, 6))
+    }
+
+    /// Pays fees in BNB, which takes `discount` off them, 0.25 is 25%.
+    pub fn with_discount(mut self, discount: Decimal) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    /// Pays fees in BNB at Binance's standard discount of 25%.
+    pub fn paid_in_bnb(self) -> Self {
+        self.with_discount(Decimal::new(25, 2))
+    }
+
+    pub fn rate(&self, liquidity: Liquidity) -> Decimal {
+        let rate = match liquidity {
+            Liquidity::Maker => self.maker,
+            Liquidity::Taker => self.taker,
+        };
+
+        rate * (Decimal::one() - self.discount)
+    }
+
+    /// The fee of an order worth `value`, in the same unit.
+    pub fn fee(&self, liquidity: Liquidity, value: Decimal) -> Decimal {
+        value * self.rate(liquidity)
+    }
+}
+
+impl Default for Fees {
+    fn default() -> Self {
+        Self::vip(0)
+    }
+}
+
+impl fmt::Display for Fees {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}% maker, {}% taker",
+            self.rate(Liquidity::Maker) * Decimal::new(100, 0),
+            self.rate(Liquidity::Taker) * Decimal::new(100, 0)
+        )
+    }
+}
+
+/// Keeps enough BNB to pay fees with: once its value falls below `min`, BNB worth `amount` is
+/// bought. Both are in the valuation currency.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TopUp {
+    pub min: Decimal,
+    pub amount: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        let fees = Fees::default();
+        assert_eq!(fees.rate(Liquidity::Taker), Decimal::new(1, 3));
+        assert_eq!(
+            fees.fee(Liquidity::Maker, Decimal::new(1000, 0)),
+            Decimal::new(1, 0)
+        );
+
+        let fees = Fees::vip(9).paid_in_bnb();
+        assert_eq!(fees.rate(Liquidity::Maker), Decimal::new(15, 5));
+        assert_eq!(fees.rate(Liquidity::Taker), Decimal::new(3, 4));
+        assert_eq!(Fees::vip(42), Fees::vip(9));
+    }
+}
@@ -1,36 +1,94 @@
-use super::{Exchange, Strategy, Trade};
+use super::{
+    book, merge, quality::Report, Account, Checks, Exchange, MarketEvent, Strategy, Trade,
+};
+use crate::Market;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use rust_decimal::prelude::*;
 use sqlx::postgres::PgPool;
-use std::time::SystemTime;
+use std::{collections::BTreeMap, path::PathBuf, time::SystemTime};
 use tokio::{
     fs::OpenOptions,
     io::{AsyncReadExt, AsyncWriteExt},
 };
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 
 pub struct Historical {
     markets: Vec<&'static str>,
     from: DateTime<Utc>,
     to: DateTime<Utc>,
     cache: bool,
+    books: Option<PathBuf>,
+    checks: Option<Checks>,
+    timer: i64,
+    finish: bool,
 }
 
 impl Historical {
     pub fn new(markets: &Vec<&'static str>, from: DateTime<Utc>, to: DateTime<Utc>, cache: bool) -> Self {
-        Self { markets: markets.clone(), from, to, cache }
+        Self {
+            markets: markets.clone(),
+            from,
+            to,
+            cache,
+            books: None,
+            checks: None,
+            timer: 1000 * 60,
+            finish: true,
+        }
     }
-}
 
-#[async_trait]
-impl<S: Strategy + 'static> Exchange<S> for Historical {
-    async fn run(self, strategy: &mut S) {
+    /// Replays the order book updates recorded in the given file alongside the trades.
+    pub fn with_books<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.books = Some(path.into());
+        self
+    }
+
+    /// Checks the recorded trades before they are aggregated per minute and replayed, and repairs
+    /// or rejects them as configured.
+    pub fn with_checks(mut self, checks: Checks) -> Self {
+        self.checks = Some(checks);
+        self
+    }
+
+    /// Sends the strategy a timer event at every multiple of `timer` milliseconds, like `Live`,
+    /// before the events at or after it. Defaults to a minute.
+    pub fn with_timer(mut self, timer: i64) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// Doesn't finish the strategy after the last trade, so it can go on with `Live`, e.g. after
+    /// warming up.
+    pub fn without_finish(mut self) -> Self {
+        self.finish = false;
+        self
+    }
+
+    /// Fires the timers up to `timestamp`, starting with the first one after the first event.
+    fn tick<S: Strategy>(&self, next: &mut Option<i64>, timestamp: i64, strategy: &mut S) {
+        let next = next.get_or_insert((timestamp / self.timer + 1) * self.timer);
+        while *next <= timestamp {
+            strategy.event(MarketEvent::Timer(*next), &Account::default());
+            *next += self.timer;
+        }
+    }
+
+    /// The trades of all markets in the range per minute, ordered by timestamp and then by market,
+    /// so replays are reproducible. With checks, the recorded trades are checked one by one as
+    /// they are read and aggregated afterwards, or the report is returned if they were rejected.
+    /// Without checks, the trades are cached in `trades.bin` for eight hours if caching is enabled.
+    pub async fn trades(&self) -> Result<Vec<Trade>, Report> {
         log::info!("Get historical data.");
 
         let uri = std::env::var("DATABASE_URL").expect("Couldn't get DATABASE_URL.");
         let pool = PgPool::connect(&uri).await.unwrap();
 
-        
+        if let Some(checks) = &self.checks {
+            let trades = checked(checks, self.raw_trades(&pool)).await?;
+            return Ok(merge::merge(merge::by_market(trades)).collect());
+        }
+
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -53,51 +111,7 @@ impl<S: Strategy + 'static> Exchange<S> for Historical {
             file.read_to_end(&mut bin).await.unwrap();
             bincode::deserialize(&bin[..]).unwrap()
         } else {
-            let trades = sqlx::query_as!(
-                Trade,
-                /*
-                r#"
-                SELECT
-                    market AS "market!",
-                    CAST(quantity AS REAL) AS "quantity!",
-                    CAST(price AS REAL) AS "price!",
-                    timestamp*1000*60 AS "timestamp!"
-                FROM trades
-                WHERE market = ANY($1)
-                AND timestamp > $2
-                AND timestamp <= $3
-                ORDER BY timestamp ASC"#,*/
-                
-                r#"
-                WITH
-                grouped AS (SELECT
-                    market,
-                    CAST(SUM(quantity) AS REAL) AS quantity,
-                    CAST(AVG(price) AS REAL) AS price,
-                    timestamp/1000/60 AS timestamp
-                FROM trades
-                WHERE market = ANY($1)
-                AND timestamp > $2
-                AND timestamp <= $3
-                GROUP BY market, timestamp/1000/60)
-                SELECT
-                    market AS "market!",
-                    quantity AS "quantity!",
-                    price AS  "price!",
-                    timestamp*1000*60 AS "timestamp!"
-                FROM grouped
-                ORDER BY timestamp ASC"#,
-                
-                &self.markets
-                .into_iter()
-                .map(String::from)
-                .collect::<Vec<String>>(),
-                self.from.timestamp_millis(),
-                self.to.timestamp_millis(),
-            )
-            .fetch_all(&pool)
-            .await
-            .unwrap();
+            let trades = self.grouped_trades(&pool).await;
 
             if self.cache {
                 let bin = bincode::serialize(&trades).unwrap();
@@ -107,8 +121,158 @@ impl<S: Strategy + 'static> Exchange<S> for Historical {
             trades
         };
 
+        Ok(merge::merge(merge::by_market(trades)).collect())
+    }
+
+    /// Every trade as it was recorded, read row by row.
+    fn raw_trades<'a>(&self, pool: &'a PgPool) -> impl Stream<Item = Trade> + Unpin + 'a {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            SELECT
+                market AS "market!",
+                quantity AS "quantity!",
+                price AS "price!",
+                timestamp AS "timestamp!",
+                NULL::TEXT AS "venue"
+            FROM trades
+            WHERE market = ANY($1)
+            AND timestamp > $2
+            AND timestamp <= $3
+            ORDER BY timestamp ASC, market ASC"#,
+            &self.markets
+                .iter()
+                .map(|market| market.to_string())
+                .collect::<Vec<String>>(),
+            self.from.timestamp_millis(),
+            self.to.timestamp_millis(),
+        )
+        .fetch(pool)
+        .map(|trade| trade.unwrap())
+    }
+
+    /// The trades aggregated per minute by the database.
+    async fn grouped_trades(&self, pool: &PgPool) -> Vec<Trade> {
+        sqlx::query_as!(
+            Trade,
+            r#"
+            WITH
+            grouped AS (SELECT
+                market,
+                SUM(quantity) AS quantity,
+                ROUND(AVG(price), 8) AS price,
+                timestamp/1000/60 AS timestamp
+            FROM trades
+            WHERE market = ANY($1)
+            AND timestamp > $2
+            AND timestamp <= $3
+            GROUP BY market, timestamp/1000/60)
+            SELECT
+                market AS "market!",
+                quantity AS "quantity!",
+                price AS  "price!",
+                timestamp*1000*60 AS "timestamp!",
+                NULL::TEXT AS "venue"
+            FROM grouped
+            ORDER BY timestamp ASC, market ASC"#,
+            &self.markets
+                .iter()
+                .map(|market| market.to_string())
+                .collect::<Vec<String>>(),
+            self.from.timestamp_millis(),
+            self.to.timestamp_millis(),
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+}
+
+/// Checks recorded trades as they arrive, repairs or rejects them as configured and aggregates
+/// what is left per minute, summing the quantities and averaging the prices like the database does
+/// without checks.
+async fn checked<T>(checks: &Checks, mut trades: T) -> Result<Vec<Trade>, Report>
+where
+    T: Stream<Item = Trade> + Unpin,
+{
+    let mut checker = checks.checker();
+    let mut minutes = BTreeMap::new();
+    while let Some(trade) = trades.next().await {
+        aggregate(&mut minutes, checker.push(trade));
+    }
+    let (rest, report) = checker.finish()?;
+    aggregate(&mut minutes, rest);
+
+    if !report.is_clean() {
+        log::warn!("Historical data has issues:\n{}", report);
+    }
+    Ok(minutes
+        .into_iter()
+        .map(|((minute, market), (quantity, price, count))| Trade {
+            market,
+            quantity,
+            price: (price / Decimal::from(count))
+                .round_dp_with_strategy(8, RoundingStrategy::RoundHalfUp),
+            timestamp: minute * 1000 * 60,
+            venue: None,
+        })
+        .collect())
+}
+
+/// Adds trades to the sums of quantities and prices and the number of trades of every minute and
+/// market.
+fn aggregate(minutes: &mut BTreeMap<(i64, Market), (Decimal, Decimal, i64)>, trades: Vec<Trade>) {
+    for trade in trades {
+        let (quantity, price, count) = minutes
+            .entry((trade.timestamp / 1000 / 60, trade.market))
+            .or_insert((Decimal::zero(), Decimal::zero(), 0));
+        *quantity += trade.quantity;
+        *price += trade.price;
+        *count += 1;
+    }
+}
+
+#[async_trait]
+impl<S: Strategy + 'static> Exchange<S> for Historical {
+    async fn run(self, strategy: &mut S) {
+        let mut books = match &self.books {
+            Some(path) => book::read_book_updates(path)
+                .expect("Couldn't read recorded order books.")
+                .into_iter()
+                .filter(|update| {
+                    self.markets.contains(&update.market.as_str())
+                        && update.timestamp > self.from.timestamp_millis()
+                        && update.timestamp <= self.to.timestamp_millis()
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        books.sort_by_key(|update| update.timestamp);
+        let mut books = books.into_iter().peekable();
+
+        let trades = match self.trades().await {
+            Ok(trades) => trades,
+            Err(report) => {
+                log::error!("Historical data failed the checks:\n{}", report);
+                return;
+            }
+        };
+
+        let mut timer = None;
         for trade in trades {
-            strategy.run(trade);
+            while let Some(update) = books.next_if(|update| update.timestamp <= trade.timestamp) {
+                self.tick(&mut timer, update.timestamp, strategy);
+                strategy.event(MarketEvent::BookUpdate(update), &Account::default());
+            }
+            self.tick(&mut timer, trade.timestamp, strategy);
+            strategy.event(MarketEvent::Trade(trade), &Account::default());
+        }
+        for update in books {
+            self.tick(&mut timer, update.timestamp, strategy);
+            strategy.event(MarketEvent::BookUpdate(update), &Account::default());
+        }
+        if self.finish {
+            strategy.finish();
         }
         
         /*
@@ -118,8 +282,8 @@ impl<S: Strategy + 'static> Exchange<S> for Historical {
             r#"
             SELECT
                 market AS "market!",
-                CAST(quantity AS REAL) AS "quantity!",
-                CAST(price AS REAL) AS "price!",
+                quantity AS "quantity!",
+                price AS "price!",
                 timestamp*1000*60 AS "timestamp!"
             FROM trades
             WHERE market = ANY($1)
@@ -132,8 +296,8 @@ impl<S: Strategy + 'static> Exchange<S> for Historical {
             WITH
             grouped AS (SELECT
                 market,
-                CAST(SUM(quantity) AS REAL) AS quantity,
-                CAST(AVG(price) AS REAL) AS price,
+                SUM(quantity) AS quantity,
+                ROUND(AVG(price), 8) AS price,
                 timestamp/1000/60 AS timestamp
             FROM trades
             WHERE market = ANY($1)
@@ -179,3 +343,60 @@ impl<S: Strategy + 'static> Exchange<S> for Historical {
         */
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::Repair;
+    use futures::stream;
+
+    fn trade(price: i64, timestamp: i64) -> Trade {
+        Trade {
+            market: "BTCUSDT".to_owned(),
+            quantity: Decimal::one(),
+            price: Decimal::new(price, 0),
+            timestamp,
+            venue: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn checked_rows() {
+        // The third trade was recorded without a price.
+        let trades = vec![
+            trade(100, 1000),
+            trade(103, 2000),
+            trade(0, 2000),
+            trade(106, 1000 * 60),
+        ];
+        let checks = Checks::new().with_repair(Repair::Drop);
+
+        let minutes = |trades: Vec<Trade>| {
+            trades
+                .into_iter()
+                .map(|trade| (trade.timestamp, trade.price, trade.quantity))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            minutes(checked(&checks, stream::iter(trades.clone())).await.unwrap()),
+            vec![
+                (0, Decimal::new(1015, 1), Decimal::new(2, 0)),
+                (1000 * 60, Decimal::new(106, 0), Decimal::new(1, 0)),
+            ]
+        );
+        assert_eq!(
+            minutes(checked(&Checks::new(), stream::iter(trades)).await.unwrap()),
+            vec![
+                (0, Decimal::new(6766666667, 8), Decimal::new(3, 0)),
+                (1000 * 60, Decimal::new(106, 0), Decimal::new(1, 0)),
+            ]
+        );
+        assert!(checked(
+            &Checks::new().with_repair(Repair::Reject),
+            stream::iter(vec![trade(100, 1000), trade(0, 1000)]),
+        )
+        .await
+        .is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use super::{AssetBalance, VenueOrder};
+use crate::{Entry, Error, Level, Market, Trade};
+use async_trait::async_trait;
+use openlimits::{
+    exchange::ExchangeAccount,
+    model::{
+        AskBid, Balance, CancelAllOrdersRequest, CancelOrderRequest, GetOrderRequest,
+        OpenLimitOrderRequest, OpenMarketOrderRequest, Order as OpenLimitsOrder, OrderStatus, Side,
+        TimeInForce, Trade as OpenLimitsTrade,
+    },
+    shared::Result as OpenLimitsResult,
+};
+use rust_decimal::prelude::*;
+
+/// The account requests the venues make through openlimits, so a mock can answer them in tests.
+#[async_trait]
+pub trait AccountRequests: Send + Sync {
+    async fn balances(&self) -> OpenLimitsResult<Vec<Balance>>;
+
+    async fn market_buy(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder>;
+
+    async fn limit_buy(&self, request: &OpenLimitOrderRequest)
+        -> OpenLimitsResult<OpenLimitsOrder>;
+
+    async fn market_sell(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder>;
+
+    async fn get_order(&self, request: &GetOrderRequest) -> OpenLimitsResult<OpenLimitsOrder>;
+
+    async fn cancel_order(&self, request: &CancelOrderRequest) -> OpenLimitsResult<()>;
+
+    async fn cancel_all(&self, request: &CancelAllOrdersRequest) -> OpenLimitsResult<()>;
+}
+
+#[async_trait]
+impl<E: ExchangeAccount + Send + Sync> AccountRequests for E {
+    async fn balances(&self) -> OpenLimitsResult<Vec<Balance>> {
+        ExchangeAccount::get_account_balances(self, None).await
+    }
+
+    async fn market_buy(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        ExchangeAccount::market_buy(self, request).await
+    }
+
+    async fn limit_buy(
+        &self,
+        request: &OpenLimitOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        ExchangeAccount::limit_buy(self, request).await
+    }
+
+    async fn market_sell(
+        &self,
+        request: &OpenMarketOrderRequest,
+    ) -> OpenLimitsResult<OpenLimitsOrder> {
+        ExchangeAccount::market_sell(self, request).await
+    }
+
+    async fn get_order(&self, request: &GetOrderRequest) -> OpenLimitsResult<OpenLimitsOrder> {
+        ExchangeAccount::get_order(self, request).await
+    }
+
+    async fn cancel_order(&self, request: &CancelOrderRequest) -> OpenLimitsResult<()> {
+        ExchangeAccount::cancel_order(self, request).await?;
+        Ok(())
+    }
+
+    async fn cancel_all(&self, request: &CancelAllOrdersRequest) -> OpenLimitsResult<()> {
+        ExchangeAccount::cancel_all_orders(self, request).await?;
+        Ok(())
+    }
+}
+
+pub fn venue_order(order: OpenLimitsOrder) -> VenueOrder {
+    let filled = match order.status {
+        OrderStatus::Filled => order.size,
+        _ => order.size - order.remaining.unwrap_or(order.size),
+    };
+
+    VenueOrder {
+        open: matches!(
+            order.status,
+            OrderStatus::New | OrderStatus::PartiallyFilled
+        ),
+        id: order.id,
+        market: order.market_pair,
+        size: order.size,
+        filled,
+        price: order.price,
+    }
+}
+
+pub fn asset_balance(balance: Balance) -> AssetBalance {
+    AssetBalance {
+        locked: balance.total - balance.free,
+        free: balance.free,
+        asset: balance.asset,
+    }
+}
+
+pub fn trade(trade: OpenLimitsTrade) -> Trade {
+    Trade {
+        market: trade.market_pair,
+        quantity: match trade.side {
+            Side::Buy => -trade.qty,
+            Side::Sell => trade.qty,
+        },
+        price: trade.price,
+        timestamp: trade.created_at as i64,
+        venue: None,
+    }
+}
+
+pub fn levels(levels: Vec<AskBid>) -> Vec<Level> {
+    levels
+        .into_iter()
+        .map(|AskBid { price, qty }| Level {
+            price,
+            quantity: qty,
+        })
+        .collect()
+}
+
+pub async fn balances<E: AccountRequests>(exchange: &E) -> Result<Vec<AssetBalance>, Error> {
+    Ok(exchange
+        .balances()
+        .await?
+        .into_iter()
+        .map(asset_balance)
+        .collect())
+}
+
+pub async fn buy<E: AccountRequests>(
+    exchange: &E,
+    market: &Market,
+    size: Decimal,
+    price: Decimal,
+    entry: Entry,
+) -> Result<VenueOrder, Error> {
+    let order = match entry {
+        Entry::Market => {
+            exchange
+                .market_buy(&OpenMarketOrderRequest {
+                    market_pair: market.clone(),
+                    size,
+                })
+                .await?
+        }
+        Entry::Limit { .. } | Entry::PostOnly { .. } => {
+            exchange
+                .limit_buy(&OpenLimitOrderRequest {
+                    market_pair: market.clone(),
+                    size,
+                    price,
+                    time_in_force: TimeInForce::GoodTillCancelled,
+                    post_only: matches!(entry, Entry::PostOnly { .. }),
+                })
+                .await?
+        }
+        Entry::ImmediateOrCancel => {
+            exchange
+                .limit_buy(&OpenLimitOrderRequest {
+                    market_pair: market.clone(),
+                    size,
+                    price,
+                    time_in_force: TimeInForce::ImmediateOrCancelled,
+                    post_only: false,
+                })
+                .await?
+        }
+    };
+
+    Ok(venue_order(order))
+}
+
+pub async fn market_sell<E: AccountRequests>(
+    exchange: &E,
+    market: &Market,
+    size: Decimal,
+) -> Result<VenueOrder, Error> {
+    let order = exchange
+        .market_sell(&OpenMarketOrderRequest {
+            market_pair: market.clone(),
+            size,
+        })
+        .await?;
+
+    Ok(venue_order(order))
+}
+
+pub async fn get_order<E: AccountRequests>(
+    exchange: &E,
+    order: &VenueOrder,
+) -> Result<VenueOrder, Error> {
+    let order = exchange
+        .get_order(&GetOrderRequest {
+            id: order.id.clone(),
+            market_pair: Some(order.market.clone()),
+        })
+        .await?;
+
+    Ok(venue_order(order))
+}
+
+pub async fn cancel_order<E: AccountRequests>(
+    exchange: &E,
+    order: &VenueOrder,
+) -> Result<(), Error> {
+    exchange
+        .cancel_order(&CancelOrderRequest {
+            id: order.id.clone(),
+            market_pair: Some(order.market.clone()),
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn cancel_all<E: AccountRequests>(exchange: &E, market: &Market) -> Result<(), Error> {
+    exchange
+        .cancel_all(&CancelAllOrdersRequest {
+            market_pair: Some(market.clone()),
+        })
+        .await?;
+
+    Ok(())
+}
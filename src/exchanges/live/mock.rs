@@ -0,0 +1,506 @@
+use super::{AssetBalance, BookDiff, BookSnapshot, Exit, Rules, Venue, VenueOrder};
+use crate::{Entry, Error, Fees, Market, Pair, Trade};
+use async_trait::async_trait;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use rust_decimal::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+use tokio::time::{sleep, Duration};
+
+/// What happened on the mock venue, in order. Requests are logged as they arrive, fills of
+/// resting entries and exits as trades reach them.
+#[derive(Debug, Default)]
+pub struct State {
+    pub log: Vec<String>,
+    pub balances: BTreeMap<String, AssetBalance>,
+    pub prices: HashMap<Market, Decimal>,
+    exits: Vec<Exit>,
+    orders: HashMap<String, VenueOrder>,
+    next_id: usize,
+}
+
+impl State {
+    pub fn free(&self, asset: &str) -> Decimal {
+        self.balances
+            .get(asset)
+            .map_or(Decimal::zero(), |balance| balance.free)
+    }
+
+    fn balance(&mut self, asset: &str) -> &mut AssetBalance {
+        self.balances
+            .entry(asset.to_owned())
+            .or_insert_with(|| AssetBalance {
+                asset: asset.to_owned(),
+                free: Decimal::zero(),
+                locked: Decimal::zero(),
+            })
+    }
+
+    /// Moves `quantity` of `pair.base` against its value at `price` in `pair.quote`. Orders the
+    /// real venue would reject for lack of funds are rejected and change nothing.
+    fn exchange(&mut self, pair: &Pair, quantity: Decimal, price: Decimal) -> Result<(), Error> {
+        let base = self.free(&pair.base) + quantity;
+        let quote = self.free(&pair.quote) - quantity * price;
+        for (asset, free) in [(&pair.base, base), (&pair.quote, quote)] {
+            if free < Decimal::zero() {
+                self.log.push(format!("not enough {}", asset));
+                return Err(Error::Rejected(format!("Not enough {}.", asset)));
+            }
+        }
+
+        self.balance(&pair.base).free = base;
+        self.balance(&pair.quote).free = quote;
+        Ok(())
+    }
+
+    fn order(
+        &mut self,
+        market: &Market,
+        size: Decimal,
+        filled: Decimal,
+        price: Decimal,
+        open: bool,
+    ) -> VenueOrder {
+        self.next_id += 1;
+        let order = VenueOrder {
+            id: self.next_id.to_string(),
+            market: market.clone(),
+            size,
+            filled,
+            price: Some(price),
+            open,
+        };
+        if open {
+            self.orders.insert(order.id.clone(), order.clone());
+        }
+        order
+    }
+
+    /// Fills the entries and exits `trade` reaches, take profits first.
+    fn settle(&mut self, pairs: &HashMap<Market, Pair>, stop_orders: bool, trade: &Trade) {
+        self.prices.insert(trade.market.clone(), trade.price);
+
+        let mut entries = self
+            .orders
+            .values()
+            .filter(|order| {
+                order.open
+                    && order.market == trade.market
+                    && order.price.map_or(false, |price| trade.price <= price)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|order| order.id.parse::<usize>().unwrap());
+        for order in entries {
+            let price = order.price.unwrap();
+            // Entries that can't be paid for are cancelled.
+            let filled = match self.exchange(&pairs[&order.market], order.size, price) {
+                Ok(()) => {
+                    self.log.push(format!(
+                        "fill {} {} at {}",
+                        order.size.round_dp(6),
+                        order.market,
+                        price
+                    ));
+                    order.size
+                }
+                Err(_) => Decimal::zero(),
+            };
+            let order = self.orders.get_mut(&order.id).unwrap();
+            order.open = false;
+            order.filled = filled;
+        }
+
+        let (filled, resting) = self.exits.drain(..).partition::<Vec<_>, _>(|exit| {
+            exit.market == trade.market
+                && (trade.price >= exit.take_profit
+                    || stop_orders && trade.price <= exit.stop_price)
+        });
+        self.exits = resting;
+
+        for exit in filled {
+            let (kind, price) = if trade.price >= exit.take_profit {
+                ("take profit", exit.take_profit)
+            } else {
+                ("stop loss", exit.stop_limit_price)
+            };
+            let pair = &pairs[&exit.market];
+            self.balance(&pair.base).locked -= exit.quantity;
+            self.balance(&pair.base).free += exit.quantity;
+            // The quantity was locked, so there is always enough of it.
+            if self.exchange(pair, -exit.quantity, price).is_ok() {
+                self.log.push(format!(
+                    "{} {} {} at {}",
+                    kind, exit.quantity, exit.market, price
+                ));
+            }
+        }
+    }
+}
+
+/// An in-process `Venue` for tests of `Live`. It stands in for a venue adapter, so the requests
+/// and conversions of `Binance` aren't covered, only the live loop on top of them. It behaves like
+/// a spot venue with OCO orders: market orders fill at the last traded price, limit orders right
+/// away if they cross it and otherwise rest until a trade reaches them, exits lock their quantity
+/// until a trade reaches them, orders without the funds for them are rejected, and trades are
+/// replayed from a script. Time has to be paused for the replay to be deterministic, then every
+/// trade is processed completely before the next one arrives.
+pub struct Mock {
+    name: &'static str,
+    pairs: HashMap<Market, Pair>,
+    rules: HashMap<Market, Rules>,
+    stop_orders: bool,
+    connections: Mutex<VecDeque<Vec<Trade>>>,
+    history: Vec<Trade>,
+    books: Mutex<VecDeque<BookSnapshot>>,
+    diffs: Vec<BookDiff>,
+    failures: Mutex<Vec<&'static str>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Self {
+            name: "Mock",
+            pairs: HashMap::new(),
+            rules: HashMap::new(),
+            stop_orders: true,
+            connections: Mutex::new(VecDeque::new()),
+            history: Vec::new(),
+            books: Mutex::new(VecDeque::new()),
+            diffs: Vec::new(),
+            failures: Mutex::new(Vec::new()),
+            state: Arc::new(Mutex::new(State::default())),
+        }
+    }
+
+    /// Tells it apart from other mock venues trading alongside it.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Lists `base` + `quote` with a tick size of 0.01, a step size of 0.001 and a minimum
+    /// notional of 10.
+    pub fn with_market(mut self, base: &str, quote: &str) -> Self {
+        let market = format!("{}{}", base, quote);
+        self.pairs.insert(
+            market.clone(),
+            Pair {
+                market: market.clone(),
+                base: base.to_owned(),
+                quote: quote.to_owned(),
+            },
+        );
+        self.rules.insert(
+            market,
+            Rules {
+                tick_size: Some(Decimal::new(1, 2)),
+                step_size: Some(Decimal::new(1, 3)),
+                min_notional: Decimal::new(10, 0),
+                ..Rules::default()
+            },
+        );
+        self
+    }
+
+    pub fn with_balance(self, asset: &str, free: i64) -> Self {
+        self.state.lock().unwrap().balance(asset).free = Decimal::new(free, 0);
+        self
+    }
+
+    /// Streams these trades on the next connection, the second after the first one and so on.
+    /// Every connection but the last is lost after its trades.
+    pub fn with_trades(self, trades: Vec<Trade>) -> Self {
+        self.connections.lock().unwrap().push_back(trades);
+        self
+    }
+
+    /// Past trades, served by `trades_between`.
+    pub fn with_history(mut self, trades: Vec<Trade>) -> Self {
+        self.history = trades;
+        self
+    }
+
+    /// Order book snapshots, served one per request in order.
+    pub fn with_book(self, snapshot: BookSnapshot) -> Self {
+        self.books.lock().unwrap().push_back(snapshot);
+        self
+    }
+
+    /// Order book diffs, streamed a second apart like trades.
+    pub fn with_diffs(mut self, diffs: Vec<BookDiff>) -> Self {
+        self.diffs = diffs;
+        self
+    }
+
+    /// Behaves like a venue without OCO orders, only take profits rest in the book.
+    pub fn without_stop_orders(mut self) -> Self {
+        self.stop_orders = false;
+        self
+    }
+
+    /// Rejects the next request of a kind, `"sell"`, `"exit"` or `"cancel all"`.
+    pub fn with_failure(self, request: &'static str) -> Self {
+        self.failures.lock().unwrap().push(request);
+        self
+    }
+
+    /// Stays accessible after the venue was moved into `Live`.
+    pub fn state(&self) -> Arc<Mutex<State>> {
+        self.state.clone()
+    }
+
+    fn log(&self, entry: String) {
+        self.state.lock().unwrap().log.push(entry);
+    }
+
+    fn fail(&self, request: &'static str) -> Result<(), Error> {
+        let mut failures = self.failures.lock().unwrap();
+        match failures.iter().position(|failure| *failure == request) {
+            Some(index) => {
+                failures.remove(index);
+                self.log(format!("{} rejected", request));
+                Err(Error::Rejected(format!("{} failed", request)))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Venue for Mock {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn is_sandbox(&self) -> bool {
+        true
+    }
+
+    fn fees(&self) -> Fees {
+        Fees::default()
+    }
+
+    fn stop_orders(&self) -> bool {
+        self.stop_orders
+    }
+
+    async fn server_time(&self) -> Result<i64, Error> {
+        Ok(0)
+    }
+
+    async fn markets(&self) -> Result<Vec<(Pair, Rules)>, Error> {
+        Ok(self
+            .pairs
+            .values()
+            .map(|pair| (pair.clone(), self.rules[&pair.market].clone()))
+            .collect())
+    }
+
+    async fn balances(&self) -> Result<Vec<AssetBalance>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .balances
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    async fn prices(&self, _markets: &[Market]) -> Result<Vec<(Market, Decimal)>, Error> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .prices
+            .iter()
+            .map(|(market, price)| (market.clone(), *price))
+            .collect())
+    }
+
+    /// Reconnecting after the script was replayed yields nothing.
+    async fn trades(
+        &self,
+        _markets: &[Market],
+    ) -> Result<BoxStream<'static, Result<Trade, Error>>, Error> {
+        let (trades, last) = {
+            let mut connections = self.connections.lock().unwrap();
+            (
+                connections.pop_front().unwrap_or_default(),
+                connections.is_empty(),
+            )
+        };
+        let (pairs, stop_orders, state) =
+            (self.pairs.clone(), self.stop_orders, self.state.clone());
+
+        let trades = stream::iter(trades).then(move |trade| {
+            let (pairs, state) = (pairs.clone(), state.clone());
+            async move {
+                sleep(Duration::from_secs(1)).await;
+                state.lock().unwrap().settle(&pairs, stop_orders, &trade);
+                Ok(trade)
+            }
+        });
+        Ok(if last {
+            trades.chain(stream::pending()).boxed()
+        } else {
+            trades.boxed()
+        })
+    }
+
+    async fn trades_between(
+        &self,
+        market: &Market,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Trade>, Error> {
+        self.log(format!("history {} {}..{}", market, from, to));
+
+        Ok(self
+            .history
+            .iter()
+            .filter(|trade| {
+                trade.market == *market && trade.timestamp > from && trade.timestamp < to
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn order_book(&self, market: &Market) -> Result<BookSnapshot, Error> {
+        self.log(format!("order book {}", market));
+
+        self.books
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or(Error::Unsupported("order books"))
+    }
+
+    async fn depth(
+        &self,
+        _market: &Market,
+    ) -> Result<BoxStream<'static, Result<BookDiff, Error>>, Error> {
+        Ok(stream::iter(self.diffs.clone())
+            .then(|diff| async move {
+                sleep(Duration::from_secs(1)).await;
+                Ok(diff)
+            })
+            .chain(stream::pending())
+            .boxed())
+    }
+
+    async fn buy(
+        &self,
+        market: &Market,
+        size: Decimal,
+        price: Decimal,
+        entry: Entry,
+    ) -> Result<VenueOrder, Error> {
+        self.log(format!(
+            "{:?} buy {} {} at {}",
+            entry,
+            size.round_dp(6),
+            market,
+            price
+        ));
+
+        let mut state = self.state.lock().unwrap();
+        let last = state.prices[market];
+        let crosses = last <= price;
+        // Post only orders that would take liquidity are rejected.
+        let (fill, open) = match entry {
+            Entry::Market => (Some(last), false),
+            Entry::PostOnly { .. } => (None, !crosses),
+            Entry::Limit { .. } if crosses => (Some(price), false),
+            Entry::Limit { .. } => (None, true),
+            Entry::ImmediateOrCancel => (Some(price).filter(|_| crosses), false),
+        };
+
+        Ok(match fill {
+            Some(fill) => {
+                state.exchange(&self.pairs[market], size, fill)?;
+                state.order(market, size, size, fill, false)
+            }
+            None => state.order(market, size, Decimal::zero(), price, open),
+        })
+    }
+
+    async fn market_sell(&self, market: &Market, size: Decimal) -> Result<VenueOrder, Error> {
+        self.log(format!("sell {} {}", size, market));
+        self.fail("sell")?;
+
+        let mut state = self.state.lock().unwrap();
+        let last = state.prices[market];
+        state.exchange(&self.pairs[market], -size, last)?;
+        Ok(state.order(market, size, size, last, false))
+    }
+
+    async fn get_order(&self, order: &VenueOrder) -> Result<VenueOrder, Error> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .orders
+            .get(&order.id)
+            .cloned()
+            .unwrap_or_else(|| order.clone()))
+    }
+
+    async fn cancel_order(&self, order: &VenueOrder) -> Result<(), Error> {
+        self.log(format!("cancel {}", order.id));
+
+        if let Some(order) = self.state.lock().unwrap().orders.get_mut(&order.id) {
+            order.open = false;
+        }
+        Ok(())
+    }
+
+    async fn cancel_all(&self, market: &Market) -> Result<(), Error> {
+        self.log(format!("cancel all {}", market));
+        self.fail("cancel all")?;
+
+        let mut state = self.state.lock().unwrap();
+        let base = &self.pairs[market].base;
+        let (cancelled, resting): (Vec<Exit>, Vec<Exit>) = state
+            .exits
+            .drain(..)
+            .partition(|exit| exit.market == *market);
+        state.exits = resting;
+        for exit in cancelled {
+            let balance = state.balance(base);
+            balance.locked -= exit.quantity;
+            balance.free += exit.quantity;
+        }
+        for order in state.orders.values_mut() {
+            if order.market == *market {
+                order.open = false;
+            }
+        }
+        Ok(())
+    }
+
+    async fn place_exit(&self, exit: &Exit) -> Result<(), Error> {
+        self.log(format!(
+            "exit {} {} at {} or {}/{}",
+            exit.quantity, exit.market, exit.take_profit, exit.stop_price, exit.stop_limit_price
+        ));
+        self.fail("exit")?;
+
+        let mut state = self.state.lock().unwrap();
+        let base = &self.pairs[&exit.market].base;
+        if state.free(base) < exit.quantity {
+            state.log.push(format!("not enough {}", base));
+            return Err(Error::Rejected(format!("Not enough {}.", base)));
+        }
+        let balance = state.balance(base);
+        balance.free -= exit.quantity;
+        balance.locked += exit.quantity;
+        state.exits.push(exit.clone());
+        Ok(())
+    }
+}
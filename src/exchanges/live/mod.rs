@@ -0,0 +1,1819 @@
+/// Conversions and requests shared by the openlimits venues.
+pub mod adapter;
+#[cfg(test)]
+pub mod mock;
+mod positions;
+mod rules;
+mod wallet;
+
+pub use positions::{Leg, Position};
+pub use rules::{FilterError, FilteredOrder, Rules};
+pub use wallet::{Holding, Wallet};
+
+use super::{
+    book::BookRecorder, Account, ClosedPosition, Entry, Exchange, Fees, Fill, Intent, Level,
+    Liquidity, MarketEvent, Order, OrderBook, Pair, Stops, Strategy, TopUp, Trade,
+};
+use crate::{
+    loggers::{Database, Logger, Sender},
+    risk::{Portfolio, RiskManager},
+    sizing::{self, FixedFraction, PositionSizer},
+    Error, Market,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::{future::join_all, stream::BoxStream, StreamExt};
+use positions::{Positions, SoldLegs};
+use rust_decimal::prelude::*;
+use std::{collections::HashMap, path::Path, sync::Mutex};
+use tokio::{
+    sync::{
+        self,
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+    },
+    time::{interval, sleep, timeout, Duration, Instant},
+};
+
+/// Balance of an asset as reported by a venue.
+#[derive(Debug, Clone)]
+pub struct AssetBalance {
+    pub asset: String,
+    pub free: Decimal,
+    /// Held by open orders.
+    pub locked: Decimal,
+}
+
+/// An order as reported by a venue.
+#[derive(Debug, Clone)]
+pub struct VenueOrder {
+    pub id: String,
+    pub market: Market,
+    pub size: Decimal,
+    /// Base quantity bought or sold so far.
+    pub filled: Decimal,
+    /// Average fill price, if the venue reports it.
+    pub price: Option<Decimal>,
+    /// Whether the order still rests in the book.
+    pub open: bool,
+}
+
+/// An entry order resting in the book until it is filled or times out.
+#[derive(Clone)]
+struct PendingEntry {
+    /// What the strategy asked for, it sees it in its account meanwhile.
+    intent: Order,
+    order: FilteredOrder,
+    venue_order: VenueOrder,
+    deadline: Instant,
+    /// Being cancelled or protected, it stays pending until that's done.
+    settling: bool,
+}
+
+/// Sells `quantity` at `take_profit`, or at `stop_limit_price` once the price fell to
+/// `stop_price`.
+#[derive(Debug, Clone)]
+pub struct Exit {
+    pub market: Market,
+    pub quantity: Decimal,
+    pub take_profit: Decimal,
+    pub stop_price: Decimal,
+    pub stop_limit_price: Decimal,
+}
+
+/// Order book levels up to `last_update_id`.
+#[derive(Debug, Clone)]
+pub struct BookSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+/// Changed order book levels from `first_update_id` to `last_update_id`, at `timestamp` on the
+/// venue.
+#[derive(Debug, Clone)]
+pub struct BookDiff {
+    pub first_update_id: u64,
+    pub last_update_id: u64,
+    pub timestamp: i64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+/// What a live exchange has to provide, everything else is shared by all venues.
+#[async_trait]
+pub trait Venue: Send + Sync + 'static {
+    /// Shown in logs.
+    fn name(&self) -> &str;
+
+    /// Sandboxes are quiet, so their streams are given more time before they are reconnected.
+    fn is_sandbox(&self) -> bool;
+
+    /// The fees of a new account.
+    fn fees(&self) -> Fees;
+
+    /// The asset fees can be paid in at a discount, if the venue has one.
+    fn fee_asset(&self) -> Option<&str> {
+        None
+    }
+
+    /// The currency balances are valued in unless `Live::with_valuation` overrides it.
+    fn valuation(&self) -> &str {
+        Wallet::DEFAULT_VALUATION
+    }
+
+    /// Whether exits rest their stop loss on the venue. If not, only the take profit is placed
+    /// and the stop loss is sold at market once a trade reaches it.
+    fn stop_orders(&self) -> bool;
+
+    async fn server_time(&self) -> Result<i64, Error>;
+
+    /// All listed markets and their trading rules.
+    async fn markets(&self) -> Result<Vec<(Pair, Rules)>, Error>;
+
+    async fn balances(&self) -> Result<Vec<AssetBalance>, Error>;
+
+    /// Last prices of `markets`. Venues that return all prices at once may return more.
+    async fn prices(&self, markets: &[Market]) -> Result<Vec<(Market, Decimal)>, Error>;
+
+    /// Streams the trades of `markets` until the connection is lost.
+    async fn trades(
+        &self,
+        markets: &[Market],
+    ) -> Result<BoxStream<'static, Result<Trade, Error>>, Error>;
+
+    /// Past trades of `market` after `from` and before `to`, in milliseconds, ordered by
+    /// timestamp.
+    async fn trades_between(
+        &self,
+        _market: &Market,
+        _from: i64,
+        _to: i64,
+    ) -> Result<Vec<Trade>, Error> {
+        Err(Error::Unsupported("trade history"))
+    }
+
+    async fn order_book(&self, _market: &Market) -> Result<BookSnapshot, Error> {
+        Err(Error::Unsupported("order books"))
+    }
+
+    /// Streams changes to the order book of `market` until the connection is lost.
+    async fn depth(
+        &self,
+        _market: &Market,
+    ) -> Result<BoxStream<'static, Result<BookDiff, Error>>, Error> {
+        Err(Error::Unsupported("order books"))
+    }
+
+    /// Buys `size` of the base asset, at market or at `price` depending on `entry`.
+    async fn buy(
+        &self,
+        market: &Market,
+        size: Decimal,
+        price: Decimal,
+        entry: Entry,
+    ) -> Result<VenueOrder, Error>;
+
+    async fn market_sell(&self, market: &Market, size: Decimal) -> Result<VenueOrder, Error>;
+
+    async fn get_order(&self, order: &VenueOrder) -> Result<VenueOrder, Error>;
+
+    async fn cancel_order(&self, order: &VenueOrder) -> Result<(), Error>;
+
+    async fn cancel_all(&self, market: &Market) -> Result<(), Error>;
+
+    async fn place_exit(&self, exit: &Exit) -> Result<(), Error>;
+}
+
+/// Trades a strategy on a venue, with the wallet, positions, risk rules, sizing and order
+/// lifecycle shared by all venues.
+pub struct Live<V: Venue> {
+    venue: V,
+    feeds: Vec<(Box<dyn Venue>, Vec<Market>)>,
+    wallet: Wallet,
+    positions: Positions,
+    /// Entries resting in the book, checked every second.
+    pending: sync::Mutex<Vec<PendingEntry>>,
+    /// Filled quantities too small to be protected, per market. They are sold with the next
+    /// position in the market.
+    dust: Mutex<HashMap<Market, Decimal>>,
+    markets: Vec<Market>,
+    rules: HashMap<Market, Rules>,
+    risk: Mutex<RiskManager>,
+    sizer: Mutex<Box<dyn PositionSizer>>,
+    start: i64,
+    order_books: bool,
+    book_recorder: Option<Mutex<BookRecorder>>,
+    timer: i64,
+    entry: Entry,
+    fees: Fees,
+    top_up: Option<TopUp>,
+    fee_reserve: Option<Decimal>,
+}
+
+impl<V: Venue> Live<V> {
+    const BOOK_LEVELS: usize = 20;
+    /// Most diffs buffered while an order book syncs, it starts over once there are more.
+    const BOOK_BUFFER: usize = 1000;
+    /// Shortest time between two snapshots of an order book.
+    const BOOK_RESYNC: Duration = Duration::from_secs(2);
+
+    pub async fn new(venue: V, markets: &[&str]) -> Self {
+        let (logger, sender) = Database::new();
+        tokio::task::spawn(async move {
+            logger.run().await;
+        });
+
+        Self::with_sender(venue, markets, sender).await
+    }
+
+    /// Like `new`, but the messages for the loggers are sent to `sender` instead of the database.
+    pub async fn with_sender(venue: V, markets: &[&str], sender: Sender) -> Self {
+        log::info!("Getting exchange info from {}.", venue.name());
+
+        let start = venue
+            .server_time()
+            .await
+            .expect("Couldn't get server time.");
+        let (pairs, rules): (Vec<Pair>, HashMap<Market, Rules>) = venue
+            .markets()
+            .await
+            .expect("Couldn't get exchange info.")
+            .into_iter()
+            .map(|(pair, rules)| {
+                let market = pair.market.clone();
+                (pair, (market, rules))
+            })
+            .unzip();
+
+        Self {
+            wallet: Wallet::new(pairs).with_valuation(venue.valuation()),
+            positions: Positions::new(sender),
+            pending: sync::Mutex::new(Vec::new()),
+            dust: Mutex::new(HashMap::new()),
+            markets: markets.iter().map(|market| market.to_string()).collect(),
+            rules,
+            risk: Mutex::new(Self::default_risk(start)),
+            sizer: Mutex::new(Box::new(FixedFraction(0.4))),
+            start,
+            order_books: false,
+            book_recorder: None,
+            timer: 1000 * 60,
+            entry: Entry::Market,
+            fees: venue.fees(),
+            top_up: None,
+            fee_reserve: None,
+            feeds: Vec::new(),
+            venue,
+        }
+    }
+
+    /// Two positions at a time, no more than one position's worth of an asset, a fifth of the
+    /// value kept as cash, a day's pause after two losses in a row and 12 hours between entries in
+    /// a market.
+    fn default_risk(start: i64) -> RiskManager {
+        let mut risk = RiskManager::new()
+            .with_max_open_positions(2)
+            .with_max_exposure(0.4)
+            .with_cash_reserve(0.2)
+            .with_cool_down(2, 1000 * 60 * 60 * 24)
+            .with_entry_cool_down(1000 * 60 * 60 * 12);
+        risk.pause_until(start);
+        risk
+    }
+
+    /// Replaces the default risk rules. Nothing is bought before the server time at startup.
+    pub fn with_risk(mut self, mut risk: RiskManager) -> Self {
+        risk.pause_until(self.start);
+        self.risk = Mutex::new(risk);
+        self
+    }
+
+    /// Values the wallet, and with it all sizes and limits, in `asset` instead of the default of
+    /// the venue.
+    pub fn with_valuation(mut self, asset: &str) -> Self {
+        self.wallet = self.wallet.with_valuation(asset);
+        self
+    }
+
+    /// Replaces the default sizing, which invests 40% of the total value in every position.
+    /// Values are in the valuation currency.
+    pub fn with_sizer<P: PositionSizer + 'static>(mut self, sizer: P) -> Self {
+        self.sizer = Mutex::new(Box::new(sizer));
+        self
+    }
+
+    /// Replaces the default fees of the venue.
+    pub fn with_fees(mut self, fees: Fees) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    /// Keeps the fee asset of the venue funded. It is bought with the valuation currency.
+    pub fn with_fee_top_up(mut self, top_up: TopUp) -> Self {
+        self.top_up = Some(top_up);
+        self
+    }
+
+    /// The value of the fee asset, in the valuation currency, that is kept to pay fees with and
+    /// doesn't count as invested. Defaults to the most the fee top up keeps, or nothing without
+    /// one.
+    pub fn with_fee_reserve(mut self, value: Decimal) -> Self {
+        self.fee_reserve = Some(value);
+        self
+    }
+
+    /// Places entries of the given kind. Limit entries rest in the book while events are
+    /// processed, and are protected once they are filled or timed out.
+    pub fn with_entry(mut self, entry: Entry) -> Self {
+        self.entry = entry;
+        self
+    }
+
+    /// Subscribes to the order books of all markets and passes their updates to the strategy.
+    pub fn with_order_books(mut self) -> Self {
+        self.order_books = true;
+        self
+    }
+
+    /// Like `with_order_books`, but also appends every update to the given file for replay with
+    /// `Historical::with_books`.
+    pub fn record_order_books<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let recorder = BookRecorder::new(path).expect("Couldn't open order book recording.");
+        self.book_recorder = Some(Mutex::new(recorder));
+        self.with_order_books()
+    }
+
+    /// Sends the strategy a timer event every `timer` milliseconds, at multiples of it. Defaults to
+    /// a minute.
+    pub fn with_timer(mut self, timer: i64) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// Also passes the trades of `feed` in `markets` to the strategy, for signals across venues.
+    /// Orders still go to the venue only. Trades are merged in the order they arrive, which is
+    /// their timestamp order up to the latency of each venue.
+    pub fn with_feed<F: Venue>(mut self, feed: F, markets: &[&str]) -> Self {
+        self.feeds.push((
+            Box::new(feed),
+            markets.iter().map(|market| market.to_string()).collect(),
+        ));
+        self
+    }
+}
+
+#[async_trait]
+impl<V: Venue, S: Strategy + 'static> Exchange<S> for Live<V> {
+    async fn run(mut self, strategy: &mut S) {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        tokio::join!(
+            self.produce_trades(tx.clone()),
+            self.produce_feeds(tx.clone()),
+            self.produce_books(tx.clone()),
+            self.produce_timer(tx.clone()),
+            self.produce_entries(tx),
+            self.consume_trades(rx, strategy),
+        );
+    }
+}
+
+impl<V: Venue> Live<V> {
+    fn rules(&self, market: &Market) -> Result<&Rules, Error> {
+        self.rules
+            .get(market)
+            .ok_or(Error::Filter(FilterError::NotListed))
+    }
+
+    async fn produce_trades(&self, tx: UnboundedSender<MarketEvent>) {
+        loop {
+            if let Ok(mut stream) = self.venue.trades(&self.markets).await {
+                log::info!("Trade stream started!");
+                while let Ok(Some(Ok(mut trade))) = timeout(
+                    Duration::from_secs(if self.venue.is_sandbox() { 500 } else { 5 }),
+                    stream.next(),
+                )
+                .await
+                {
+                    trade.venue = Some(self.venue.name().to_owned());
+                    self.trade(trade, &tx).await;
+                }
+                log::warn!("Message timeout, trying to reconnect.");
+            } else {
+                log::warn!("Unable to reach websocket, trying to reconnect.");
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    async fn produce_feeds(&self, tx: UnboundedSender<MarketEvent>) {
+        join_all(
+            self.feeds
+                .iter()
+                .map(|(feed, markets)| Self::produce_feed(feed.as_ref(), markets, tx.clone())),
+        )
+        .await;
+    }
+
+    /// Passes on the trades of a feed. They are market data only, so positions aren't touched.
+    async fn produce_feed(feed: &dyn Venue, markets: &[Market], tx: UnboundedSender<MarketEvent>) {
+        loop {
+            if let Ok(mut stream) = feed.trades(markets).await {
+                log::info!("Trade stream of {} started!", feed.name());
+                while let Ok(Some(Ok(mut trade))) = timeout(
+                    Duration::from_secs(if feed.is_sandbox() { 500 } else { 5 }),
+                    stream.next(),
+                )
+                .await
+                {
+                    trade.venue = Some(feed.name().to_owned());
+                    tx.send(MarketEvent::Trade(trade)).unwrap();
+                }
+                log::warn!("Message timeout of {}, trying to reconnect.", feed.name());
+            } else {
+                log::warn!("Unable to reach {}, trying to reconnect.", feed.name());
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    /// Settles the exits `trade` reached and passes it on.
+    async fn trade(&self, trade: Trade, tx: &UnboundedSender<MarketEvent>) {
+        let (market, price, timestamp) = (trade.market.clone(), trade.price, trade.timestamp);
+
+        let stop_loss = self
+            .positions
+            .get(&market)
+            .await
+            .map(|position| position.stop_loss);
+        let closed = match self.positions.sell_legs(&market, price, &self.fees).await {
+            Some(SoldLegs::Moved(position)) => {
+                // Until the stop loss is moved up, the rest stays protected at the old one.
+                let previous = Position {
+                    stop_loss: stop_loss.unwrap_or(position.stop_loss),
+                    ..position.clone()
+                };
+                if let Err(err) = self.replace_exits(&previous, &position).await {
+                    log::error!("Couldn't replace exits: {:#?}", err);
+                }
+                None
+            }
+            Some(SoldLegs::Closed(position)) => Some(position),
+            None => None,
+        };
+        let closed = match closed {
+            Some(position) => Some(position),
+            None => self.positions.check(&market, price, &self.fees).await,
+        };
+        if let Some(position) = &closed {
+            let profitable = position.profitable == Some(true);
+            if profitable {
+                log::info!("Last trade was profitable.");
+            } else {
+                log::info!("Last trade was unprofitable.");
+                if !self.venue.stop_orders() {
+                    if let Err(err) = self.stop_out(position).await {
+                        log::error!("Couldn't sell at stop loss: {:#?}", err);
+                    }
+                }
+            }
+            self.risk.lock().unwrap().closed(profitable, timestamp);
+        }
+
+        tx.send(MarketEvent::Trade(trade)).unwrap();
+        if let Some(position) = closed {
+            tx.send(MarketEvent::PositionClosed(ClosedPosition {
+                market,
+                buy_price: position.buy_price,
+                sell_price: price,
+                timestamp,
+            }))
+            .unwrap();
+        }
+    }
+
+    /// Sells a position whose stop loss was reached on a venue that doesn't rest stop losses.
+    async fn stop_out(&self, position: &Position) -> Result<(), Error> {
+        let quantity = self.rules(&position.market)?.quantity(position.quantity)?;
+
+        log::info!(
+            "Stop loss of {} reached, selling {}.",
+            position.market,
+            quantity
+        );
+        // The take profit locks the quantity, so it has to be cancelled first. If the sale fails,
+        // it is placed again.
+        self.venue.cancel_all(&position.market).await?;
+        if let Err(err) = self.venue.market_sell(&position.market, quantity).await {
+            self.restore_exits(position).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn produce_timer(&self, tx: UnboundedSender<MarketEvent>) {
+        let mut timer = interval(Duration::from_millis(self.timer as u64));
+        // The first tick completes right away.
+        timer.tick().await;
+
+        loop {
+            timer.tick().await;
+            let now = Utc::now().timestamp_millis();
+            tx.send(MarketEvent::Timer(now / self.timer * self.timer))
+                .unwrap();
+        }
+    }
+
+    /// Protects resting entries once they are filled or timed out, and tells the strategy.
+    async fn produce_entries(&self, tx: UnboundedSender<MarketEvent>) {
+        loop {
+            sleep(Duration::from_secs(1)).await;
+
+            // The venue is asked without holding the lock.
+            let resting = self
+                .pending
+                .lock()
+                .await
+                .iter()
+                .filter(|entry| !entry.settling)
+                .map(|entry| (entry.venue_order.clone(), entry.deadline))
+                .collect::<Vec<_>>();
+            for (venue_order, deadline) in resting {
+                let venue_order = match self.venue.get_order(&venue_order).await {
+                    Ok(venue_order) => venue_order,
+                    Err(err) => {
+                        log::warn!("Couldn't get entry order: {:#?}", err);
+                        venue_order
+                    }
+                };
+                if venue_order.open {
+                    if Instant::now() < deadline {
+                        if let Some(entry) = self
+                            .pending
+                            .lock()
+                            .await
+                            .iter_mut()
+                            .find(|entry| entry.venue_order.id == venue_order.id)
+                        {
+                            entry.venue_order = venue_order;
+                        }
+                        continue;
+                    }
+                    log::info!(
+                        "Entry order of {} timed out, cancelling it.",
+                        venue_order.market
+                    );
+                }
+
+                let timestamp = Utc::now().timestamp_millis();
+                match self.settle_entry(venue_order, timestamp).await {
+                    Ok(Some(position)) => tx
+                        .send(MarketEvent::OrderFilled(Fill {
+                            market: position.market,
+                            side: crate::Side::Buy,
+                            price: position.buy_price,
+                            timestamp,
+                        }))
+                        .unwrap(),
+                    Ok(None) => {}
+                    Err(err) => log::error!("Couldn't settle entry: {:#?}", err),
+                }
+            }
+        }
+    }
+
+    async fn produce_books(&self, tx: UnboundedSender<MarketEvent>) {
+        if self.order_books {
+            join_all(
+                self.markets
+                    .iter()
+                    .map(|market| self.produce_book(market, tx.clone())),
+            )
+            .await;
+        }
+    }
+
+    async fn produce_book(&self, market: &Market, tx: UnboundedSender<MarketEvent>) {
+        loop {
+            match self.venue.depth(market).await {
+                Ok(mut stream) => {
+                    log::info!("Order book stream for {} started!", market);
+                    let mut book = OrderBook::new(market.clone());
+                    // Diffs received while the book isn't synced, replayed once a snapshot is.
+                    let mut buffer: Vec<BookDiff> = Vec::new();
+                    let mut requested: Option<Instant> = None;
+
+                    while let Ok(Some(Ok(diff))) = timeout(
+                        Duration::from_secs(if self.venue.is_sandbox() { 500 } else { 30 }),
+                        stream.next(),
+                    )
+                    .await
+                    {
+                        buffer.push(diff);
+
+                        if !book.is_synced() {
+                            if buffer.len() > Self::BOOK_BUFFER {
+                                log::warn!("Order book of {} didn't sync, starting over.", market);
+                                buffer.clear();
+                                continue;
+                            }
+                            // Diffs arriving meanwhile wait in the stream.
+                            if requested.map_or(false, |at| at.elapsed() < Self::BOOK_RESYNC) {
+                                continue;
+                            }
+                            requested = Some(Instant::now());
+
+                            match self.venue.order_book(market).await {
+                                // Diffs older than the snapshot are skipped by the order book.
+                                Ok(snapshot)
+                                    if buffer[0].first_update_id <= snapshot.last_update_id + 1 =>
+                                {
+                                    book.snapshot(
+                                        snapshot.last_update_id,
+                                        snapshot.bids,
+                                        snapshot.asks,
+                                    )
+                                }
+                                // Missing diffs between the two, a newer snapshot is taken later.
+                                Ok(_) => {
+                                    log::debug!("Order book of {} older than its diffs.", market);
+                                    continue;
+                                }
+                                Err(err) => {
+                                    log::warn!("Couldn't get order book of {}: {:?}", market, err);
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let mut timestamp = None;
+                        for diff in buffer.drain(..) {
+                            if let Err(gap) = book.update(
+                                diff.first_update_id,
+                                diff.last_update_id,
+                                diff.bids,
+                                diff.asks,
+                            ) {
+                                log::warn!(
+                                    "Order book of {} out of sync, expected update {} but got {}.",
+                                    market,
+                                    gap.expected,
+                                    gap.received
+                                );
+                                timestamp = None;
+                                break;
+                            }
+                            timestamp = Some(diff.timestamp);
+                        }
+                        let timestamp = match timestamp {
+                            Some(timestamp) => timestamp,
+                            None => continue,
+                        };
+
+                        let update = book.top(Self::BOOK_LEVELS, timestamp);
+                        if let Some(recorder) = &self.book_recorder {
+                            if let Err(err) = recorder.lock().unwrap().record(&update) {
+                                log::error!("Couldn't record order book: {}", err);
+                            }
+                        }
+                        tx.send(MarketEvent::BookUpdate(update)).unwrap();
+                    }
+                    log::warn!("Order book message timeout, trying to reconnect.");
+                }
+                Err(Error::Unsupported(feature)) => {
+                    log::warn!("{} has no {}.", self.venue.name(), feature);
+                    return;
+                }
+                Err(_) => {
+                    log::warn!("Unable to reach websocket, trying to reconnect.");
+                    sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    }
+
+    async fn consume_trades<S: Strategy + 'static>(
+        &self,
+        mut rx: UnboundedReceiver<MarketEvent>,
+        strategy: &mut S,
+    ) {
+        while let Some(event) = rx.recv().await {
+            log::trace!("Receiving event: {:?}", event);
+
+            match &event {
+                // Trades of feeds only reach the strategy.
+                MarketEvent::Trade(trade) if trade.venue.as_deref() == Some(self.venue.name()) => {
+                    self.wallet
+                        .update_price(trade.market.clone(), trade.price, trade.timestamp)
+                        .await;
+                    let total_value = self.wallet.total_value().await.to_f64().unwrap();
+                    self.risk
+                        .lock()
+                        .unwrap()
+                        .update(total_value, trade.timestamp);
+                    self.sizer.lock().unwrap().trade(trade);
+                    self.trail_stop(trade).await;
+                }
+                MarketEvent::PositionClosed(position) => {
+                    self.sizer.lock().unwrap().closed(position)
+                }
+                _ => (),
+            }
+
+            // Fills are passed back to the strategy before the next market event.
+            let mut events = vec![event];
+            while let Some(event) = events.pop() {
+                let timestamp = event.timestamp();
+                let account = self.account().await;
+
+                let intents = strategy.event(event, &account);
+                let ready = strategy.is_ready();
+
+                for intent in intents {
+                    log::info!("Processing intent {:?}.", intent);
+                    match self.execute(intent, timestamp, ready).await {
+                        Ok(Some(position)) => events.push(MarketEvent::OrderFilled(Fill {
+                            market: position.market,
+                            side: crate::Side::Buy,
+                            price: position.buy_price,
+                            timestamp,
+                        })),
+                        Ok(None) => {}
+                        Err(err) => log::error!("Error occured during intent: {:#?}", err),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Entries are only placed once the strategy is `ready`, exits are always managed.
+    async fn execute(
+        &self,
+        intent: Intent,
+        timestamp: i64,
+        ready: bool,
+    ) -> Result<Option<Position>, Error> {
+        match intent {
+            Intent::Place(order) if ready => return self.order(order, timestamp).await,
+            Intent::Place(order) => {
+                log::warn!("Strategy hasn't warmed up, not entering {}.", order.market)
+            }
+            Intent::Cancel(market) => return self.cancel_entry(&market, timestamp).await,
+            Intent::AmendStop { market, stop_loss } => self.amend_stop(&market, stop_loss).await?,
+            Intent::TrailStop { market, trail } => {
+                self.positions
+                    .update(&market, |position| position.stops.trail(trail))
+                    .await
+            }
+            Intent::BreakEven { market, after } => {
+                self.positions
+                    .update(&market, |position| position.stops.break_even(after))
+                    .await
+            }
+            Intent::Close { market, fraction } => self.close(&market, fraction).await?,
+        }
+
+        Ok(None)
+    }
+
+    /// Open positions, and resting entries as open orders.
+    async fn account(&self) -> Account {
+        let mut account = self.positions.account().await;
+        account.orders = self
+            .pending
+            .lock()
+            .await
+            .iter()
+            .map(|entry| entry.intent.clone())
+            .collect();
+        account
+    }
+
+    /// Cancels the resting entry in `market` and protects what of it was filled.
+    async fn cancel_entry(
+        &self,
+        market: &Market,
+        timestamp: i64,
+    ) -> Result<Option<Position>, Error> {
+        let venue_order = match self
+            .pending
+            .lock()
+            .await
+            .iter()
+            .find(|entry| entry.order.market == *market && !entry.settling)
+        {
+            Some(entry) => entry.venue_order.clone(),
+            None => {
+                log::debug!("No open entry orders in {}.", market);
+                return Ok(None);
+            }
+        };
+
+        log::info!("Cancelling entry order of {}.", market);
+        self.settle_entry(venue_order, timestamp).await
+    }
+
+    /// Cancels what of a resting entry is still open and protects what was filled. The entry
+    /// stays pending meanwhile, so its market isn't entered again, but it is settled only once.
+    async fn settle_entry(
+        &self,
+        venue_order: VenueOrder,
+        timestamp: i64,
+    ) -> Result<Option<Position>, Error> {
+        let entry = match self
+            .pending
+            .lock()
+            .await
+            .iter_mut()
+            .find(|entry| entry.venue_order.id == venue_order.id && !entry.settling)
+        {
+            Some(entry) => {
+                entry.settling = true;
+                entry.clone()
+            }
+            None => return Ok(None),
+        };
+
+        let venue_order = if venue_order.open {
+            match self.cancel_resting(&venue_order).await {
+                Ok(venue_order) => venue_order,
+                Err(err) => {
+                    // Still resting, it is tried again later.
+                    if let Some(entry) = self
+                        .pending
+                        .lock()
+                        .await
+                        .iter_mut()
+                        .find(|entry| entry.venue_order.id == venue_order.id)
+                    {
+                        entry.settling = false;
+                    }
+                    return Err(err);
+                }
+            }
+        } else {
+            venue_order
+        };
+
+        let id = venue_order.id.clone();
+        let result = self
+            .fill_entry(
+                PendingEntry {
+                    venue_order,
+                    ..entry
+                },
+                timestamp,
+            )
+            .await;
+        self.pending
+            .lock()
+            .await
+            .retain(|entry| entry.venue_order.id != id);
+        result
+    }
+
+    async fn cancel_resting(&self, order: &VenueOrder) -> Result<VenueOrder, Error> {
+        self.venue.cancel_order(order).await?;
+        self.venue.get_order(order).await
+    }
+
+    /// Protects what of a resting entry was filled. It was in the book, so it paid maker fees.
+    async fn fill_entry(
+        &self,
+        entry: PendingEntry,
+        timestamp: i64,
+    ) -> Result<Option<Position>, Error> {
+        match self.protect(entry.order, entry.venue_order).await? {
+            Some(position) => Ok(Some(self.open(position, Liquidity::Maker, timestamp).await)),
+            None => Ok(None),
+        }
+    }
+
+    /// Books a protected position whose entry paid fees as `liquidity`.
+    async fn open(&self, mut position: Position, liquidity: Liquidity, timestamp: i64) -> Position {
+        position.fees = self
+            .fees
+            .fee(liquidity, position.quantity * position.buy_price);
+
+        self.risk
+            .lock()
+            .unwrap()
+            .opened(&position.market, timestamp);
+        self.positions.open(position.clone()).await;
+        position
+    }
+
+    /// Moves up the stop loss of positions with trailing or break-even stops. To not replace the
+    /// exits on every trade, the stop loss is only moved once it would rise by at least 0.1%.
+    async fn trail_stop(&self, trade: &Trade) {
+        let mut new_stop_loss = None;
+        self.positions
+            .update(&trade.market, |position| {
+                if position.stops.is_active() {
+                    new_stop_loss = position
+                        .stops
+                        .update(trade, Some(position.stop_loss))
+                        .filter(|stop_loss| {
+                            *stop_loss >= position.stop_loss * Decimal::new(1001, 3)
+                        });
+                }
+            })
+            .await;
+
+        if let Some(stop_loss) = new_stop_loss {
+            if let Err(err) = self.amend_stop(&trade.market, stop_loss).await {
+                log::error!("Couldn't move stop loss: {:#?}", err);
+            }
+        }
+    }
+
+    async fn amend_stop(&self, market: &Market, stop_loss: Decimal) -> Result<(), Error> {
+        let position = match self.positions.get(market).await {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        log::info!("Moving stop loss of {} to {}.", market, stop_loss);
+        self.replace_exits(
+            &position,
+            &Position {
+                stop_loss,
+                ..position.clone()
+            },
+        )
+        .await?;
+        self.positions.adjust(market, stop_loss).await;
+
+        Ok(())
+    }
+
+    async fn close(&self, market: &Market, fraction: Decimal) -> Result<(), Error> {
+        let position = match self.positions.get(market).await {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let rules = self.rules(market)?;
+        let mut quantity = rules.quantity(position.quantity * fraction.min(Decimal::one()))?;
+        // A rest too small to be sold or protected on its own is sold along with it.
+        let rest = position.quantity - quantity;
+        if rest > Decimal::zero()
+            && rules
+                .quantity(rest)
+                .and_then(|rest| rules.stop_prices(position.stop_loss, rest))
+                .is_err()
+        {
+            quantity = rules.quantity(position.quantity)?;
+        }
+
+        log::info!("Selling {} of {}.", quantity, market);
+        // The exits lock the quantity, so they have to be cancelled first. Until the sale went
+        // through, the position is protected by the same exits as before.
+        self.venue.cancel_all(market).await?;
+        let sell = match self.venue.market_sell(market, quantity).await {
+            Ok(sell) => sell,
+            Err(err) => {
+                self.restore_exits(&position).await;
+                return Err(err);
+            }
+        };
+        let price = sell.price.unwrap_or(position.buy_price);
+        let fee = self.fees.fee(Liquidity::Taker, quantity * price);
+
+        if let Some(mut reduced) = self.positions.reduce(market, quantity, fee).await {
+            // The legs shrank with the position, they may be off the lot size or too small to sell.
+            rules.round_legs(&mut reduced.legs, reduced.quantity, reduced.stop_loss);
+            let legs = reduced.legs.clone();
+            self.positions
+                .update(market, |position| position.legs = legs)
+                .await;
+            if let Err(err) = self.place_exits(&reduced).await {
+                // What is left is protected by a single exit at the take profit and stop loss the
+                // position had before the sale.
+                self.restore_exits(&Position {
+                    quantity: reduced.quantity,
+                    legs: Vec::new(),
+                    ..position
+                })
+                .await;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Protects a position with one exit per take profit leg, and one for the rest of it.
+    async fn place_exits(&self, position: &Position) -> Result<(), Error> {
+        let rules = self.rules(&position.market)?;
+        let rest = position.quantity
+            - position
+                .legs
+                .iter()
+                .map(|leg| leg.quantity)
+                .sum::<Decimal>();
+        let rest = if rest > Decimal::zero() {
+            Some(rules.quantity(rest)?)
+        } else {
+            None
+        };
+        let exits = position
+            .legs
+            .iter()
+            .map(|leg| (leg.quantity, leg.price))
+            .chain(rest.map(|rest| (rest, position.take_profit)));
+
+        for (quantity, take_profit) in exits {
+            let (stop_price, stop_limit_price) = rules.stop_prices(position.stop_loss, quantity)?;
+            self.venue
+                .place_exit(&Exit {
+                    market: position.market.clone(),
+                    quantity,
+                    take_profit,
+                    stop_price,
+                    stop_limit_price,
+                })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Cancels the open orders of a position and places new ones for what is left of it. If they
+    /// can't be placed, the exits of `previous` are placed again.
+    async fn replace_exits(&self, previous: &Position, position: &Position) -> Result<(), Error> {
+        self.venue.cancel_all(&position.market).await?;
+        if let Err(err) = self.place_exits(position).await {
+            self.restore_exits(previous).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Places the exits of `position` again after replacing them failed, so it isn't left
+    /// unprotected. Exits that were placed in the meantime are cancelled first.
+    async fn restore_exits(&self, position: &Position) {
+        log::warn!("Restoring exits of {}.", position.market);
+
+        let restored = match self.venue.cancel_all(&position.market).await {
+            Ok(()) => self.place_exits(position).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = restored {
+            log::error!(
+                "Couldn't restore exits, {} is unprotected: {:#?}",
+                position.market,
+                err
+            );
+        }
+    }
+
+    fn fee_reserve(&self) -> Decimal {
+        self.fee_reserve.unwrap_or_else(|| {
+            self.top_up
+                .map_or(Decimal::zero(), |top_up| top_up.min + top_up.amount)
+        })
+    }
+
+    /// Buys the fee asset worth the top up amount once its value falls below the minimum.
+    async fn top_up(&self, now: i64) -> Result<(), Error> {
+        let (top_up, asset) = match (self.top_up, self.venue.fee_asset()) {
+            (Some(top_up), Some(asset)) => (top_up, asset),
+            _ => return Ok(()),
+        };
+        if self.wallet.value(asset).await >= top_up.min {
+            return Ok(());
+        }
+
+        let market = format!("{}{}", asset, self.wallet.valuation());
+        let price = match self.wallet.price(asset).await {
+            Some(price) if self.wallet.pair(&market).is_some() => price,
+            _ => {
+                log::warn!("Can't buy {} in {}.", asset, market);
+                return Ok(());
+            }
+        };
+
+        let size = self.rules(&market)?.quantity(top_up.amount / price)?;
+        log::info!("Topping up {} {}.", size, asset);
+        self.venue.buy(&market, size, price, Entry::Market).await?;
+
+        self.wallet.update(&self.venue, now).await
+    }
+
+    async fn order(&self, order: Order, timestamp: i64) -> Result<Option<Position>, Error> {
+        log::info!("Requesting order {}.", order);
+        if self
+            .pending
+            .lock()
+            .await
+            .iter()
+            .any(|entry| entry.order.market == order.market)
+        {
+            log::info!("Entry order of {} is still resting.", order.market);
+            return Ok(None);
+        }
+
+        // E.g. markets of feeds, nothing is requested from the venue for them.
+        let pair = match self.wallet.pair(&order.market) {
+            Some(pair) if self.rules.contains_key(&order.market) => pair.clone(),
+            _ => {
+                log::warn!("Market {} isn't listed.", order.market);
+                return Ok(None);
+            }
+        };
+
+        self.wallet.update(&self.venue, timestamp).await?;
+        if let Err(err) = self.top_up(timestamp).await {
+            log::error!("Couldn't top up fee asset: {:#?}", err);
+        }
+        for holding in self.wallet.breakdown(timestamp).await {
+            match holding.price {
+                None => log::warn!("Can't value {}, it is left out.", holding.asset),
+                Some(_) if holding.stale => log::warn!("Price of {} is stale.", holding.asset),
+                Some(price) => log::trace!(
+                    "{}: {} free, {} locked at {}, worth {} {}",
+                    holding.asset,
+                    holding.free,
+                    holding.locked,
+                    price,
+                    holding.value,
+                    self.wallet.valuation()
+                ),
+            }
+        }
+        let quote_price = match self.wallet.price(&pair.quote).await {
+            Some(price) => price,
+            None => {
+                log::warn!("Can't value {} in {}.", pair.quote, self.wallet.valuation());
+                return Ok(None);
+            }
+        };
+
+        // Dust too small to be sold and the fee asset kept to pay fees with don't count as being
+        // invested. Both are in the valuation currency.
+        let dust = self.rules(&order.market)?.min_notional * quote_price;
+        let held = if self.venue.fee_asset() == Some(pair.base.as_str()) {
+            dust + self.fee_reserve()
+        } else {
+            dust
+        };
+        let exposure = self.wallet.value(pair.base.clone()).await - held;
+        let portfolio = Portfolio {
+            total_value: self.wallet.total_value().await.to_f64().unwrap(),
+            cash: self.wallet.free_value(&pair.quote).await.to_f64().unwrap(),
+            open_positions: self.positions.len().await + self.pending.lock().await.len(),
+            exposure: exposure.max(Decimal::zero()).to_f64().unwrap(),
+        };
+
+        let size = {
+            let risk = self.risk.lock().unwrap();
+            if risk.check(&order, &portfolio, timestamp).is_err() {
+                return Ok(None);
+            }
+
+            let available = risk.available(&portfolio);
+            let sizer = self.sizer.lock().unwrap();
+            match sizing::investment(&**sizer, &order, &portfolio, available) {
+                Some(size) => size,
+                None => return Ok(None),
+            }
+        };
+        log::info!(
+            "Total value is {}, available {}, investing {}",
+            portfolio.total_value,
+            portfolio.cash,
+            size
+        );
+
+        // In the base asset, leaving room for the fee.
+        let quantity =
+            Decimal::from_f64(size).unwrap() / quote_price / order.price * Decimal::new(99, 2);
+        log::info!("Placing order of size {}", quantity);
+        let filtered_order =
+            self.rules(&order.market)?
+                .apply(order.clone(), &pair, quantity, self.entry)?;
+
+        // Entries filled as they are placed took liquidity, whatever their kind.
+        match self.enter(order, filtered_order).await? {
+            Some(position) => Ok(Some(self.open(position, Liquidity::Taker, timestamp).await)),
+            None => Ok(None),
+        }
+    }
+
+    #[cfg(feature = "stop-orders")]
+    async fn enter(&self, _intent: Order, order: FilteredOrder) -> Result<Option<Position>, Error> {
+        Ok(Some(Position {
+            market: order.market,
+            base: order.base,
+            quote: order.quote,
+            quantity: order.quantity,
+            buy_price: order.buy_price,
+            take_profit: order.take_profit_price,
+            stop_loss: order.stop_price,
+            legs: Vec::new(),
+            stops: Stops::new(order.buy_price),
+            profitable: None,
+            fees: Decimal::zero(),
+            timestamp: Utc::now(),
+        }))
+    }
+
+    /// Places the entry order and protects whatever of it was filled. Limit entries that don't
+    /// fill right away rest in the book and are protected by `produce_entries`.
+    #[cfg(not(feature = "stop-orders"))]
+    async fn enter(&self, intent: Order, order: FilteredOrder) -> Result<Option<Position>, Error> {
+        log::info!("FilteredOrder: {:#?}", order);
+
+        if order.quantity <= Decimal::zero() {
+            log::info!("Balance not sufficient.");
+            return Ok(None);
+        }
+
+        log::info!("Placing {:?} entry order.", self.entry);
+
+        let size = order.quantity * Decimal::new(999, 3);
+
+        let buy_order = self
+            .venue
+            .buy(&order.market, size, order.buy_price, self.entry)
+            .await?;
+        if let (Entry::Limit { timeout } | Entry::PostOnly { timeout }, true) =
+            (self.entry, buy_order.open)
+        {
+            log::info!("Entry order of {} rests in the book.", order.market);
+            self.pending.lock().await.push(PendingEntry {
+                intent,
+                order,
+                venue_order: buy_order,
+                deadline: Instant::now() + Duration::from_millis(timeout as u64),
+                settling: false,
+            });
+            return Ok(None);
+        }
+
+        log::info!("Placing entry order was successful!");
+        self.protect(order, buy_order).await
+    }
+
+    /// Places exits for whatever of an entry order was filled.
+    async fn protect(
+        &self,
+        order: FilteredOrder,
+        buy_order: VenueOrder,
+    ) -> Result<Option<Position>, Error> {
+        let filled = buy_order.filled;
+        if filled <= Decimal::zero() {
+            log::info!("Entry order was killed.");
+            return Ok(None);
+        }
+        if filled >= buy_order.size {
+            log::info!("Entry order was filled.");
+        } else {
+            log::info!(
+                "Entry order was partially filled: {} of {}.",
+                filled,
+                buy_order.size
+            );
+        }
+
+        // Partial fills can be too small to sell, then they are kept as dust until the next fill
+        // in the market.
+        let rules = self.rules(&order.market)?;
+        let filled = filled
+            + self
+                .dust
+                .lock()
+                .unwrap()
+                .remove(&order.market)
+                .unwrap_or_default();
+        let quantity = match rules.quantity(filled).and_then(|quantity| {
+            rules.stop_prices(order.stop_limit_price, quantity)?;
+            Ok(quantity)
+        }) {
+            Ok(quantity) => quantity,
+            Err(err) => {
+                log::warn!(
+                    "Can't protect filled quantity {}, keeping it as dust: {:?}",
+                    filled,
+                    err
+                );
+                self.dust.lock().unwrap().insert(order.market, filled);
+                return Ok(None);
+            }
+        };
+        if filled > quantity {
+            self.dust
+                .lock()
+                .unwrap()
+                .insert(order.market.clone(), filled - quantity);
+        }
+
+        let position = Position {
+            market: order.market,
+            base: order.base,
+            quote: order.quote,
+            quantity,
+            buy_price: order.buy_price,
+            take_profit: order.take_profit_price,
+            stop_loss: order.stop_limit_price,
+            legs: rules.legs(&order.tranches, quantity, order.stop_limit_price),
+            stops: Stops::new(order.buy_price),
+            profitable: None,
+            fees: Decimal::zero(),
+            timestamp: Utc::now(),
+        };
+
+        log::info!("Placing exits.");
+        self.place_exits(&position).await?;
+        log::info!("Placing exits was successful!");
+
+        Ok(Some(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loggers::Message, Account, Side, Tranche};
+    use mock::Mock;
+    use std::fmt;
+
+    /// Buys on the first trade, or on every trade without a position if `again` is set, `discount`
+    /// below the price of the trade, sells the given fraction of the position at the price of
+    /// `close`, and records the fills and closed positions it is told about. Trades of other venues
+    /// than the mock are only recorded.
+    #[derive(Default)]
+    struct Scripted {
+        again: bool,
+        discount: i64,
+        close: Option<(i64, Decimal)>,
+        tranches: Vec<Tranche>,
+        placed: bool,
+        log: Vec<String>,
+        feed: Vec<String>,
+        timers: usize,
+    }
+
+    impl Strategy for Scripted {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            None
+        }
+
+        fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+            match event {
+                MarketEvent::Trade(trade) if trade.venue.as_deref() != Some("Mock") => {
+                    self.feed.push(format!(
+                        "{} {} at {}",
+                        trade.venue.unwrap_or_default(),
+                        trade.market,
+                        trade.price
+                    ));
+                    Vec::new()
+                }
+                MarketEvent::Trade(trade)
+                    if account.position(&trade.market).is_none()
+                        && (self.again || !self.placed) =>
+                {
+                    self.placed = true;
+                    vec![Intent::Place(Order {
+                        market: trade.market,
+                        price: trade.price - Decimal::new(self.discount, 0),
+                        take_profit: Some(trade.price * Decimal::new(12, 1)),
+                        stop_loss: Some(trade.price * Decimal::new(9, 1)),
+                        tranches: self.tranches.clone(),
+                        size: None,
+                        side: Side::Buy,
+                    })]
+                }
+                MarketEvent::Trade(trade) => match self.close {
+                    Some((price, fraction)) if trade.price == Decimal::new(price, 0) => {
+                        vec![Intent::Close {
+                            market: trade.market,
+                            fraction,
+                        }]
+                    }
+                    _ => Vec::new(),
+                },
+                MarketEvent::OrderFilled(fill) => {
+                    self.log
+                        .push(format!("filled {} at {}", fill.market, fill.price));
+                    Vec::new()
+                }
+                MarketEvent::Timer(_) => {
+                    self.timers += 1;
+                    Vec::new()
+                }
+                MarketEvent::PositionClosed(position) => {
+                    self.log.push(format!(
+                        "closed {} at {}",
+                        position.market, position.sell_price
+                    ));
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            }
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Scripted {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "scripted")
+        }
+    }
+
+    fn trades(prices: &[i64]) -> Vec<Trade> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| Trade {
+                market: "BTCUSDT".to_owned(),
+                quantity: Decimal::one(),
+                price: Decimal::new(*price, 0),
+                timestamp: (i as i64 + 1) * 1000,
+                venue: None,
+            })
+            .collect()
+    }
+
+    fn mock(prices: &[i64]) -> Mock {
+        Mock::new()
+            .with_market("BTC", "USDT")
+            .with_balance("USDT", 1000)
+            .with_trades(trades(prices))
+    }
+
+    /// Runs the whole live loop until the venue ran out of trades, and returns the messages for
+    /// the loggers in order.
+    async fn run<F: FnOnce(Live<Mock>) -> Live<Mock>>(
+        venue: Mock,
+        strategy: &mut Scripted,
+        configure: F,
+    ) -> Vec<String> {
+        let (sender, mut rx) = mpsc::unbounded_channel();
+        let live = configure(Live::with_sender(venue, &["BTCUSDT"], sender.into()).await);
+        let _ = timeout(Duration::from_secs(60), live.run(strategy)).await;
+
+        let mut messages = Vec::new();
+        while let Ok(message) = rx.try_recv() {
+            messages.push(match message {
+                Message::Open(position) => format!("open {}", position.quantity),
+                Message::Adjust(position) => format!("adjust {}", position.quantity),
+                Message::Close(position) => format!("close {}", position.quantity),
+            });
+        }
+        messages
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn take_profit() {
+        let venue = mock(&[100, 110, 121]);
+        let state = venue.state();
+        let mut strategy = Scripted::default();
+
+        let messages = run(venue, &mut strategy, |live| live).await;
+
+        // 40% of the value, less a percent of room for fees and rounded down to the step size.
+        let state = state.lock().unwrap();
+        assert_eq!(
+            state.log,
+            vec![
+                "Market buy 3.956040 BTCUSDT at 100.02",
+                "exit 3.956 BTCUSDT at 120.00 or 89.98/90.00",
+                "take profit 3.956 BTCUSDT at 120.00",
+            ]
+        );
+        assert_eq!(state.free("USDT").round_dp(2), Decimal::new(107912, 2));
+        assert_eq!(messages, vec!["open 3.956", "close 3.956"]);
+        assert_eq!(
+            strategy.log,
+            vec!["filled BTCUSDT at 100.02", "closed BTCUSDT at 121"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn take_profit_legs() {
+        let venue = mock(&[100, 110, 115]);
+        let state = venue.state();
+        let tranche = |price, stop_loss| Tranche {
+            price: Decimal::new(price, 0),
+            fraction: Decimal::new(5, 1),
+            stop_loss,
+        };
+        let mut strategy = Scripted {
+            tranches: vec![
+                tranche(110, Some(Decimal::new(100, 0))),
+                tranche(115, None),
+            ],
+            ..Scripted::default()
+        };
+
+        let messages = run(venue, &mut strategy, |live| live).await;
+
+        // Selling the last leg closes the position like reaching its take profit.
+        assert_eq!(
+            state.lock().unwrap().log[1..],
+            [
+                "exit 1.978 BTCUSDT at 110.00 or 89.98/90.00",
+                "exit 1.978 BTCUSDT at 115.00 or 89.98/90.00",
+                "take profit 1.978 BTCUSDT at 110.00",
+                "cancel all BTCUSDT",
+                "exit 1.978 BTCUSDT at 115.00 or 99.98/100.00",
+                "take profit 1.978 BTCUSDT at 115.00",
+            ]
+        );
+        assert_eq!(
+            messages,
+            vec!["open 3.956", "adjust 1.978", "adjust 0.000", "close 0.000"]
+        );
+        assert_eq!(
+            strategy.log,
+            vec!["filled BTCUSDT at 100.02", "closed BTCUSDT at 115"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn feed() {
+        let venue = mock(&[100, 110, 121]);
+        let state = venue.state();
+        let mut strategy = Scripted::default();
+
+        let messages = run(venue, &mut strategy, |live| {
+            live.with_feed(
+                Mock::new()
+                    .with_name("Feed")
+                    .with_trades(trades(&[50, 200])),
+                &["BTCUSDT"],
+            )
+        })
+        .await;
+
+        // The feed would have reached both exits, but only the venue settles them.
+        assert_eq!(state.lock().unwrap().log.len(), 3);
+        assert_eq!(messages, vec!["open 3.956", "close 3.956"]);
+        assert_eq!(
+            strategy.log,
+            vec!["filled BTCUSDT at 100.02", "closed BTCUSDT at 121"]
+        );
+        assert_eq!(
+            strategy.feed,
+            vec!["Feed BTCUSDT at 50", "Feed BTCUSDT at 200"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timer() {
+        let mut strategy = Scripted::default();
+
+        run(mock(&[100]), &mut strategy, |live| {
+            live.with_timer(1000 * 25)
+        })
+        .await;
+
+        assert_eq!(strategy.timers, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn close_rejected() {
+        let venue = mock(&[100, 110, 105]).with_failure("sell");
+        let state = venue.state();
+        let mut strategy = Scripted {
+            close: Some((110, Decimal::new(5, 1))),
+            ..Scripted::default()
+        };
+
+        let messages = run(venue, &mut strategy, |live| live).await;
+
+        // The sale failed, so the position is protected by the same exit as before.
+        assert_eq!(
+            state.lock().unwrap().log[1..],
+            [
+                "exit 3.956 BTCUSDT at 120.00 or 89.98/90.00",
+                "cancel all BTCUSDT",
+                "sell 1.978 BTCUSDT",
+                "sell rejected",
+                "cancel all BTCUSDT",
+                "exit 3.956 BTCUSDT at 120.00 or 89.98/90.00",
+            ]
+        );
+        assert_eq!(messages, vec!["open 3.956"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn close_rest() {
+        let venue = mock(&[100, 110, 105]);
+        let state = venue.state();
+        let mut strategy = Scripted {
+            close: Some((110, Decimal::new(99, 2))),
+            ..Scripted::default()
+        };
+
+        let messages = run(venue, &mut strategy, |live| live).await;
+
+        // Less than the minimum notional would be left, so all of it is sold.
+        assert_eq!(
+            state.lock().unwrap().log[2..],
+            ["cancel all BTCUSDT", "sell 3.956 BTCUSDT"]
+        );
+        assert_eq!(messages, vec!["open 3.956", "close 0.000"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_loss_cool_down() {
+        let venue = mock(&[100, 89, 95]);
+        let state = venue.state();
+        let mut strategy = Scripted {
+            again: true,
+            ..Scripted::default()
+        };
+
+        run(venue, &mut strategy, |live| {
+            live.with_risk(RiskManager::new().with_cool_down(1, 1000 * 60 * 60 * 24))
+        })
+        .await;
+
+        // The OCO order sold on its own, and the loss paused trading.
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec![
+                "Market buy 3.956040 BTCUSDT at 100.02",
+                "exit 3.956 BTCUSDT at 120.00 or 89.98/90.00",
+                "stop loss 3.956 BTCUSDT at 90.00",
+            ]
+        );
+        assert_eq!(
+            strategy.log,
+            vec!["filled BTCUSDT at 100.02", "closed BTCUSDT at 89"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn client_side_stop_loss() {
+        let venue = mock(&[100, 95, 89]).without_stop_orders();
+        let state = venue.state();
+        let mut strategy = Scripted::default();
+
+        run(venue, &mut strategy, |live| live).await;
+
+        let state = state.lock().unwrap();
+        assert_eq!(
+            state.log,
+            vec![
+                "Market buy 3.956040 BTCUSDT at 100.02",
+                "exit 3.956 BTCUSDT at 120.00 or 89.98/90.00",
+                "cancel all BTCUSDT",
+                "sell 3.956 BTCUSDT",
+            ]
+        );
+        assert_eq!(state.free("USDT").round_dp(2), Decimal::new(95648, 2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn post_only_rejected() {
+        let venue = mock(&[100, 121]);
+        let state = venue.state();
+        let mut strategy = Scripted::default();
+
+        let messages = run(venue, &mut strategy, |live| {
+            live.with_entry(Entry::PostOnly { timeout: 5000 })
+        })
+        .await;
+
+        // The entry would have crossed the book, so nothing was bought.
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec!["PostOnly { timeout: 5000 } buy 3.956040 BTCUSDT at 100.00"]
+        );
+        assert!(messages.is_empty());
+        assert!(strategy.log.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limit_entry() {
+        let venue = mock(&[100, 100, 99, 121]);
+        let state = venue.state();
+        let mut strategy = Scripted {
+            discount: 1,
+            ..Scripted::default()
+        };
+
+        let messages = run(venue, &mut strategy, |live| {
+            live.with_entry(Entry::Limit { timeout: 5000 })
+        })
+        .await;
+
+        // The entry rests at the order price until a trade reaches it, then it is protected.
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec![
+                "Limit { timeout: 5000 } buy 3.996000 BTCUSDT at 99.00",
+                "fill 3.996000 BTCUSDT at 99.00",
+                "exit 3.996 BTCUSDT at 120.00 or 89.98/90.00",
+                "take profit 3.996 BTCUSDT at 120.00",
+            ]
+        );
+        assert_eq!(messages, vec!["open 3.996", "close 3.996"]);
+        assert_eq!(
+            strategy.log,
+            vec!["filled BTCUSDT at 99.00", "closed BTCUSDT at 121"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn limit_entry_timeout() {
+        let venue = mock(&[100, 101, 102, 103, 104, 105, 106]);
+        let state = venue.state();
+        let mut strategy = Scripted {
+            again: true,
+            discount: 1,
+            ..Scripted::default()
+        };
+
+        let messages = run(venue, &mut strategy, |live| {
+            live.with_entry(Entry::Limit { timeout: 3000 })
+        })
+        .await;
+
+        // Trades are processed while an entry rests, but there is one entry per market at most.
+        // Once it timed out after three seconds, the next trade places a new one.
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec![
+                "Limit { timeout: 3000 } buy 3.996000 BTCUSDT at 99.00",
+                "cancel 1",
+                "Limit { timeout: 3000 } buy 3.878118 BTCUSDT at 102.00",
+                "cancel 2",
+                "Limit { timeout: 3000 } buy 3.767229 BTCUSDT at 105.00",
+                "cancel 3",
+            ]
+        );
+        assert!(messages.is_empty());
+        assert!(strategy.log.is_empty());
+    }
+
+    fn diff(first_update_id: u64, last_update_id: u64, timestamp: i64) -> BookDiff {
+        BookDiff {
+            first_update_id,
+            last_update_id,
+            timestamp,
+            bids: vec![Level {
+                price: Decimal::from(last_update_id),
+                quantity: Decimal::one(),
+            }],
+            asks: Vec::new(),
+        }
+    }
+
+    fn snapshot(last_update_id: u64) -> BookSnapshot {
+        BookSnapshot {
+            last_update_id,
+            bids: vec![Level {
+                price: Decimal::from(last_update_id),
+                quantity: Decimal::one(),
+            }],
+            asks: Vec::new(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn book_sync() {
+        let venue = mock(&[])
+            .with_diffs(vec![
+                diff(5, 6, 1000),
+                diff(7, 8, 2000),
+                diff(9, 9, 3000),
+                diff(11, 11, 4000),
+                diff(12, 12, 5000),
+                diff(14, 14, 6000),
+            ])
+            .with_book(snapshot(3))
+            .with_book(snapshot(8))
+            .with_book(snapshot(13));
+        let state = venue.state();
+        let (sender, _) = mpsc::unbounded_channel();
+        let live = Live::with_sender(venue, &["BTCUSDT"], sender.into()).await;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _ = timeout(
+            Duration::from_secs(60),
+            live.produce_book(&"BTCUSDT".to_owned(), tx),
+        )
+        .await;
+
+        let mut updates = Vec::new();
+        while let Ok(MarketEvent::BookUpdate(update)) = rx.try_recv() {
+            updates.push(format!("{} {}", update.timestamp, update.bids[0].price));
+        }
+
+        // The first snapshot is older than the first diff, the second one is taken two seconds
+        // later and followed by the buffered diffs. The gap before update 11 takes a third one,
+        // again not before two seconds have passed.
+        assert_eq!(state.lock().unwrap().log, vec!["order book BTCUSDT"; 3]);
+        assert_eq!(updates, vec!["3000 9", "5000 13", "6000 14"]);
+    }
+}
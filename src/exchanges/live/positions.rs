@@ -0,0 +1,243 @@
+use crate::{
+    loggers::{Message, Sender},
+    Account, Fees, Liquidity, Market, OpenPosition, Stops,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::*;
+use tokio::sync::Mutex;
+
+/// A take profit tranche of an open position, in base asset.
+#[derive(Debug, Clone)]
+pub struct Leg {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub stop_loss: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub market: Market,
+    /// The assets traded in `market`.
+    pub base: String,
+    pub quote: String,
+    /// What is left of the position.
+    pub quantity: Decimal,
+    pub buy_price: Decimal,
+    pub take_profit: Decimal,
+    pub stop_loss: Decimal,
+    /// Take profit legs that haven't been sold yet, the rest is sold at `take_profit`.
+    pub legs: Vec<Leg>,
+    pub stops: Stops,
+    pub profitable: Option<bool>,
+    /// Fees paid so far, in the quote asset.
+    pub fees: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// What selling take profit legs did to a position.
+#[derive(Debug)]
+pub enum SoldLegs {
+    /// The stop loss of the rest moved, its exits have to be replaced.
+    Moved(Position),
+    /// The last leg was sold, the position is closed.
+    Closed(Position),
+}
+
+pub struct Positions {
+    positions: Mutex<Vec<Position>>,
+    sender: Sender,
+}
+
+impl Positions {
+    pub fn new(sender: Sender) -> Self {
+        Positions {
+            positions: Mutex::new(Vec::new()),
+            sender,
+        }
+    }
+
+    /// Closes the position in `market` if `price` hits its take profit or stop loss.
+    pub async fn check(&self, market: &Market, price: Decimal, fees: &Fees) -> Option<Position> {
+        let mut positions = self.positions.lock().await;
+
+        let mut profitalbe = false;
+        let mut index = None;
+        for (i, position) in positions
+            .iter()
+            .enumerate()
+            .filter(|(_, position)| position.market == *market)
+        {
+            if price <= position.stop_loss {
+                index = Some(i);
+            }
+            if price >= position.take_profit {
+                index = Some(i);
+                profitalbe = true;
+            }
+        }
+
+        if let Some(index) = index {
+            let position = positions.get_mut(index).unwrap();
+            position.profitable = Some(profitalbe);
+            // The take profit rests in the book, the stop loss crosses it once triggered.
+            let liquidity = if profitalbe {
+                Liquidity::Maker
+            } else {
+                Liquidity::Taker
+            };
+            position.fees += fees.fee(liquidity, position.quantity * price);
+
+            log::info!("Closing postion: {:?}", position);
+
+            let position = positions.remove(index);
+            self.sender.send(Message::Close(position.clone()));
+            Some(position)
+        } else {
+            None
+        }
+    }
+
+    pub async fn open(&self, position: Position) {
+        log::info!("Opening postion: {:?}", position);
+        self.sender.send(Message::Open(position.clone()));
+        self.positions.lock().await.push(position);
+    }
+
+    pub async fn len(&self) -> usize {
+        self.positions.lock().await.len()
+    }
+
+    pub async fn get(&self, market: &Market) -> Option<Position> {
+        self.positions
+            .lock()
+            .await
+            .iter()
+            .find(|position| position.market == *market)
+            .cloned()
+    }
+
+    pub async fn update<F: FnOnce(&mut Position)>(&self, market: &Market, f: F) {
+        if let Some(position) = self
+            .positions
+            .lock()
+            .await
+            .iter_mut()
+            .find(|position| position.market == *market)
+        {
+            f(position);
+        }
+    }
+
+    /// Moves the stop loss of the position in `market`. Has to be called after the exits were
+    /// replaced.
+    pub async fn adjust(&self, market: &Market, stop_loss: Decimal) {
+        if let Some(position) = self
+            .positions
+            .lock()
+            .await
+            .iter_mut()
+            .find(|position| position.market == *market)
+        {
+            position.stop_loss = stop_loss;
+
+            log::info!("Adjusting postion: {:?}", position);
+            self.sender.send(Message::Adjust(position.clone()));
+        }
+    }
+
+    /// Marks the take profit legs `price` has reached as sold. Returns the position if its stop
+    /// loss changed, then its remaining exits have to be replaced, or if it was closed.
+    pub async fn sell_legs(
+        &self,
+        market: &Market,
+        price: Decimal,
+        fees: &Fees,
+    ) -> Option<SoldLegs> {
+        let mut positions = self.positions.lock().await;
+        let index = positions
+            .iter()
+            .position(|position| position.market == *market)?;
+        let position = &mut positions[index];
+
+        let mut stop_loss = None;
+        while position
+            .legs
+            .first()
+            .map_or(false, |leg| price >= leg.price)
+        {
+            let leg = position.legs.remove(0);
+            position.quantity -= leg.quantity;
+            position.fees += fees.fee(Liquidity::Maker, leg.quantity * leg.price);
+            stop_loss = leg.stop_loss.or(stop_loss);
+
+            log::info!("Sold take profit leg of postion: {:?}", position);
+            self.sender.send(Message::Adjust(position.clone()));
+        }
+
+        if position.quantity <= Decimal::zero() {
+            position.profitable = Some(true);
+            log::info!("Closing postion: {:?}", position);
+
+            let position = positions.remove(index);
+            self.sender.send(Message::Close(position.clone()));
+            return Some(SoldLegs::Closed(position));
+        }
+
+        let stop_loss = stop_loss?;
+        position.stop_loss = stop_loss;
+        Some(SoldLegs::Moved(position.clone()))
+    }
+
+    /// Removes `quantity` from the position in `market` after a partial sell that cost `fee`, and
+    /// returns what is left of it. Positions that are sold completely are closed.
+    pub async fn reduce(
+        &self,
+        market: &Market,
+        quantity: Decimal,
+        fee: Decimal,
+    ) -> Option<Position> {
+        let mut positions = self.positions.lock().await;
+        let index = positions
+            .iter()
+            .position(|position| position.market == *market)?;
+
+        let position = &mut positions[index];
+        let remaining = position.quantity - quantity;
+        // Legs keep their share of what is left.
+        for leg in &mut position.legs {
+            leg.quantity = leg.quantity * remaining / position.quantity;
+        }
+        position.quantity = remaining;
+        position.fees += fee;
+        if position.quantity > Decimal::zero() {
+            Some(position.clone())
+        } else {
+            log::info!("Closing postion: {:?}", position);
+
+            let position = positions.remove(index);
+            self.sender.send(Message::Close(position));
+            None
+        }
+    }
+
+    pub async fn account(&self) -> Account {
+        Account {
+            positions: self
+                .positions
+                .lock()
+                .await
+                .iter()
+                .map(|position| OpenPosition {
+                    market: position.market.clone(),
+                    quantity: position.quantity,
+                    entry_price: position.buy_price,
+                    take_profit: Some(position.take_profit),
+                    stop_loss: Some(position.stop_loss),
+                    timestamp: position.timestamp.timestamp_millis(),
+                })
+                .collect(),
+            // Resting entries are added by `Live`.
+            orders: Vec::new(),
+        }
+    }
+}
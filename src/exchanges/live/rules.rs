@@ -0,0 +1,353 @@
+use super::Leg;
+use crate::{Entry, Error, Market, Order, Pair, Tranche};
+use rust_decimal::prelude::*;
+
+#[derive(Debug)]
+pub enum FilterError {
+    MinQty,
+    MaxQty,
+    MinPrice,
+    MaxPrice,
+    MinNotional,
+    NoTickSize,
+    NoTakeProfit,
+    NoStopLoss,
+    NotListed,
+}
+
+/// Trading rules of a market, quantities are in the base asset and prices in the quote asset.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    /// Prices are rounded to multiples of it.
+    pub tick_size: Option<Decimal>,
+    pub min_price: Decimal,
+    pub max_price: Option<Decimal>,
+    /// Quantities are rounded down to multiples of it.
+    pub step_size: Option<Decimal>,
+    pub min_quantity: Decimal,
+    pub max_quantity: Option<Decimal>,
+    /// Minimum value of an order.
+    pub min_notional: Decimal,
+}
+
+/// An order that passed the rules of its market.
+#[derive(Debug, Clone)]
+pub struct FilteredOrder {
+    pub market: Market,
+    pub base: String,
+    pub quote: String,
+    pub buy_price: Decimal,
+    pub take_profit_price: Decimal,
+    pub stop_price: Decimal,
+    pub stop_limit_price: Decimal,
+    pub tranches: Vec<Tranche>,
+    /// In the base asset.
+    pub quantity: Decimal,
+}
+
+impl Rules {
+    /// Filters an order for `quantity` of the base asset. Entries that take liquidity are priced
+    /// two ticks above the order price so they fill even if the price moved, entries that rest in
+    /// the book are placed at the order price.
+    pub fn apply(
+        &self,
+        order: Order,
+        pair: &Pair,
+        quantity: Decimal,
+        entry: Entry,
+    ) -> Result<FilteredOrder, Error> {
+        let quantity = self.quantity(quantity)?;
+        let stop_loss = order
+            .stop_loss
+            .ok_or(Error::Filter(FilterError::NoStopLoss))?;
+        let take_profit = order
+            .take_profit
+            .ok_or(Error::Filter(FilterError::NoTakeProfit))?;
+        let (stop_price, stop_limit_price) = self.stop_prices(stop_loss, quantity)?;
+        let slippage = match entry {
+            Entry::Market | Entry::ImmediateOrCancel => Decimal::new(2, 0) * self.tick_size()?,
+            Entry::Limit { .. } | Entry::PostOnly { .. } => Decimal::zero(),
+        };
+
+        Ok(FilteredOrder {
+            market: order.market,
+            base: pair.base.clone(),
+            quote: pair.quote.clone(),
+            buy_price: self.price(order.price, quantity)? + slippage,
+            take_profit_price: self.price(take_profit, quantity)?,
+            stop_price,
+            stop_limit_price,
+            tranches: order
+                .tranches
+                .into_iter()
+                .map(|tranche| {
+                    Ok(Tranche {
+                        price: self.price(tranche.price, quantity)?,
+                        ..tranche
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            quantity,
+        })
+    }
+
+    /// Splits `quantity` into take profit legs. Tranches that would violate the lot size or
+    /// notional limits are left to the final take profit, unless that is too small itself.
+    pub fn legs(&self, tranches: &[Tranche], quantity: Decimal, stop_loss: Decimal) -> Vec<Leg> {
+        let mut legs: Vec<Leg> = tranches
+            .iter()
+            .filter_map(|tranche| {
+                let leg = self
+                    .quantity(quantity * tranche.fraction)
+                    .and_then(|quantity| {
+                        Ok(Leg {
+                            price: self.price(tranche.price, quantity)?,
+                            quantity,
+                            stop_loss: tranche.stop_loss,
+                        })
+                    });
+
+                match leg {
+                    Ok(leg) => Some(leg),
+                    Err(err) => {
+                        log::warn!("Merging tranche {:?} into take profit: {:?}", tranche, err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        self.merge_rest(&mut legs, quantity, stop_loss);
+        legs
+    }
+
+    /// Adds what `legs` leave of `quantity` to the last leg if it is too small to be protected by
+    /// an exit of its own.
+    pub fn merge_rest(&self, legs: &mut [Leg], quantity: Decimal, stop_loss: Decimal) {
+        let rest = quantity - legs.iter().map(|leg| leg.quantity).sum::<Decimal>();
+        if rest <= Decimal::zero() {
+            return;
+        }
+
+        if let Err(err) = self
+            .quantity(rest)
+            .and_then(|rest| self.stop_prices(stop_loss, rest))
+        {
+            if let Some(leg) = legs.last_mut() {
+                log::warn!("Merging take profit {} into the last leg: {:?}", rest, err);
+                leg.quantity += rest;
+            }
+        }
+    }
+
+    /// Rounds legs that shrank with their position to the lot size again. Legs too small to be
+    /// sold on their own are left out, and the rest is merged like in `legs`.
+    pub fn round_legs(&self, legs: &mut Vec<Leg>, quantity: Decimal, stop_loss: Decimal) {
+        *legs = legs
+            .drain(..)
+            .filter_map(|leg| match self.quantity(leg.quantity) {
+                Ok(quantity) if quantity > Decimal::zero() => Some(Leg { quantity, ..leg }),
+                _ => None,
+            })
+            .collect();
+        self.merge_rest(legs, quantity, stop_loss);
+    }
+
+    /// Stop and limit price of the stop loss of an exit.
+    pub fn stop_prices(
+        &self,
+        stop_loss: Decimal,
+        quantity: Decimal,
+    ) -> Result<(Decimal, Decimal), Error> {
+        let stop_limit_price = self.price(stop_loss, quantity)?;
+        let tick = self.tick_size()?;
+
+        Ok((
+            stop_limit_price - Decimal::new(2, 0) * tick,
+            stop_limit_price,
+        ))
+    }
+
+    pub fn tick_size(&self) -> Result<Decimal, Error> {
+        self.tick_size.ok_or(Error::Filter(FilterError::NoTickSize))
+    }
+
+    pub fn quantity(&self, mut quantity: Decimal) -> Result<Decimal, Error> {
+        if let Some(step_size) = self
+            .step_size
+            .filter(|step_size| *step_size > Decimal::zero())
+        {
+            if quantity < self.min_quantity {
+                return Err(Error::Filter(FilterError::MinQty));
+            }
+            if self
+                .max_quantity
+                .map_or(false, |max_quantity| quantity > max_quantity)
+            {
+                return Err(Error::Filter(FilterError::MaxQty));
+            }
+            quantity = (quantity / step_size)
+                .round_dp_with_strategy(0, RoundingStrategy::RoundDown)
+                * step_size;
+        }
+
+        Ok(quantity)
+    }
+
+    pub fn price(&self, mut price: Decimal, quantity: Decimal) -> Result<Decimal, Error> {
+        if let Some(tick_size) = self
+            .tick_size
+            .filter(|tick_size| *tick_size > Decimal::zero())
+        {
+            if price < self.min_price {
+                return Err(Error::Filter(FilterError::MinPrice));
+            }
+            if self.max_price.map_or(false, |max_price| price > max_price) {
+                return Err(Error::Filter(FilterError::MaxPrice));
+            }
+            price = (price / tick_size).round() * tick_size;
+        }
+        if price * quantity < self.min_notional {
+            return Err(Error::Filter(FilterError::MinNotional));
+        }
+
+        Ok(price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Rules {
+        Rules {
+            tick_size: Some(Decimal::new(1, 2)),
+            min_price: Decimal::new(1, 2),
+            max_price: None,
+            step_size: Some(Decimal::new(1, 3)),
+            min_quantity: Decimal::new(1, 3),
+            max_quantity: Some(Decimal::new(1000, 0)),
+            min_notional: Decimal::new(10, 0),
+        }
+    }
+
+    #[test]
+    fn rounding() {
+        let rules = rules();
+        assert_eq!(
+            rules.quantity(Decimal::new(12345, 4)).unwrap(),
+            Decimal::new(1234, 3)
+        );
+        assert_eq!(
+            rules
+                .price(Decimal::new(100006, 3), Decimal::one())
+                .unwrap(),
+            Decimal::new(10001, 2)
+        );
+        assert_eq!(
+            rules
+                .stop_prices(Decimal::new(90, 0), Decimal::one())
+                .unwrap(),
+            (Decimal::new(8998, 2), Decimal::new(9000, 2))
+        );
+
+        assert!(matches!(
+            rules.quantity(Decimal::new(1, 4)),
+            Err(Error::Filter(FilterError::MinQty))
+        ));
+        assert!(matches!(
+            rules.price(Decimal::new(5, 0), Decimal::one()),
+            Err(Error::Filter(FilterError::MinNotional))
+        ));
+        assert!(matches!(
+            Rules::default().tick_size(),
+            Err(Error::Filter(FilterError::NoTickSize))
+        ));
+    }
+
+    #[test]
+    fn merge_rest() {
+        let rules = rules();
+        let tranche = |price, fraction| Tranche {
+            price: Decimal::new(price, 0),
+            fraction: Decimal::new(fraction, 2),
+            stop_loss: None,
+        };
+        let quantities =
+            |legs: Vec<Leg>| legs.into_iter().map(|leg| leg.quantity).collect::<Vec<_>>();
+
+        // 5% of 1.2 is too little to be sold at the stop loss of 90.
+        let legs = rules.legs(
+            &[tranche(110, 50), tranche(115, 45)],
+            Decimal::new(12, 1),
+            Decimal::new(90, 0),
+        );
+        assert_eq!(
+            quantities(legs),
+            vec![Decimal::new(6, 1), Decimal::new(6, 1)]
+        );
+
+        let legs = rules.legs(
+            &[tranche(110, 50), tranche(115, 30)],
+            Decimal::new(12, 1),
+            Decimal::new(90, 0),
+        );
+        assert_eq!(
+            quantities(legs),
+            vec![Decimal::new(6, 1), Decimal::new(36, 2)]
+        );
+    }
+
+    #[test]
+    fn round_legs() {
+        let rules = rules();
+        let leg = |price, quantity| Leg {
+            price: Decimal::new(price, 0),
+            quantity: Decimal::new(quantity, 4),
+            stop_loss: None,
+        };
+
+        // Legs of 0.6 and 0.36 of 1.2 after selling all but 0.701. The rest of 0.141 is too little
+        // to be sold at the stop loss of 60.
+        let mut legs = vec![leg(110, 3505), leg(115, 2103), leg(120, 3)];
+        rules.round_legs(&mut legs, Decimal::new(701, 3), Decimal::new(60, 0));
+        assert_eq!(
+            legs.iter().map(|leg| leg.quantity).collect::<Vec<_>>(),
+            vec![Decimal::new(350, 3), Decimal::new(351, 3)]
+        );
+    }
+
+    #[test]
+    fn entry_price() {
+        let order = Order {
+            market: "BTCUSDT".to_owned(),
+            price: Decimal::new(100004, 3),
+            take_profit: Some(Decimal::new(120, 0)),
+            stop_loss: Some(Decimal::new(90, 0)),
+            tranches: Vec::new(),
+            size: None,
+            side: crate::Side::Buy,
+        };
+        let pair = Pair {
+            market: "BTCUSDT".to_owned(),
+            base: "BTC".to_owned(),
+            quote: "USDT".to_owned(),
+        };
+        let buy_price = |entry| {
+            rules()
+                .apply(order.clone(), &pair, Decimal::one(), entry)
+                .unwrap()
+                .buy_price
+        };
+
+        assert_eq!(buy_price(Entry::Market), Decimal::new(10002, 2));
+        assert_eq!(buy_price(Entry::ImmediateOrCancel), Decimal::new(10002, 2));
+        assert_eq!(
+            buy_price(Entry::Limit { timeout: 0 }),
+            Decimal::new(10000, 2)
+        );
+        assert_eq!(
+            buy_price(Entry::PostOnly { timeout: 0 }),
+            Decimal::new(10000, 2)
+        );
+    }
+}
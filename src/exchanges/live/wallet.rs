@@ -0,0 +1,441 @@
+use super::Venue;
+use crate::{Error, Market, Pair};
+use rust_decimal::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use tokio::sync::Mutex;
+
+type Symbol = String;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Balance {
+    free: Decimal,
+    /// Held by open orders.
+    locked: Decimal,
+}
+
+impl Balance {
+    fn total(&self) -> Decimal {
+        self.free + self.locked
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Price {
+    price: Decimal,
+    timestamp: i64,
+}
+
+/// Markets from an asset to the valuation currency, and whether their price is inverted.
+type Path = Vec<(Market, bool)>;
+
+#[derive(Debug, Default)]
+struct Prices {
+    /// Ordered, so that the same cross rates are used every time.
+    markets: BTreeMap<Market, Price>,
+    /// Shortest path of every asset valued so far. They only change once another market has a
+    /// price, so they are searched again then.
+    paths: HashMap<Symbol, Option<Path>>,
+}
+
+impl Prices {
+    fn insert(&mut self, market: Market, price: Price) {
+        if self.markets.insert(market, price).is_none() {
+            self.paths.clear();
+        }
+    }
+}
+
+/// What an asset in the wallet is worth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub asset: Symbol,
+    pub free: Decimal,
+    pub locked: Decimal,
+    /// Of one unit in the valuation currency, `None` if it couldn't be priced.
+    pub price: Option<Decimal>,
+    pub value: Decimal,
+    /// Whether one of the prices used is older than the maximum age.
+    pub stale: bool,
+}
+
+/// Balances of all assets, valued in a single currency through the last price of every market.
+#[derive(Debug)]
+pub struct Wallet {
+    valuation: Symbol,
+    max_age: i64,
+    pairs: HashMap<Market, Pair>,
+    balances: Mutex<HashMap<Symbol, Balance>>,
+    prices: Mutex<Prices>,
+}
+
+impl Wallet {
+    pub const DEFAULT_VALUATION: &'static str = "USDT";
+
+    pub fn new<I: IntoIterator<Item = Pair>>(pairs: I) -> Self {
+        Self {
+            valuation: Wallet::DEFAULT_VALUATION.to_owned(),
+            max_age: 1000 * 60 * 5,
+            pairs: pairs
+                .into_iter()
+                .map(|pair| (pair.market.clone(), pair))
+                .collect(),
+            balances: Mutex::new(HashMap::new()),
+            prices: Mutex::new(Prices::default()),
+        }
+    }
+
+    /// Values all assets in `asset` instead of USDT.
+    pub fn with_valuation<A: Into<Symbol>>(mut self, asset: A) -> Self {
+        self.valuation = asset.into();
+        self
+    }
+
+    /// Prices older than `max_age` milliseconds are stale and refreshed on the next update. The
+    /// default is 5 minutes.
+    pub fn with_max_age(mut self, max_age: i64) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn valuation(&self) -> &str {
+        &self.valuation
+    }
+
+    pub fn pair(&self, market: &Market) -> Option<&Pair> {
+        self.pairs.get(market)
+    }
+
+    /// Fetches the balances, and the prices of the markets of held assets if one of them has no
+    /// recent price.
+    pub async fn update<V: Venue + ?Sized>(&self, venue: &V, now: i64) -> Result<(), Error> {
+        let balances = venue.balances().await?;
+
+        {
+            let mut wallet = self.balances.lock().await;
+            for balance in balances {
+                wallet.insert(
+                    balance.asset,
+                    Balance {
+                        free: balance.free,
+                        locked: balance.locked,
+                    },
+                );
+            }
+        }
+
+        let breakdown = self.breakdown(now).await;
+        if breakdown
+            .iter()
+            .any(|holding| holding.price.is_none() || holding.stale)
+        {
+            let held: HashSet<&str> = breakdown
+                .iter()
+                .map(|holding| holding.asset.as_str())
+                .collect();
+            let markets: Vec<Market> = self
+                .pairs
+                .values()
+                .filter(|pair| {
+                    held.contains(pair.base.as_str()) || held.contains(pair.quote.as_str())
+                })
+                .map(|pair| pair.market.clone())
+                .collect();
+            let prices = venue.prices(&markets).await?;
+
+            let mut wallet = self.prices.lock().await;
+            for (market, price) in prices {
+                if !self.pairs.contains_key(&market) {
+                    continue;
+                }
+                // Live prices are newer than the ticker unless they went stale.
+                let outdated = wallet
+                    .markets
+                    .get(&market)
+                    .map_or(true, |price| now - price.timestamp > self.max_age);
+                if outdated {
+                    wallet.insert(
+                        market,
+                        Price {
+                            price,
+                            timestamp: now,
+                        },
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prices of markets that aren't listed are ignored.
+    pub async fn update_price(&self, market: Market, price: Decimal, timestamp: i64) {
+        if self.pairs.contains_key(&market) {
+            self.prices
+                .lock()
+                .await
+                .insert(market, Price { price, timestamp });
+        }
+    }
+
+    pub async fn update_quantity(&self, asset: Symbol, quantity: Decimal) {
+        self.update_balance(asset, quantity, Decimal::zero()).await
+    }
+
+    pub async fn update_balance(&self, asset: Symbol, free: Decimal, locked: Decimal) {
+        self.balances
+            .lock()
+            .await
+            .insert(asset, Balance { free, locked });
+    }
+
+    /// The value of one unit of `asset` in the valuation currency, through as few markets as
+    /// possible, e.g. ETH through ETHBTC and BTCUSDT. `None` if no path has prices yet.
+    pub async fn price<A: AsRef<str>>(&self, asset: A) -> Option<Decimal> {
+        let mut prices = self.prices.lock().await;
+        self.rate(&mut prices, asset.as_ref()).map(|(rate, _)| rate)
+    }
+
+    /// The value of all of `asset` in the valuation currency, zero if it can't be priced.
+    pub async fn value<A: AsRef<str>>(&self, asset: A) -> Decimal {
+        self.valued(asset, Balance::total).await
+    }
+
+    /// Like `value`, but without what is held by open orders.
+    pub async fn free_value<A: AsRef<str>>(&self, asset: A) -> Decimal {
+        self.valued(asset, |balance| balance.free).await
+    }
+
+    pub async fn total_value(&self) -> Decimal {
+        let balances = self.balances.lock().await;
+        let mut prices = self.prices.lock().await;
+
+        balances
+            .iter()
+            .filter(|(_, balance)| !balance.total().is_zero())
+            .filter_map(|(asset, balance)| Some(self.rate(&mut prices, asset)?.0 * balance.total()))
+            .sum()
+    }
+
+    /// Every asset held, most valuable first.
+    pub async fn breakdown(&self, now: i64) -> Vec<Holding> {
+        let balances = self.balances.lock().await;
+        let mut prices = self.prices.lock().await;
+
+        let mut holdings: Vec<Holding> = balances
+            .iter()
+            .filter(|(_, balance)| !balance.total().is_zero())
+            .map(|(asset, balance)| {
+                let rate = self.rate(&mut prices, asset);
+                Holding {
+                    asset: asset.clone(),
+                    free: balance.free,
+                    locked: balance.locked,
+                    price: rate.map(|(rate, _)| rate),
+                    value: rate.map_or(Decimal::zero(), |(rate, _)| rate * balance.total()),
+                    stale: rate.map_or(false, |(_, timestamp)| now - timestamp > self.max_age),
+                }
+            })
+            .collect();
+
+        holdings.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.asset.cmp(&b.asset)));
+        holdings
+    }
+
+    async fn valued<A: AsRef<str>, F: Fn(&Balance) -> Decimal>(
+        &self,
+        asset: A,
+        quantity: F,
+    ) -> Decimal {
+        let quantity = self
+            .balances
+            .lock()
+            .await
+            .get(asset.as_ref())
+            .map(quantity)
+            .unwrap_or(Decimal::zero());
+
+        if quantity.is_zero() {
+            return quantity;
+        }
+
+        self.price(asset).await.unwrap_or(Decimal::zero()) * quantity
+    }
+
+    /// The rate of `asset` along its path, and the timestamp of the oldest price it is based on.
+    fn rate(&self, prices: &mut Prices, asset: &str) -> Option<(Decimal, i64)> {
+        if !prices.paths.contains_key(asset) {
+            let path = self.path(&prices.markets, asset);
+            prices.paths.insert(asset.to_owned(), path);
+        }
+
+        prices.paths[asset].as_ref()?.iter().try_fold(
+            (Decimal::one(), i64::MAX),
+            |(rate, timestamp), (market, inverted)| {
+                let Price {
+                    price,
+                    timestamp: updated,
+                } = prices.markets[market];
+                let rate = match inverted {
+                    false => rate * price,
+                    true if price.is_zero() => return None,
+                    true => rate / price,
+                };
+                Some((rate, timestamp.min(updated)))
+            },
+        )
+    }
+
+    /// Breadth first search from `asset` to the valuation currency over markets with a price.
+    fn path(&self, prices: &BTreeMap<Market, Price>, asset: &str) -> Option<Path> {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(asset.to_owned());
+        queue.push_back((asset.to_owned(), Path::new()));
+
+        while let Some((asset, path)) = queue.pop_front() {
+            if asset == self.valuation {
+                return Some(path);
+            }
+
+            for (market, Price { price, .. }) in prices {
+                let pair = &self.pairs[market];
+                let (next, inverted) = if pair.base == asset {
+                    (&pair.quote, false)
+                } else if pair.quote == asset && !price.is_zero() {
+                    (&pair.base, true)
+                } else {
+                    continue;
+                };
+
+                if visited.insert(next.clone()) {
+                    let mut path = path.clone();
+                    path.push((market.clone(), inverted));
+                    queue.push_back((next.clone(), path));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn pair(base: &str, quote: &str) -> Pair {
+        Pair {
+            market: format!("{}{}", base, quote),
+            base: base.to_owned(),
+            quote: quote.to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn wallet() {
+        let wallet = Wallet::new(vec![pair("BTC", "USDT")]);
+        assert_eq!(wallet.total_value().await, Decimal::new(0, 0));
+        wallet
+            .update_quantity("BTC".to_owned(), Decimal::new(1, 0))
+            .await;
+        wallet
+            .update_price("BTCUSDT".to_owned(), Decimal::new(50000, 0), 0)
+            .await;
+        assert_eq!(wallet.total_value().await, Decimal::new(50000, 0));
+        wallet
+            .update_quantity(Wallet::DEFAULT_VALUATION.to_owned(), Decimal::new(10000, 0))
+            .await;
+        assert_eq!(wallet.total_value().await, Decimal::new(60000, 0));
+    }
+
+    #[tokio::test]
+    async fn cross_rates() {
+        let wallet = Wallet::new(vec![
+            pair("BTC", "USDT"),
+            pair("ETH", "BTC"),
+            pair("BTC", "EUR"),
+            pair("ETH", "EUR"),
+        ])
+        .with_valuation("EUR");
+
+        wallet
+            .update_price("BTCUSDT".to_owned(), Decimal::new(50000, 0), 0)
+            .await;
+        wallet
+            .update_price("ETHBTC".to_owned(), Decimal::new(5, 2), 0)
+            .await;
+        wallet
+            .update_price("BTCEUR".to_owned(), Decimal::new(40000, 0), 0)
+            .await;
+        // Unlisted markets don't panic.
+        wallet
+            .update_price("XYZABC".to_owned(), Decimal::new(1, 0), 0)
+            .await;
+
+        wallet
+            .update_quantity("ETH".to_owned(), Decimal::new(2, 0))
+            .await;
+        wallet
+            .update_quantity("USDT".to_owned(), Decimal::new(1000, 0))
+            .await;
+        wallet
+            .update_quantity("EUR".to_owned(), Decimal::new(100, 0))
+            .await;
+
+        assert_eq!(wallet.value("ETH").await, Decimal::new(4000, 0));
+        assert_eq!(wallet.value("USDT").await, Decimal::new(800, 0));
+        assert_eq!(wallet.total_value().await, Decimal::new(4900, 0));
+        assert_eq!(wallet.price("XYZ").await, None);
+
+        // Paths are searched again once another market has a price, others only move the rate.
+        wallet
+            .update_price("ETHEUR".to_owned(), Decimal::new(2500, 0), 0)
+            .await;
+        wallet
+            .update_price("BTCEUR".to_owned(), Decimal::new(30000, 0), 0)
+            .await;
+        assert_eq!(wallet.value("ETH").await, Decimal::new(5000, 0));
+        assert_eq!(wallet.value("USDT").await, Decimal::new(600, 0));
+    }
+
+    #[tokio::test]
+    async fn breakdown() {
+        let wallet = Wallet::new(vec![pair("BTC", "USDT"), pair("ETH", "USDT")]).with_max_age(1000);
+
+        wallet
+            .update_price("BTCUSDT".to_owned(), Decimal::new(50000, 0), 0)
+            .await;
+        wallet
+            .update_price("ETHUSDT".to_owned(), Decimal::new(4000, 0), 1500)
+            .await;
+        wallet
+            .update_balance("BTC".to_owned(), Decimal::new(1, 1), Decimal::new(1, 1))
+            .await;
+        wallet
+            .update_balance("ETH".to_owned(), Decimal::new(1, 0), Decimal::zero())
+            .await;
+        wallet
+            .update_quantity("DOGE".to_owned(), Decimal::new(100, 0))
+            .await;
+
+        // Locked balances count towards the value, but aren't free to invest.
+        assert_eq!(wallet.value("BTC").await, Decimal::new(10000, 0));
+        assert_eq!(wallet.free_value("BTC").await, Decimal::new(5000, 0));
+        assert_eq!(wallet.total_value().await, Decimal::new(14000, 0));
+
+        let breakdown = wallet.breakdown(2000).await;
+        assert_eq!(
+            breakdown
+                .iter()
+                .map(|holding| (holding.asset.as_str(), holding.value, holding.stale))
+                .collect::<Vec<_>>(),
+            vec![
+                ("BTC", Decimal::new(10000, 0), true),
+                ("ETH", Decimal::new(4000, 0), false),
+                ("DOGE", Decimal::zero(), false),
+            ]
+        );
+        assert_eq!(breakdown[2].price, None);
+    }
+}
@@ -0,0 +1,143 @@
+use super::Trade;
+use crate::Market;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    iter::Peekable,
+    vec::IntoIter,
+};
+
+/// Merges streams of trades, each ordered by timestamp, into one ordered by timestamp, then
+/// market, then stream, then position in the stream. The order doesn't depend on how the streams
+/// were produced, so replays are reproducible.
+pub fn merge<I: Iterator<Item = Trade>>(streams: Vec<I>) -> impl Iterator<Item = Trade> {
+    let mut streams = streams
+        .into_iter()
+        .map(Iterator::peekable)
+        .collect::<Vec<Peekable<I>>>();
+    let mut heads = streams
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(i, stream)| head(stream, i))
+        .collect::<BinaryHeap<_>>();
+
+    std::iter::from_fn(move || {
+        let Reverse((_, _, i)) = heads.pop()?;
+        let trade = streams[i].next()?;
+        heads.extend(head(&mut streams[i], i));
+        Some(trade)
+    })
+}
+
+/// Splits trades into one stream per market, keeping their order.
+pub fn by_market(trades: Vec<Trade>) -> Vec<IntoIter<Trade>> {
+    let mut markets: BTreeMap<Market, Vec<Trade>> = BTreeMap::new();
+    for trade in trades {
+        markets.entry(trade.market.clone()).or_default().push(trade);
+    }
+
+    markets.into_values().map(Vec::into_iter).collect()
+}
+
+/// Every stream is in the heap once at most, keyed by its next trade.
+fn head<I: Iterator<Item = Trade>>(
+    stream: &mut Peekable<I>,
+    i: usize,
+) -> Option<Reverse<(i64, Market, usize)>> {
+    let trade = stream.peek()?;
+    Some(Reverse((trade.timestamp, trade.market.clone(), i)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        strategies::{Custom, Duplicated, Interval, Simulated, Strategy},
+        Number,
+    };
+    use rust_decimal::prelude::*;
+
+    fn trades(market: &str, j: usize) -> Vec<Trade> {
+        (0..20000)
+            .map(|i| Trade {
+                market: market.to_owned(),
+                quantity: Decimal::from((i * 7 + j * 3) % 11) - Decimal::new(5, 0),
+                price: Decimal::from_f64(
+                    100.0
+                        + 10.0 * ((i + j * 100) as Number / 300.0).sin()
+                        + ((i * 13 + j) % 17) as Number * 0.1,
+                )
+                .unwrap()
+                .round_dp(4),
+                // Two trades per minute, at the same time in all markets.
+                timestamp: i as i64 / 2 * 1000 * 60,
+                venue: None,
+            })
+            .collect()
+    }
+
+    fn replay(markets: &[(&str, usize)]) -> (Vec<Trade>, String) {
+        let merged = merge(
+            markets
+                .iter()
+                .map(|(market, j)| trades(market, *j).into_iter())
+                .collect(),
+        )
+        .collect::<Vec<Trade>>();
+
+        let mut simulated = Simulated::new(
+            Duplicated::new(Interval::new(Custom::new(), 1000 * 60)),
+            0.001,
+            2,
+        );
+        for trade in merged.iter().cloned() {
+            simulated.run(trade);
+        }
+        simulated.finish();
+
+        (merged, simulated.to_string())
+    }
+
+    #[test]
+    fn total_order() {
+        let merged = merge(vec![
+            trades("ETHUSDT", 0).into_iter().take(3),
+            trades("BTCUSDT", 1).into_iter().take(3),
+            trades("BTCUSDT", 2).into_iter().take(1),
+        ])
+        .map(|trade| format!("{} {} {}", trade.timestamp, trade.market, trade.quantity))
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            merged,
+            vec![
+                "0 BTCUSDT -2",
+                "0 BTCUSDT 5",
+                "0 BTCUSDT 1",
+                "0 ETHUSDT -5",
+                "0 ETHUSDT 2",
+                "60000 BTCUSDT 1",
+                "60000 ETHUSDT -2",
+            ]
+        );
+    }
+
+    #[test]
+    fn reproducible() {
+        let (trades, results) = replay(&[("BTCUSDT", 0), ("ETHUSDT", 1), ("ADAUSDT", 2)]);
+        let (shuffled_trades, shuffled_results) =
+            replay(&[("ADAUSDT", 2), ("BTCUSDT", 0), ("ETHUSDT", 1)]);
+
+        assert_eq!(
+            trades
+                .iter()
+                .map(|trade| (trade.timestamp, &trade.market, trade.price))
+                .collect::<Vec<_>>(),
+            shuffled_trades
+                .iter()
+                .map(|trade| (trade.timestamp, &trade.market, trade.price))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(results, shuffled_results);
+    }
+}
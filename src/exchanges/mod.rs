@@ -1,11 +1,31 @@
+pub mod account;
+pub mod aggregate;
 pub mod binance;
+pub mod book;
+pub mod coinbase;
+pub mod fees;
 pub mod historical;
+pub mod live;
+pub mod merge;
+pub mod quality;
+pub mod recorder;
+pub mod stops;
 
+pub use account::{Account, Intent, OpenPosition};
+pub use aggregate::Aggregate;
 pub use binance::Binance;
+pub use book::{BookUpdate, Level, OrderBook};
+pub use coinbase::Coinbase;
+pub use fees::{Fees, Liquidity, TopUp};
 pub use historical::Historical;
+pub use live::{Live, Venue};
+pub use quality::{Checks, Repair};
+pub use recorder::Recorder;
+pub use stops::{Stops, Trail};
 
-use crate::{Market, Number, Strategy};
+use crate::{Market, Strategy};
 use async_trait::async_trait;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -14,7 +34,7 @@ pub trait Exchange<S: Strategy>: Send + 'static {
     async fn run(self, strategy: &mut S);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Side {
     Buy,
     Sell,
@@ -33,12 +53,27 @@ impl fmt::Display for Side {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Part of a position that is sold once the price reaches `price`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tranche {
+    pub price: Decimal,
+    /// Fraction of the whole position, 0.5 is half of it.
+    pub fraction: Decimal,
+    /// Stop loss for the rest of the position once this tranche was sold.
+    pub stop_loss: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub market: Market,
-    pub price: Number,
-    pub take_profit: Option<Number>,
-    pub stop_loss: Option<Number>,
+    pub price: Decimal,
+    /// Sells whatever is left of the position after all tranches.
+    pub take_profit: Option<Decimal>,
+    pub stop_loss: Option<Decimal>,
+    /// Take profit tranches, sold in order.
+    pub tranches: Vec<Tranche>,
+    /// Fraction of the total value to invest, 0.1 is 10%. Overrides the position sizer.
+    pub size: Option<Decimal>,
     pub side: Side,
 }
 
@@ -46,10 +81,17 @@ impl fmt::Display for Order {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} {} {}{}{}",
+            "{} {} {}{}{}{}{}",
             self.side,
             self.price,
             self.market,
+            self.tranches
+                .iter()
+                .map(|tranche| format!(
+                    ", take profit {}: {}",
+                    tranche.fraction, tranche.price
+                ))
+                .collect::<String>(),
             if let Some(take_profit) = self.take_profit {
                 format!(", take profit: {}", take_profit)
             } else {
@@ -59,15 +101,151 @@ impl fmt::Display for Order {
                 format!(", stop loss: {}", stop_loss)
             } else {
                 String::new()
+            },
+            if let Some(size) = self.size {
+                format!(", size: {}", size)
+            } else {
+                String::new()
             }
         )
     }
 }
 
+/// How entry orders are placed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Entry {
+    /// Buys at market, so the order is always filled but pays taker fees and slippage.
+    Market,
+    /// Buys with a limit order at the order price. Whatever isn't filled after `timeout`
+    /// milliseconds is cancelled.
+    Limit { timeout: i64 },
+    /// Like `Limit`, but the order is rejected instead of filled immediately, so it never pays
+    /// taker fees.
+    PostOnly { timeout: i64 },
+    /// Buys with a limit order at the order price. Whatever can't be filled immediately is
+    /// cancelled.
+    ImmediateOrCancel,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self::Market
+    }
+}
+
+/// A market and the assets it trades, as listed by the exchange.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pair {
+    pub market: Market,
+    pub base: String,
+    pub quote: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub market: Market,
-    pub quantity: Number,
-    pub price: Number,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub timestamp: i64,
+    /// The venue the trade happened on, if known. Tells trades apart once several venues are merged.
+    pub venue: Option<String>,
+}
+
+/// Trades aggregated over `interval` milliseconds, starting at `timestamp`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    pub market: Market,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub timestamp: i64,
+    pub interval: i64,
+}
+
+impl Bar {
+    pub fn new(trade: &Trade, interval: i64) -> Self {
+        Self {
+            market: trade.market.clone(),
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.quantity.abs(),
+            timestamp: trade.timestamp / interval * interval,
+            interval,
+        }
+    }
+
+    pub fn add(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.quantity.abs();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub market: Market,
+    pub side: Side,
+    pub price: Decimal,
     pub timestamp: i64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedPosition {
+    pub market: Market,
+    pub buy_price: Decimal,
+    pub sell_price: Decimal,
+    pub timestamp: i64,
+}
+
+impl ClosedPosition {
+    pub fn is_profitable(&self) -> bool {
+        self.sell_price > self.buy_price
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MarketEvent {
+    Trade(Trade),
+    Bar(Bar),
+    BookUpdate(BookUpdate),
+    OrderFilled(Fill),
+    PositionClosed(ClosedPosition),
+    Timer(i64),
+}
+
+impl MarketEvent {
+    pub fn market(&self) -> Option<&Market> {
+        match self {
+            Self::Trade(Trade { market, .. })
+            | Self::Bar(Bar { market, .. })
+            | Self::BookUpdate(BookUpdate { market, .. })
+            | Self::OrderFilled(Fill { market, .. })
+            | Self::PositionClosed(ClosedPosition { market, .. }) => Some(market),
+            Self::Timer(_) => None,
+        }
+    }
+
+    /// The venue of a trade, if known. Other events are about the venue orders go to.
+    pub fn venue(&self) -> Option<&str> {
+        match self {
+            Self::Trade(Trade { venue, .. }) => venue.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            Self::Trade(Trade { timestamp, .. })
+            | Self::Bar(Bar { timestamp, .. })
+            | Self::BookUpdate(BookUpdate { timestamp, .. })
+            | Self::OrderFilled(Fill { timestamp, .. })
+            | Self::PositionClosed(ClosedPosition { timestamp, .. })
+            | Self::Timer(timestamp) => *timestamp,
+        }
+    }
+}
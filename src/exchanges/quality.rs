@@ -0,0 +1,500 @@
+use super::Trade;
+use crate::Market;
+use rust_decimal::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+};
+
+/// What happens to trades that fail the checks.
+#[derive(Debug, Clone, Copy)]
+pub enum Repair {
+    /// Replays the trades as they are, issues are only reported.
+    Keep,
+    /// Refuses to replay anything if there are issues other than gaps and possible duplicates.
+    Reject,
+    /// Leaves out trades out of order, bad prices and spikes. Gaps and possible duplicates stay.
+    Drop,
+    /// Like `Drop`, but bad prices and spikes are replaced by the last good price, and gaps are
+    /// filled with trades of no quantity at the last good price, one per interval.
+    ForwardFill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Issue {
+    Duplicate,
+    OutOfOrder,
+    BadPrice,
+    Spike,
+}
+
+/// Issues found in the trades of a market.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MarketReport {
+    /// Timestamps of the trades before and after every gap.
+    pub gaps: Vec<(i64, i64)>,
+    /// Intervals without trades in all gaps together.
+    pub missing: i64,
+    /// Trades at the same price and quantity in the same millisecond as another one. Binance has
+    /// many legitimate ones, so they are only reported.
+    pub duplicates: usize,
+    pub out_of_order: usize,
+    /// Trades at a price of zero or less.
+    pub bad_prices: usize,
+    pub spikes: usize,
+}
+
+impl MarketReport {
+    fn count(&mut self, issue: Issue) {
+        match issue {
+            Issue::Duplicate => self.duplicates += 1,
+            Issue::OutOfOrder => self.out_of_order += 1,
+            Issue::BadPrice => self.bad_prices += 1,
+            Issue::Spike => self.spikes += 1,
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        self.out_of_order + self.bad_prices + self.spikes > 0
+    }
+
+    fn has_issues(&self) -> bool {
+        !self.gaps.is_empty() || self.duplicates > 0 || self.has_errors()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub markets: BTreeMap<Market, MarketReport>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.markets.values().all(|report| !report.has_issues())
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (market, report) in &self.markets {
+            if !report.has_issues() {
+                continue;
+            }
+            writeln!(
+                f,
+                "{}: {} gaps ({} intervals missing), {} possible duplicates, {} out of order, {} bad prices, {} spikes",
+                market,
+                report.gaps.len(),
+                report.missing,
+                report.duplicates,
+                report.out_of_order,
+                report.bad_prices,
+                report.spikes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a market stands while its trades are checked.
+#[derive(Default)]
+struct State {
+    /// Latest timestamp so far, and the prices and quantities traded at it.
+    timestamp: i64,
+    traded: Vec<(Decimal, Decimal)>,
+    /// Last price that passed the checks.
+    price: Option<Decimal>,
+    /// Timestamp of the last trade that is replayed.
+    replayed: Option<i64>,
+}
+
+/// Checks trades before they are replayed, per market.
+#[derive(Debug, Clone)]
+pub struct Checks {
+    interval: i64,
+    max_jump: Decimal,
+    repair: Repair,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checks {
+    /// One minute intervals, like the trades `Historical` replays, and spikes of more than 10%.
+    /// Issues are only reported.
+    pub fn new() -> Self {
+        Self {
+            interval: 1000 * 60,
+            max_jump: Decimal::new(1, 1),
+            repair: Repair::Keep,
+        }
+    }
+
+    /// Trades more than `interval` milliseconds apart leave a gap.
+    pub fn with_interval(mut self, interval: i64) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// A trade that moves the price by more than `max_jump` while the trades before and after it
+    /// are within `max_jump` of each other is a spike. 0.1 is 10%. The trade after it has to follow
+    /// within the interval.
+    pub fn with_max_jump(mut self, max_jump: Decimal) -> Self {
+        self.max_jump = max_jump;
+        self
+    }
+
+    pub fn with_repair(mut self, repair: Repair) -> Self {
+        self.repair = repair;
+        self
+    }
+
+    /// Checks trades one by one as they arrive, e.g. while they are read from the database.
+    pub fn checker(&self) -> Checker {
+        Checker {
+            checks: self.clone(),
+            states: HashMap::new(),
+            report: Report::default(),
+            waiting: VecDeque::new(),
+            released: 0,
+            last: HashMap::new(),
+        }
+    }
+
+    /// The trades to replay and what was wrong with them, or only the report if they were
+    /// rejected.
+    pub fn apply(&self, trades: Vec<Trade>) -> Result<(Vec<Trade>, Report), Report> {
+        let mut checker = self.checker();
+        let mut checked = Vec::with_capacity(trades.len());
+        for trade in trades {
+            checked.extend(checker.push(trade));
+        }
+        let (rest, report) = checker.finish()?;
+        checked.extend(rest);
+
+        if let Repair::ForwardFill = self.repair {
+            // Fills are added once the gap is over, after trades of other markets.
+            checked.sort_by_key(|trade| trade.timestamp);
+        }
+        Ok((checked, report))
+    }
+
+    fn issue(&self, state: &State, trade: &Trade, next: Option<Decimal>) -> Option<Issue> {
+        if !state.traded.is_empty() && trade.timestamp < state.timestamp {
+            return Some(Issue::OutOfOrder);
+        }
+        if trade.price <= Decimal::zero() {
+            return Some(Issue::BadPrice);
+        }
+        if let (Some(previous), Some(next)) = (state.price, next) {
+            if next > Decimal::zero()
+                && self.jumps(previous, trade.price)
+                && self.jumps(next, trade.price)
+                && !self.jumps(previous, next)
+            {
+                return Some(Issue::Spike);
+            }
+        }
+        // Without the id of the trade on the exchange, this can't be told apart from a legitimate
+        // trade, so it is only reported.
+        if trade.timestamp == state.timestamp
+            && state.traded.contains(&(trade.price, trade.quantity))
+        {
+            return Some(Issue::Duplicate);
+        }
+        None
+    }
+
+    fn jumps(&self, from: Decimal, to: Decimal) -> bool {
+        (to / from - Decimal::one()).abs() > self.max_jump
+    }
+}
+
+/// Checks trades as they arrive, in the order they are replayed.
+pub struct Checker {
+    checks: Checks,
+    states: HashMap<Market, State>,
+    report: Report,
+    /// Trades that wait for the next trade of their market to tell spikes from moves, and its
+    /// price once it arrived.
+    waiting: VecDeque<(Trade, Option<Decimal>)>,
+    /// Trades that stopped waiting so far, and the position of the last trade of every market.
+    released: usize,
+    last: HashMap<Market, usize>,
+}
+
+impl Checker {
+    /// Checks `trade` and returns the trades that are ready to be replayed. A trade is held back
+    /// until the next trade of its market arrives, or the interval passed without one.
+    pub fn push(&mut self, trade: Trade) -> Vec<Trade> {
+        let position = self.released + self.waiting.len();
+        if let Some(previous) = self.last.insert(trade.market.clone(), position) {
+            if let Some((_, next)) = previous
+                .checked_sub(self.released)
+                .and_then(|i| self.waiting.get_mut(i))
+            {
+                *next = Some(trade.price);
+            }
+        }
+        let timestamp = trade.timestamp;
+        self.waiting.push_back((trade, None));
+
+        let mut checked = Vec::new();
+        while let Some((first, next)) = self.waiting.front() {
+            if next.is_none() && timestamp - first.timestamp <= self.checks.interval {
+                break;
+            }
+            let (trade, next) = self.waiting.pop_front().unwrap();
+            self.released += 1;
+            self.check(trade, next, &mut checked);
+        }
+        checked
+    }
+
+    /// The trades that were still held back and what was wrong with all of them, or only the
+    /// report if they were rejected. Then the trades `push` returned mustn't be replayed either.
+    pub fn finish(mut self) -> Result<(Vec<Trade>, Report), Report> {
+        let mut checked = Vec::new();
+        while let Some((trade, next)) = self.waiting.pop_front() {
+            self.check(trade, next, &mut checked);
+        }
+
+        match self.checks.repair {
+            Repair::Reject if self.report.markets.values().any(MarketReport::has_errors) => {
+                Err(self.report)
+            }
+            _ => Ok((checked, self.report)),
+        }
+    }
+
+    fn check(&mut self, trade: Trade, next: Option<Decimal>, checked: &mut Vec<Trade>) {
+        let state = self.states.entry(trade.market.clone()).or_default();
+        let market_report = self.report.markets.entry(trade.market.clone()).or_default();
+
+        let issue = self.checks.issue(state, &trade, next);
+        if trade.timestamp > state.timestamp || state.traded.is_empty() {
+            state.timestamp = trade.timestamp;
+            state.traded.clear();
+        }
+        if issue != Some(Issue::OutOfOrder) {
+            state.traded.push((trade.price, trade.quantity));
+        }
+
+        // Gaps are filled at the price before them.
+        let previous = state.price;
+        let replayed = match (issue, self.checks.repair) {
+            (None, _) => {
+                state.price = Some(trade.price);
+                Some(trade.clone())
+            }
+            (Some(Issue::Duplicate), _) | (Some(_), Repair::Keep) => Some(trade.clone()),
+            (Some(Issue::BadPrice), Repair::ForwardFill)
+            | (Some(Issue::Spike), Repair::ForwardFill) => previous.map(|price| Trade {
+                price,
+                ..trade.clone()
+            }),
+            (Some(_), _) => None,
+        };
+        if let Some(issue) = issue {
+            market_report.count(issue);
+        }
+
+        if let Some(replayed) = replayed {
+            let interval = self.checks.interval;
+            if let Some(last) = state.replayed {
+                if replayed.timestamp - last > interval {
+                    market_report.gaps.push((last, replayed.timestamp));
+                    market_report.missing += (replayed.timestamp - last - 1) / interval;
+                    if let (Repair::ForwardFill, Some(price)) = (self.checks.repair, previous) {
+                        checked.extend(
+                            (1..)
+                                .map(|k| last + k * interval)
+                                .take_while(|timestamp| *timestamp < replayed.timestamp)
+                                .map(|timestamp| Trade {
+                                    market: trade.market.clone(),
+                                    quantity: Decimal::zero(),
+                                    price,
+                                    timestamp,
+                                    venue: trade.venue.clone(),
+                                }),
+                        );
+                    }
+                }
+            }
+            state.replayed = Some(
+                state
+                    .replayed
+                    .map_or(replayed.timestamp, |last| last.max(replayed.timestamp)),
+            );
+            checked.push(replayed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(market: &str, price: i64, minute: i64) -> Trade {
+        Trade {
+            market: market.to_owned(),
+            quantity: Decimal::one(),
+            price: Decimal::new(price, 0),
+            timestamp: minute * 1000 * 60,
+            venue: None,
+        }
+    }
+
+    fn trades() -> Vec<Trade> {
+        vec![
+            trade("BTCUSDT", 100, 0),
+            trade("ETHUSDT", 10, 0),
+            trade("BTCUSDT", 100, 0),
+            trade("BTCUSDT", 101, 1),
+            trade("ETHUSDT", 10, 1),
+            trade("BTCUSDT", 150, 2),
+            trade("ETHUSDT", 0, 2),
+            trade("BTCUSDT", 102, 3),
+            trade("ETHUSDT", 11, 5),
+            trade("ETHUSDT", 12, 4),
+            trade("BTCUSDT", 103, 4),
+        ]
+    }
+
+    fn replayed(trades: &[Trade]) -> Vec<String> {
+        trades
+            .iter()
+            .map(|trade| {
+                format!(
+                    "{} {} {} {}",
+                    trade.timestamp / 1000 / 60,
+                    trade.market,
+                    trade.price,
+                    trade.quantity
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn report() {
+        let (trades, report) = Checks::new().apply(trades()).unwrap();
+
+        assert_eq!(trades.len(), 11);
+        assert_eq!(
+            report.markets["BTCUSDT"],
+            MarketReport {
+                duplicates: 1,
+                spikes: 1,
+                ..MarketReport::default()
+            }
+        );
+        assert_eq!(
+            report.markets["ETHUSDT"],
+            MarketReport {
+                gaps: vec![(1000 * 60 * 2, 1000 * 60 * 5)],
+                missing: 2,
+                out_of_order: 1,
+                bad_prices: 1,
+                ..MarketReport::default()
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "BTCUSDT: 0 gaps (0 intervals missing), 1 possible duplicates, 0 out of order, 0 bad prices, 1 spikes\n\
+             ETHUSDT: 1 gaps (2 intervals missing), 0 possible duplicates, 1 out of order, 1 bad prices, 0 spikes\n"
+        );
+    }
+
+    #[test]
+    fn reject() {
+        let checks = Checks::new().with_repair(Repair::Reject);
+
+        assert!(checks.apply(trades()).is_err());
+        assert!(checks.apply(vec![trade("BTCUSDT", 100, 0)]).is_ok());
+        assert!(checks
+            .apply(vec![trade("BTCUSDT", 100, 0), trade("BTCUSDT", 100, 0)])
+            .is_ok());
+    }
+
+    #[test]
+    fn checker() {
+        let mut checker = Checks::new().checker();
+
+        // Trades wait for the next one of their market, or for the interval to pass.
+        assert!(checker.push(trade("BTCUSDT", 100, 0)).is_empty());
+        assert!(checker.push(trade("ETHUSDT", 10, 0)).is_empty());
+        assert_eq!(
+            replayed(&checker.push(trade("BTCUSDT", 101, 1))),
+            vec!["0 BTCUSDT 100 1"]
+        );
+        assert_eq!(
+            replayed(&checker.push(trade("BTCUSDT", 102, 2))),
+            vec!["0 ETHUSDT 10 1", "1 BTCUSDT 101 1"]
+        );
+
+        let (trades, report) = checker.finish().unwrap();
+        assert_eq!(replayed(&trades), vec!["2 BTCUSDT 102 1"]);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn drop_issues() {
+        let (trades, report) = Checks::new()
+            .with_repair(Repair::Drop)
+            .apply(trades())
+            .unwrap();
+
+        assert_eq!(
+            replayed(&trades),
+            vec![
+                "0 BTCUSDT 100 1",
+                "0 ETHUSDT 10 1",
+                "0 BTCUSDT 100 1",
+                "1 BTCUSDT 101 1",
+                "1 ETHUSDT 10 1",
+                "3 BTCUSDT 102 1",
+                "5 ETHUSDT 11 1",
+                "4 BTCUSDT 103 1",
+            ]
+        );
+        // Without the spike, BTCUSDT has a gap as well.
+        assert_eq!(
+            report.markets["BTCUSDT"].gaps,
+            vec![(1000 * 60, 1000 * 60 * 3)]
+        );
+        assert_eq!(
+            report.markets["ETHUSDT"].gaps,
+            vec![(1000 * 60, 1000 * 60 * 5)]
+        );
+    }
+
+    #[test]
+    fn forward_fill() {
+        let (trades, _) = Checks::new()
+            .with_repair(Repair::ForwardFill)
+            .apply(trades())
+            .unwrap();
+
+        assert_eq!(
+            replayed(&trades),
+            vec![
+                "0 BTCUSDT 100 1",
+                "0 ETHUSDT 10 1",
+                "0 BTCUSDT 100 1",
+                "1 BTCUSDT 101 1",
+                "1 ETHUSDT 10 1",
+                "2 BTCUSDT 101 1",
+                "2 ETHUSDT 10 1",
+                "3 BTCUSDT 102 1",
+                "3 ETHUSDT 10 0",
+                "4 ETHUSDT 10 0",
+                "4 BTCUSDT 103 1",
+                "5 ETHUSDT 11 1",
+            ]
+        );
+    }
+}
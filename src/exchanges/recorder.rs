@@ -0,0 +1,270 @@
+use super::{Trade, Venue};
+use crate::{Error, Market};
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use sqlx::postgres::PgPool;
+use std::collections::{HashMap, HashSet};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{sleep, timeout, Duration, Instant},
+};
+
+/// Records the trades of a venue into the `trades` table that `Historical` reads. Gaps left by
+/// lost connections or restarts are filled with past trades from the venue once it is reachable
+/// again.
+pub struct Recorder<V: Venue> {
+    venue: V,
+    markets: Vec<Market>,
+    batch_size: usize,
+    sender: UnboundedSender<Vec<Trade>>,
+    /// Timestamp of the last trade recorded in every market.
+    last: HashMap<Market, i64>,
+}
+
+impl<V: Venue> Recorder<V> {
+    const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub async fn new(venue: V, markets: &[&str]) -> Self {
+        let uri = std::env::var("DATABASE_URL").expect("Couldn't get DATABASE_URL.");
+        let pool = PgPool::connect(&uri).await.unwrap();
+        let last = recorded(&pool)
+            .await
+            .expect("Couldn't get the last recorded trades.");
+
+        let (sender, rx) = mpsc::unbounded_channel();
+        tokio::task::spawn(store(pool, rx));
+
+        Self {
+            last,
+            ..Self::with_sender(venue, markets, sender)
+        }
+    }
+
+    fn with_sender(venue: V, markets: &[&str], sender: UnboundedSender<Vec<Trade>>) -> Self {
+        Self {
+            venue,
+            markets: markets.iter().map(|market| market.to_string()).collect(),
+            batch_size: 1000,
+            sender,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Trades are inserted once this many arrived or a second after the last insert, whatever
+    /// comes first. Defaults to a thousand.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub async fn run(mut self) {
+        let mut last = std::mem::take(&mut self.last);
+        let mut batch = Vec::new();
+
+        loop {
+            if let Ok(mut stream) = self.venue.trades(&self.markets).await {
+                log::info!("Recording trades of {}.", self.venue.name());
+                let mut connected = HashSet::new();
+                let mut flushed = Instant::now();
+
+                while let Ok(Some(Ok(trade))) = timeout(
+                    Duration::from_secs(if self.venue.is_sandbox() { 500 } else { 5 }),
+                    stream.next(),
+                )
+                .await
+                {
+                    // The first trade of a market after connecting closes the gap.
+                    if connected.insert(trade.market.clone()) {
+                        if let Some(from) = last.get(&trade.market) {
+                            batch
+                                .extend(self.backfill(&trade.market, *from, trade.timestamp).await);
+                        }
+                    }
+                    last.insert(trade.market.clone(), trade.timestamp);
+                    batch.push(trade);
+
+                    if batch.len() >= self.batch_size || flushed.elapsed() >= Self::FLUSH_INTERVAL {
+                        self.flush(&mut batch);
+                        flushed = Instant::now();
+                    }
+                }
+                self.flush(&mut batch);
+                log::warn!("Message timeout, trying to reconnect.");
+            } else {
+                log::warn!("Unable to reach websocket, trying to reconnect.");
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+
+    async fn backfill(&self, market: &Market, from: i64, to: i64) -> Vec<Trade> {
+        match self.venue.trades_between(market, from, to).await {
+            Ok(trades) => {
+                log::info!("Backfilled {} trades of {}.", trades.len(), market);
+                trades
+            }
+            Err(Error::Unsupported(feature)) => {
+                log::warn!("{} has no {}.", self.venue.name(), feature);
+                Vec::new()
+            }
+            Err(err) => {
+                log::error!("Couldn't backfill trades of {}: {:?}", market, err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn flush(&self, batch: &mut Vec<Trade>) {
+        if !batch.is_empty() {
+            self.sender.send(std::mem::take(batch)).unwrap();
+        }
+    }
+}
+
+/// Timestamp of the last recorded trade of every market, so gaps since are filled like gaps left
+/// by lost connections.
+async fn recorded(pool: &PgPool) -> Result<HashMap<Market, i64>, sqlx::Error> {
+    Ok(sqlx::query!(
+        r#"
+            SELECT market AS "market!", MAX(timestamp) AS "timestamp!"
+            FROM trades
+            GROUP BY market
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.market, row.timestamp))
+    .collect())
+}
+
+/// Inserts every batch in a single statement. A batch that fails is retried until it is recorded,
+/// the batches after it wait in the channel.
+async fn store(pool: PgPool, mut rx: UnboundedReceiver<Vec<Trade>>) {
+    while let Some(trades) = rx.recv().await {
+        while let Err(err) = insert(&pool, &trades).await {
+            log::error!("Couldn't record {} trades, retrying: {}", trades.len(), err);
+            sleep(Duration::from_secs(5)).await;
+        }
+    }
+}
+
+async fn insert(pool: &PgPool, trades: &[Trade]) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO trades (market, quantity, price, timestamp)
+            SELECT * FROM UNNEST($1::TEXT[], $2::NUMERIC[], $3::NUMERIC[], $4::BIGINT[])
+        "#,
+        &trades
+            .iter()
+            .map(|trade| trade.market.clone())
+            .collect::<Vec<Market>>(),
+        &trades
+            .iter()
+            .map(|trade| trade.quantity)
+            .collect::<Vec<Decimal>>(),
+        &trades
+            .iter()
+            .map(|trade| trade.price)
+            .collect::<Vec<Decimal>>(),
+        &trades
+            .iter()
+            .map(|trade| trade.timestamp)
+            .collect::<Vec<i64>>(),
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::live::mock::Mock;
+
+    fn trades(market: &str, timestamps: &[i64]) -> Vec<Trade> {
+        timestamps
+            .iter()
+            .map(|timestamp| Trade {
+                market: market.to_owned(),
+                quantity: Decimal::new(-1, 0),
+                price: Decimal::new(100, 0),
+                timestamp: *timestamp,
+                venue: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backfill_after_reconnect() {
+        let venue = Mock::new()
+            .with_trades([trades("BTCUSDT", &[1000, 2000]), trades("ETHUSDT", &[1500])].concat())
+            .with_trades(trades("BTCUSDT", &[5000, 6000]))
+            .with_history(
+                [
+                    trades("BTCUSDT", &[2000, 3000, 4000, 5000]),
+                    trades("ETHUSDT", &[4500]),
+                ]
+                .concat(),
+            );
+        let state = venue.state();
+        let (sender, mut rx) = mpsc::unbounded_channel();
+        let recorder = Recorder::with_sender(venue, &["BTCUSDT", "ETHUSDT"], sender);
+        let _ = timeout(Duration::from_secs(60), recorder.run()).await;
+
+        let mut batches = Vec::new();
+        while let Ok(batch) = rx.try_recv() {
+            batches.push(
+                batch
+                    .into_iter()
+                    .map(|trade| format!("{} {}", trade.market, trade.timestamp))
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        // Only the gap of BTCUSDT is filled, ETHUSDT hasn't traded since. Trades arrive a second
+        // apart, so every batch is inserted right away.
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec!["history BTCUSDT 2000..5000"]
+        );
+        assert_eq!(
+            batches,
+            vec![
+                vec!["BTCUSDT 1000"],
+                vec!["BTCUSDT 2000"],
+                vec!["ETHUSDT 1500"],
+                vec!["BTCUSDT 3000", "BTCUSDT 4000", "BTCUSDT 5000"],
+                vec!["BTCUSDT 6000"],
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backfill_after_restart() {
+        let venue = Mock::new()
+            .with_trades(trades("BTCUSDT", &[5000]))
+            .with_history(trades("BTCUSDT", &[2000, 3000, 4000, 5000]));
+        let state = venue.state();
+        let (sender, mut rx) = mpsc::unbounded_channel();
+        // Recorded before the restart.
+        let recorder = Recorder {
+            last: vec![("BTCUSDT".to_owned(), 3000)].into_iter().collect(),
+            ..Recorder::with_sender(venue, &["BTCUSDT"], sender)
+        };
+        let _ = timeout(Duration::from_secs(60), recorder.run()).await;
+
+        assert_eq!(
+            state.lock().unwrap().log,
+            vec!["history BTCUSDT 3000..5000"]
+        );
+        assert_eq!(
+            rx.try_recv()
+                .unwrap()
+                .iter()
+                .map(|trade| trade.timestamp)
+                .collect::<Vec<_>>(),
+            vec![4000, 5000]
+        );
+    }
+}
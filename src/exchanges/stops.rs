@@ -0,0 +1,217 @@
+use super::{Bar, Trade};
+use crate::{indicators::Atr, Number};
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Trail {
+    /// Trails the highest price by a fixed distance.
+    Distance(Decimal),
+    /// Trails the highest price by a fraction of it, 0.01 is 1%.
+    Percentage(Decimal),
+    /// Trails the highest price by `multiplier` average true ranges of bars over `interval`
+    /// milliseconds. Until the ATR has warmed up, the stop loss isn't moved.
+    Atr {
+        multiplier: Decimal,
+        period: Number,
+        interval: i64,
+    },
+}
+
+/// Moves the stop loss of an open position as the price moves. The stop loss is only ever raised.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Stops {
+    entry_price: Decimal,
+    highest: Decimal,
+    trail: Option<Trail>,
+    break_even: Option<Decimal>,
+    atr: Option<(Atr, Bar)>,
+}
+
+impl Stops {
+    pub fn new(entry_price: Decimal) -> Self {
+        Self {
+            entry_price,
+            highest: entry_price,
+            trail: None,
+            break_even: None,
+            atr: None,
+        }
+    }
+
+    pub fn trail(&mut self, trail: Trail) {
+        // Keep the ATR built so far if only the multiplier changes.
+        let same_atr = match (self.trail, trail) {
+            (
+                Some(Trail::Atr {
+                    period: old_period,
+                    interval: old_interval,
+                    ..
+                }),
+                Trail::Atr {
+                    period, interval, ..
+                },
+            ) => old_period == period && old_interval == interval,
+            _ => false,
+        };
+        if !same_atr {
+            self.atr = None;
+        }
+
+        self.trail = Some(trail);
+    }
+
+    /// Moves the stop loss to the entry price once the price has risen by `after`, 0.01 is 1%.
+    pub fn break_even(&mut self, after: Decimal) {
+        self.break_even = Some(after);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.trail.is_some() || self.break_even.is_some()
+    }
+
+    /// Returns the new stop loss after `trade`, if it is higher than `stop_loss`.
+    pub fn update(&mut self, trade: &Trade, stop_loss: Option<Decimal>) -> Option<Decimal> {
+        self.highest = self.highest.max(trade.price);
+        self.update_atr(trade);
+
+        let break_even = self
+            .break_even
+            .filter(|after| self.highest >= self.entry_price * (Decimal::one() + after))
+            .map(|_| self.entry_price);
+        let trailing = self.distance().map(|distance| self.highest - distance);
+
+        let new_stop_loss = match (break_even, trailing) {
+            (Some(break_even), Some(trailing)) => Some(break_even.max(trailing)),
+            (break_even, trailing) => break_even.or(trailing),
+        }?;
+
+        if stop_loss.map_or(true, |stop_loss| new_stop_loss > stop_loss) {
+            Some(new_stop_loss)
+        } else {
+            None
+        }
+    }
+
+    fn update_atr(&mut self, trade: &Trade) {
+        let (period, interval) = match self.trail {
+            Some(Trail::Atr {
+                period, interval, ..
+            }) => (period, interval),
+            _ => return,
+        };
+
+        match &mut self.atr {
+            Some((_, bar)) if bar.timestamp == trade.timestamp / interval * interval => {
+                bar.add(trade)
+            }
+            Some((atr, bar)) => {
+                atr.run(
+                    bar.high.to_f64().unwrap(),
+                    bar.low.to_f64().unwrap(),
+                    bar.close.to_f64().unwrap(),
+                );
+                *bar = Bar::new(trade, interval);
+            }
+            None => self.atr = Some((Atr::new(period), Bar::new(trade, interval))),
+        }
+    }
+
+    fn distance(&self) -> Option<Decimal> {
+        match self.trail? {
+            Trail::Distance(distance) => Some(distance),
+            Trail::Percentage(fraction) => Some(self.highest * fraction),
+            Trail::Atr { multiplier, .. } => {
+                let (atr, _) = self.atr.as_ref()?;
+                if atr.is_ready() {
+                    Some(Decimal::from_f64(atr.get())? * multiplier)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Stops {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stops")
+            .field("entry_price", &self.entry_price)
+            .field("highest", &self.highest)
+            .field("trail", &self.trail)
+            .field("break_even", &self.break_even)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(price: i64, timestamp: i64) -> Trade {
+        Trade {
+            market: "BTCUSDT".to_owned(),
+            quantity: Decimal::new(1, 0),
+            price: Decimal::new(price, 0),
+            timestamp,
+            venue: None,
+        }
+    }
+
+    #[test]
+    fn trail() {
+        let mut stops = Stops::new(Decimal::new(100, 0));
+        stops.trail(Trail::Percentage(Decimal::new(1, 1)));
+
+        assert_eq!(
+            stops.update(&trade(100, 0), None),
+            Some(Decimal::new(90, 0))
+        );
+        assert_eq!(
+            stops.update(&trade(120, 1), Some(Decimal::new(90, 0))),
+            Some(Decimal::new(108, 0))
+        );
+        // Never moved down.
+        assert_eq!(
+            stops.update(&trade(110, 2), Some(Decimal::new(108, 0))),
+            None
+        );
+    }
+
+    #[test]
+    fn break_even() {
+        let mut stops = Stops::new(Decimal::new(100, 0));
+        stops.break_even(Decimal::new(5, 2));
+        stops.trail(Trail::Distance(Decimal::new(10, 0)));
+
+        let stop_loss = Some(Decimal::new(95, 0));
+        assert_eq!(stops.update(&trade(104, 0), stop_loss), None);
+        assert_eq!(
+            stops.update(&trade(105, 1), stop_loss),
+            Some(Decimal::new(100, 0))
+        );
+        assert_eq!(
+            stops.update(&trade(115, 2), stop_loss),
+            Some(Decimal::new(105, 0))
+        );
+    }
+
+    #[test]
+    fn atr() {
+        let mut stops = Stops::new(Decimal::new(100, 0));
+        stops.trail(Trail::Atr {
+            multiplier: Decimal::new(2, 0),
+            period: 1.0,
+            interval: 1000,
+        });
+
+        assert_eq!(stops.update(&trade(100, 0), None), None);
+        assert_eq!(stops.update(&trade(104, 500), None), None);
+        // The first bar ranged from 100 to 104.
+        assert_eq!(
+            stops.update(&trade(106, 1000), None),
+            Some(Decimal::new(98, 0))
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use super::{Ema, Indicator};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// Average directional index with the positive and negative directional indicators, all in the
+/// range 0 to 100 and using Wilder's smoothing.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Adx {
+    tr: Ema,
+    plus_dm: Ema,
+    minus_dm: Ema,
+    adx: Ema,
+    previous: Option<(Number, Number, Number)>,
+    samples: u64,
+}
+
+impl Adx {
+    pub fn new(period: Number) -> Self {
+        let period = 2.0 * period - 1.0;
+
+        Self {
+            tr: Ema::new(period),
+            plus_dm: Ema::new(period),
+            minus_dm: Ema::new(period),
+            adx: Ema::new(period),
+            previous: None,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, high: Number, low: Number, close: Number) {
+        if let Some((previous_high, previous_low, previous_close)) = self.previous {
+            let up = high - previous_high;
+            let down = previous_low - low;
+            let tr = (high - low)
+                .max((high - previous_close).abs())
+                .max((low - previous_close).abs());
+
+            self.tr.run(tr);
+            self.plus_dm
+                .run(if up > down && up > 0.0 { up } else { 0.0 });
+            self.minus_dm
+                .run(if down > up && down > 0.0 { down } else { 0.0 });
+
+            if let (Some(plus_di), Some(minus_di)) =
+                (self.try_get_plus_di(), self.try_get_minus_di())
+            {
+                self.adx.run(if plus_di + minus_di > 0.0 {
+                    100.0 * (plus_di - minus_di).abs() / (plus_di + minus_di)
+                } else {
+                    0.0
+                });
+            }
+        }
+        self.previous = Some((high, low, close));
+        self.samples += 1;
+    }
+
+    pub fn get(&self) -> Number {
+        self.adx.get()
+    }
+
+    pub fn get_plus_di(&self) -> Number {
+        self.try_get_plus_di().expect("No value assigned to +DI.")
+    }
+
+    pub fn get_minus_di(&self) -> Number {
+        self.try_get_minus_di().expect("No value assigned to -DI.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.adx.try_get()
+    }
+
+    pub fn try_get_plus_di(&self) -> Option<Number> {
+        Self::di(self.plus_dm.try_get()?, self.tr.try_get()?)
+    }
+
+    pub fn try_get_minus_di(&self) -> Option<Number> {
+        Self::di(self.minus_dm.try_get()?, self.tr.try_get()?)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.tr.is_ready() && self.adx.is_ready()
+    }
+
+    fn di(dm: Number, tr: Number) -> Option<Number> {
+        if tr > 0.0 {
+            Some(100.0 * dm / tr)
+        } else {
+            None
+        }
+    }
+}
+
+impl Indicator for Adx {
+    type Input = (Number, Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (high, low, close): (Number, Number, Number)) {
+        Adx::run(self, high, low, close)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Adx::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Adx::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match (self.try_get(), self.try_get_plus_di(), self.try_get_minus_di()) {
+            (Some(adx), Some(plus_di), Some(minus_di)) => {
+                vec![("adx", adx), ("plus_di", plus_di), ("minus_di", minus_di)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut adx = Adx::new(1.0);
+        adx.run(10.0, 8.0, 9.0);
+        assert_eq!(adx.try_get(), None);
+        adx.run(12.0, 9.0, 11.0);
+        assert_eq!(adx.get_plus_di(), 100.0 * 2.0 / 3.0);
+        assert_eq!(adx.get_minus_di(), 0.0);
+        assert_eq!(adx.get(), 100.0);
+        adx.run(11.0, 6.0, 7.0);
+        assert_eq!(adx.get_plus_di(), 0.0);
+        assert_eq!(adx.get_minus_di(), 60.0);
+        assert_eq!(adx.get(), 100.0);
+    }
+
+    #[test]
+    fn trending() {
+        let mut adx = Adx::new(14.0);
+        for i in 0..200 {
+            let close = 100.0 + i as Number;
+            adx.run(close + 1.0, close - 1.0, close);
+        }
+        assert!(adx.is_ready());
+        assert!(adx.get_plus_di() > adx.get_minus_di());
+        assert!(adx.get() > 99.0);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{Ema, Indicator};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// Average true range, using Wilder's smoothing.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Atr {
+    tr: Ema,
+    close: Option<Number>,
+}
+
+impl Atr {
+    pub fn new(period: Number) -> Self {
+        Self {
+            tr: Ema::new(2.0 * period - 1.0),
+            close: None,
+        }
+    }
+
+    pub fn run(&mut self, high: Number, low: Number, close: Number) {
+        let tr = match self.close {
+            Some(previous) => (high - low)
+                .max((high - previous).abs())
+                .max((low - previous).abs()),
+            None => high - low,
+        };
+        self.tr.run(tr);
+        self.close = Some(close);
+    }
+
+    pub fn get(&self) -> Number {
+        self.tr.get()
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.tr.try_get()
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.tr.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.tr.is_ready()
+    }
+}
+
+impl Indicator for Atr {
+    type Input = (Number, Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (high, low, close): (Number, Number, Number)) {
+        Atr::run(self, high, low, close)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Atr::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Atr::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|atr| vec![("atr", atr)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut atr = Atr::new(2.0);
+        atr.run(12.0, 8.0, 10.0);
+        assert_eq!(atr.get(), 4.0);
+        // Gap up, true range is high - previous close = 8.
+        atr.run(18.0, 16.0, 17.0);
+        assert_eq!(atr.get(), 6.0);
+        // Gap down, true range is previous close - low = 9.
+        atr.run(9.0, 8.0, 8.0);
+        assert_eq!(atr.get(), 7.5);
+    }
+}
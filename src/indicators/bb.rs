@@ -1,7 +1,8 @@
-use super::{Ema, Stdev};
+use super::{Ema, Indicator, Stdev};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Bb {
     ma: Ema,
     sigma: Stdev,
@@ -37,6 +38,56 @@ impl Bb {
     pub fn range(&self) -> Number {
         self.get_upper() - self.get_lower()
     }
+
+    pub fn try_get_ma(&self) -> Option<Number> {
+        self.ma.try_get()
+    }
+
+    pub fn try_get_upper(&self) -> Option<Number> {
+        Some(self.ma.try_get()? + self.times * self.sigma.try_get()?)
+    }
+
+    pub fn try_get_lower(&self) -> Option<Number> {
+        Some(self.ma.try_get()? - self.times * self.sigma.try_get()?)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.ma.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ma.is_ready() && self.sigma.is_ready()
+    }
+}
+
+impl Indicator for Bb {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Bb::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get_ma()
+    }
+
+    fn is_ready(&self) -> bool {
+        Bb::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Bb::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match (self.try_get_ma(), self.try_get_upper(), self.try_get_lower()) {
+            (Some(ma), Some(upper), Some(lower)) => {
+                vec![("ma", ma), ("upper", upper), ("lower", lower)]
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -51,6 +102,6 @@ mod tests {
         assert_eq!(bb.get_upper(), 0.0);
         bb.run(16.0);
         assert_eq!(bb.get_ma(), 8.0);
-        assert_eq!(bb.get_upper(), 8.0 + (32.0 as Number).sqrt());
+        assert_eq!(bb.get_upper(), 16.0);
     }
 }
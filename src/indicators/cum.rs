@@ -1,22 +1,63 @@
+use super::Indicator;
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Cum {
     sum: Number,
+    samples: u64,
 }
 
 impl Cum {
     pub fn new() -> Self {
-        Self { sum: 0.0 }
+        Self {
+            sum: 0.0,
+            samples: 0,
+        }
     }
 
     pub fn run(&mut self, input: Number) {
         self.sum += input;
+        self.samples += 1;
     }
 
     pub fn get(&self) -> Number {
         self.sum
     }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// A cumulative sum is meaningful from the first sample on.
+    pub fn is_ready(&self) -> bool {
+        self.samples > 0
+    }
+}
+
+impl Indicator for Cum {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Cum::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        Some(Cum::get(self))
+    }
+
+    fn is_ready(&self) -> bool {
+        Cum::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Cum::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        vec![("cum", Cum::get(self))]
+    }
 }
 
 #[cfg(test)]
@@ -1,7 +1,8 @@
-use super::Macd;
+use super::{Indicator, Macd};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Differential {
     period: Number,
     macd: Macd,
@@ -26,6 +27,45 @@ impl Differential {
     pub fn second_derivative(&self) -> Number {
         self.macd.get_hist() / (self.period / 4.0).powi(2)
     }
+
+    pub fn samples(&self) -> u64 {
+        self.macd.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.macd.is_ready()
+    }
+}
+
+impl Indicator for Differential {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Differential::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.macd.try_get().map(|_| self.first_derivative())
+    }
+
+    fn is_ready(&self) -> bool {
+        Differential::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Differential::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match self.macd.try_get_hist() {
+            Some(_) => vec![
+                ("first_derivative", self.first_derivative()),
+                ("second_derivative", self.second_derivative()),
+            ],
+            None => Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -41,8 +81,8 @@ mod tests {
             diff.run(x);
         }
 
-        assert_eq!(diff.first_derivative(), 0.49996948 as Number);
-        assert_eq!(diff.second_derivative(), 0.0000000037252903 as Number);
+        assert!((diff.first_derivative() - 0.5).abs() < 1e-9);
+        assert!(diff.second_derivative().abs() < 1e-9);
     }
 
     #[test]
@@ -54,6 +94,6 @@ mod tests {
             diff.run(x);
         }
 
-        assert_eq!(diff.second_derivative(), 1.9862366 as Number);
+        assert!((diff.second_derivative() - 1.984375).abs() < 1e-9);
     }
 }
@@ -1,9 +1,13 @@
+use super::Indicator;
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Ema {
     output: Option<Number>,
     alpha: Number,
+    period: Number,
+    samples: u64,
 }
 
 impl Ema {
@@ -13,6 +17,8 @@ impl Ema {
         Self {
             alpha: 2.0 / (1.0 + period),
             output: None,
+            period,
+            samples: 0,
         }
     }
 
@@ -22,11 +28,50 @@ impl Ema {
         } else {
             self.output = Some(input);
         }
+        self.samples += 1;
     }
 
     pub fn get(&self) -> Number {
         self.output.expect("No value assigned to EMA.")
     }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.output
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// An EMA is considered converged once it has seen at least `period` samples.
+    pub fn is_ready(&self) -> bool {
+        self.samples as Number >= self.period
+    }
+}
+
+impl Indicator for Ema {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Ema::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Ema::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Ema::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|ema| vec![("ema", ema)]).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -45,4 +90,18 @@ mod tests {
         ema.run(16.0);
         assert_eq!(ema.get(), 8.0);
     }
+
+    #[test]
+    fn ready() {
+        let mut ema = Ema::new(3.0);
+        assert_eq!(ema.try_get(), None);
+        assert!(!ema.is_ready());
+        ema.run(1.0);
+        ema.run(1.0);
+        assert_eq!(ema.try_get(), Some(1.0));
+        assert!(!ema.is_ready());
+        ema.run(1.0);
+        assert_eq!(ema.samples(), 3);
+        assert!(ema.is_ready());
+    }
 }
@@ -0,0 +1,106 @@
+use super::{Atr, Ema, Indicator};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// Keltner channels, an EMA of the close with bands `times` ATRs above and below.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Keltner {
+    ma: Ema,
+    atr: Atr,
+    times: Number,
+}
+
+impl Keltner {
+    pub fn new(period: Number, atr_period: Number, times: Number) -> Self {
+        Self {
+            ma: Ema::new(period),
+            atr: Atr::new(atr_period),
+            times,
+        }
+    }
+
+    pub fn run(&mut self, high: Number, low: Number, close: Number) {
+        self.ma.run(close);
+        self.atr.run(high, low, close);
+    }
+
+    pub fn get_ma(&self) -> Number {
+        self.ma.get()
+    }
+
+    pub fn get_upper(&self) -> Number {
+        self.get_ma() + self.times * self.atr.get()
+    }
+
+    pub fn get_lower(&self) -> Number {
+        self.get_ma() - self.times * self.atr.get()
+    }
+
+    pub fn try_get_ma(&self) -> Option<Number> {
+        self.ma.try_get()
+    }
+
+    pub fn try_get_upper(&self) -> Option<Number> {
+        Some(self.ma.try_get()? + self.times * self.atr.try_get()?)
+    }
+
+    pub fn try_get_lower(&self) -> Option<Number> {
+        Some(self.ma.try_get()? - self.times * self.atr.try_get()?)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.ma.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ma.is_ready() && self.atr.is_ready()
+    }
+}
+
+impl Indicator for Keltner {
+    type Input = (Number, Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (high, low, close): (Number, Number, Number)) {
+        Keltner::run(self, high, low, close)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get_ma()
+    }
+
+    fn is_ready(&self) -> bool {
+        Keltner::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Keltner::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match (self.try_get_ma(), self.try_get_upper(), self.try_get_lower()) {
+            (Some(ma), Some(upper), Some(lower)) => {
+                vec![("ma", ma), ("upper", upper), ("lower", lower)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut keltner = Keltner::new(3.0, 1.0, 2.0);
+        keltner.run(12.0, 8.0, 10.0);
+        assert_eq!(keltner.get_ma(), 10.0);
+        assert_eq!(keltner.get_upper(), 18.0);
+        assert_eq!(keltner.get_lower(), 2.0);
+        keltner.run(13.0, 11.0, 12.0);
+        assert_eq!(keltner.get_ma(), 11.0);
+        assert_eq!(keltner.get_upper(), 17.0);
+        assert_eq!(keltner.get_lower(), 5.0);
+    }
+}
@@ -1,7 +1,8 @@
-use super::Ema;
+use super::{Ema, Indicator};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Lsq {
     aa: Ema,
     ab: Ema,
@@ -20,12 +21,54 @@ impl Lsq {
     pub fn run(&mut self, a: Number, b: Number) {
         self.aa.run(a.powi(2));
         self.ab.run(a * b);
-        self.lsq = (1.0 / self.aa.get()) * self.ab.get();
+
+        // Without any variance in `a` there is no slope to estimate, keep the last one instead.
+        let lsq = self.ab.get() / self.aa.get();
+        if self.aa.get() > Number::EPSILON && lsq.is_finite() {
+            self.lsq = lsq;
+        }
     }
 
     pub fn get(&self) -> Number {
         self.lsq
     }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.aa.try_get().map(|_| self.lsq)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.aa.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.aa.is_ready() && self.ab.is_ready()
+    }
+}
+
+impl Indicator for Lsq {
+    type Input = (Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (a, b): (Number, Number)) {
+        Lsq::run(self, a, b)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Lsq::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Lsq::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|lsq| vec![("lsq", lsq)]).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -40,4 +83,17 @@ mod tests {
         lsq.run(-8.0, 4.0);
         assert_eq!(lsq.get(), -0.5);
     }
+
+    #[test]
+    fn degenerate() {
+        let mut lsq = Lsq::new(1.0);
+        lsq.run(0.0, 5.0);
+        assert_eq!(lsq.get(), 0.0);
+        lsq.run(2.0, 4.0);
+        assert_eq!(lsq.get(), 2.0);
+        lsq.run(0.0, 0.0);
+        assert_eq!(lsq.get(), 2.0);
+        lsq.run(1e-200, 1.0);
+        assert_eq!(lsq.get(), 2.0);
+    }
 }
@@ -1,7 +1,8 @@
-use super::Ema;
+use super::{Ema, Indicator};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Macd {
     fast: Ema,
     slow: Ema,
@@ -34,6 +35,56 @@ impl Macd {
     pub fn get_hist(&self) -> Number {
         self.get() - self.get_signal()
     }
+
+    pub fn try_get(&self) -> Option<Number> {
+        Some(self.fast.try_get()? - self.slow.try_get()?)
+    }
+
+    pub fn try_get_signal(&self) -> Option<Number> {
+        self.signal.try_get()
+    }
+
+    pub fn try_get_hist(&self) -> Option<Number> {
+        Some(self.try_get()? - self.try_get_signal()?)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.slow.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.fast.is_ready() && self.slow.is_ready() && self.signal.is_ready()
+    }
+}
+
+impl Indicator for Macd {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Macd::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Macd::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Macd::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match (self.try_get(), self.try_get_signal()) {
+            (Some(macd), Some(signal)) => {
+                vec![("macd", macd), ("signal", signal), ("hist", macd - signal)]
+            }
+            _ => Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -52,4 +103,17 @@ mod tests {
         assert_eq!(macd.get_signal(), 4.0);
         assert_eq!(macd.get_hist(), 4.0);
     }
+
+    #[test]
+    fn ready() {
+        let mut macd = Macd::new(1.0, 3.0, 2.0);
+        assert_eq!(macd.try_get(), None);
+        assert_eq!(macd.try_get_hist(), None);
+        macd.run(0.0);
+        macd.run(16.0);
+        assert_eq!(macd.try_get(), Some(8.0));
+        assert!(!macd.is_ready());
+        macd.run(16.0);
+        assert!(macd.is_ready());
+    }
 }
@@ -1,17 +1,101 @@
+mod adx;
+mod atr;
 mod bb;
 mod cum;
 mod differential;
 mod ema;
+mod keltner;
 mod lsq;
 mod macd;
+mod obv;
+mod pipeline;
+mod rsi;
+mod sma;
 mod stdev;
+mod stoch;
+mod time_ema;
+mod time_lsq;
+mod time_macd;
+mod time_stdev;
 mod val;
+mod vwap;
+mod wma;
 
+pub use adx::Adx;
+pub use atr::Atr;
 pub use bb::Bb;
 pub use cum::Cum;
 pub use differential::Differential;
 pub use ema::Ema;
+pub use keltner::Keltner;
 pub use lsq::Lsq;
 pub use macd::Macd;
+pub use obv::Obv;
+pub use pipeline::{Chain, Difference, Lag, Map, Ratio};
+pub use rsi::Rsi;
+pub use sma::Sma;
 pub use stdev::Stdev;
+pub use stoch::Stoch;
+pub use time_ema::TimeEma;
+pub use time_lsq::TimeLsq;
+pub use time_macd::TimeMacd;
+pub use time_stdev::TimeStdev;
 pub use val::Val;
+pub use vwap::Vwap;
+pub use wma::Wma;
+
+use crate::Number;
+
+/// Common interface of all indicators, so they can be combined and inspected generically.
+pub trait Indicator {
+    type Input;
+    type Output;
+
+    fn run(&mut self, input: Self::Input);
+    fn get(&self) -> Option<Self::Output>;
+    fn is_ready(&self) -> bool;
+    /// Number of inputs received so far, regardless of the window or warm-up of the indicator.
+    fn samples(&self) -> u64;
+    /// All values the indicator produces, by name. Single valued indicators return one entry.
+    fn outputs(&self) -> Vec<(&'static str, Number)>;
+
+    /// Feeds the output of this indicator into `next`.
+    fn chain<I: Indicator<Input = Self::Output>>(self, next: I) -> Chain<Self, I>
+    where
+        Self: Sized,
+    {
+        Chain::new(self, next)
+    }
+
+    fn map<F: Fn(Self::Output) -> Number>(self, name: &'static str, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+    {
+        Map::new(self, name, f)
+    }
+
+    /// Delays the output by `lag` inputs.
+    fn lag(self, lag: usize) -> Lag<Self>
+    where
+        Self: Sized + Indicator<Output = Number>,
+    {
+        Lag::new(self, lag)
+    }
+
+    /// The change of the output over the last `lag` inputs.
+    fn difference(self, lag: usize) -> Difference<Self>
+    where
+        Self: Sized + Indicator<Output = Number>,
+    {
+        Difference::new(self, lag)
+    }
+
+    /// Divides the output by the output of `other`, both receive the same input.
+    fn ratio<I: Indicator<Input = Self::Input, Output = Number>>(self, other: I) -> Ratio<Self, I>
+    where
+        Self: Sized + Indicator<Output = Number>,
+        Self::Input: Clone,
+    {
+        Ratio::new(self, other)
+    }
+}
@@ -0,0 +1,88 @@
+use super::Indicator;
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// On-balance volume, adds the volume on up moves and subtracts it on down moves.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Obv {
+    obv: Number,
+    close: Option<Number>,
+    samples: u64,
+}
+
+impl Obv {
+    pub fn new() -> Self {
+        Self {
+            obv: 0.0,
+            close: None,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, close: Number, volume: Number) {
+        if let Some(previous) = self.close {
+            if close > previous {
+                self.obv += volume.abs();
+            } else if close < previous {
+                self.obv -= volume.abs();
+            }
+        }
+        self.close = Some(close);
+        self.samples += 1;
+    }
+
+    pub fn get(&self) -> Number {
+        self.obv
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.samples > 1
+    }
+}
+
+impl Indicator for Obv {
+    type Input = (Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (close, volume): (Number, Number)) {
+        Obv::run(self, close, volume)
+    }
+
+    fn get(&self) -> Option<Number> {
+        Some(Obv::get(self))
+    }
+
+    fn is_ready(&self) -> bool {
+        Obv::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Obv::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        vec![("obv", Obv::get(self))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut obv = Obv::new();
+        obv.run(10.0, 100.0);
+        assert_eq!(obv.get(), 0.0);
+        obv.run(11.0, 50.0);
+        assert_eq!(obv.get(), 50.0);
+        obv.run(11.0, 70.0);
+        assert_eq!(obv.get(), 50.0);
+        obv.run(9.0, -80.0);
+        assert_eq!(obv.get(), -30.0);
+    }
+}
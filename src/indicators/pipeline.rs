@@ -0,0 +1,296 @@
+use super::Indicator;
+use crate::Number;
+use std::collections::VecDeque;
+
+#[derive(Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Chain<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Indicator, B: Indicator<Input = A::Output>> Indicator for Chain<A, B> {
+    type Input = A::Input;
+    type Output = B::Output;
+
+    fn run(&mut self, input: A::Input) {
+        self.first.run(input);
+        if let Some(output) = self.first.get() {
+            self.second.run(output);
+        }
+    }
+
+    fn get(&self) -> Option<B::Output> {
+        self.second.get()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.first.is_ready() && self.second.is_ready()
+    }
+
+    fn samples(&self) -> u64 {
+        self.first.samples()
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.second.outputs()
+    }
+}
+
+#[derive(Clone)]
+pub struct Map<I, F> {
+    indicator: I,
+    name: &'static str,
+    f: F,
+}
+
+impl<I, F> Map<I, F> {
+    pub fn new(indicator: I, name: &'static str, f: F) -> Self {
+        Self { indicator, name, f }
+    }
+}
+
+impl<I: Indicator, F: Fn(I::Output) -> Number> Indicator for Map<I, F> {
+    type Input = I::Input;
+    type Output = Number;
+
+    fn run(&mut self, input: I::Input) {
+        self.indicator.run(input);
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.indicator.get().map(&self.f)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.indicator.is_ready()
+    }
+
+    fn samples(&self) -> u64 {
+        self.indicator.samples()
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.get().map(|output| vec![(self.name, output)]).unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct Lag<I> {
+    indicator: I,
+    history: VecDeque<Number>,
+    lag: usize,
+}
+
+impl<I> Lag<I> {
+    pub fn new(indicator: I, lag: usize) -> Self {
+        Self {
+            indicator,
+            history: VecDeque::with_capacity(lag + 1),
+            lag,
+        }
+    }
+}
+
+impl<I: Indicator<Output = Number>> Indicator for Lag<I> {
+    type Input = I::Input;
+    type Output = Number;
+
+    fn run(&mut self, input: I::Input) {
+        self.indicator.run(input);
+        if let Some(output) = self.indicator.get() {
+            self.history.push_back(output);
+            if self.history.len() > self.lag + 1 {
+                self.history.pop_front();
+            }
+        }
+    }
+
+    fn get(&self) -> Option<Number> {
+        if self.history.len() == self.lag + 1 {
+            self.history.front().copied()
+        } else {
+            None
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.indicator.is_ready() && self.get().is_some()
+    }
+
+    fn samples(&self) -> u64 {
+        self.indicator.samples()
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.get().map(|output| vec![("lag", output)]).unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct Difference<I> {
+    lag: Lag<I>,
+}
+
+impl<I> Difference<I> {
+    pub fn new(indicator: I, lag: usize) -> Self {
+        Self {
+            lag: Lag::new(indicator, lag),
+        }
+    }
+}
+
+impl<I: Indicator<Output = Number>> Indicator for Difference<I> {
+    type Input = I::Input;
+    type Output = Number;
+
+    fn run(&mut self, input: I::Input) {
+        self.lag.run(input);
+    }
+
+    fn get(&self) -> Option<Number> {
+        Some(self.lag.history.back()? - self.lag.get()?)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.lag.is_ready()
+    }
+
+    fn samples(&self) -> u64 {
+        self.lag.samples()
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.get().map(|output| vec![("difference", output)]).unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct Ratio<A, B> {
+    numerator: A,
+    denominator: B,
+}
+
+impl<A, B> Ratio<A, B> {
+    pub fn new(numerator: A, denominator: B) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+}
+
+impl<A, B> Indicator for Ratio<A, B>
+where
+    A: Indicator<Output = Number>,
+    A::Input: Clone,
+    B: Indicator<Input = A::Input, Output = Number>,
+{
+    type Input = A::Input;
+    type Output = Number;
+
+    fn run(&mut self, input: A::Input) {
+        self.numerator.run(input.clone());
+        self.denominator.run(input);
+    }
+
+    fn get(&self) -> Option<Number> {
+        let denominator = self.denominator.get()?;
+        if denominator != 0.0 {
+            Some(self.numerator.get()? / denominator)
+        } else {
+            None
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.numerator.is_ready() && self.denominator.is_ready()
+    }
+
+    fn samples(&self) -> u64 {
+        self.numerator.samples()
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.get().map(|output| vec![("ratio", output)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indicators::{Ema, Macd, Sma};
+
+    #[test]
+    fn chain() {
+        let mut indicator = Sma::new(2).chain(Ema::new(3.0));
+        indicator.run(2.0);
+        assert_eq!(indicator.get(), Some(2.0));
+        indicator.run(6.0);
+        assert_eq!(indicator.get(), Some(3.0));
+        assert!(!indicator.is_ready());
+        indicator.run(10.0);
+        assert_eq!(indicator.get(), Some(5.5));
+        assert!(indicator.is_ready());
+    }
+
+    #[test]
+    fn lag_and_difference() {
+        let mut lag = Sma::new(1).lag(2);
+        let mut difference = Sma::new(1).difference(2);
+        for (i, input) in [1.0, 4.0, 9.0, 16.0].iter().enumerate() {
+            lag.run(*input);
+            difference.run(*input);
+            if i < 2 {
+                assert_eq!(lag.get(), None);
+                assert_eq!(difference.get(), None);
+            }
+        }
+        assert_eq!(lag.get(), Some(4.0));
+        assert_eq!(difference.get(), Some(12.0));
+    }
+
+    #[test]
+    fn ratio_and_map() {
+        let mut indicator = Sma::new(1)
+            .ratio(Sma::new(2))
+            .map("percent", |ratio| (ratio - 1.0) * 100.0);
+        indicator.run(2.0);
+        assert_eq!(indicator.get(), Some(0.0));
+        indicator.run(6.0);
+        assert_eq!(indicator.get(), Some(50.0));
+        assert_eq!(indicator.outputs(), vec![("percent", 50.0)]);
+    }
+
+    #[test]
+    fn generic() {
+        let mut indicators: Vec<Box<dyn Indicator<Input = Number, Output = Number>>> = vec![
+            Box::new(Ema::new(3.0)),
+            Box::new(Macd::new(1.0, 3.0, 3.0)),
+            Box::new(Sma::new(2).difference(1)),
+        ];
+        for indicator in &mut indicators {
+            indicator.run(0.0);
+            indicator.run(16.0);
+        }
+        let outputs = indicators
+            .iter()
+            .flat_map(|indicator| indicator.outputs())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            vec![
+                ("ema", 8.0),
+                ("macd", 8.0),
+                ("signal", 4.0),
+                ("hist", 4.0),
+                ("difference", 8.0),
+            ]
+        );
+    }
+}
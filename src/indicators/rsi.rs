@@ -0,0 +1,105 @@
+use super::{Ema, Indicator};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// Relative strength index in the range 0 to 100, using Wilder's smoothing.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Rsi {
+    gain: Ema,
+    loss: Ema,
+    previous: Option<Number>,
+    samples: u64,
+}
+
+impl Rsi {
+    pub fn new(period: Number) -> Self {
+        Self {
+            // Wilder's smoothing factor 1/n corresponds to an EMA of period 2n-1.
+            gain: Ema::new(2.0 * period - 1.0),
+            loss: Ema::new(2.0 * period - 1.0),
+            previous: None,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, input: Number) {
+        if let Some(previous) = self.previous {
+            let change = input - previous;
+            self.gain.run(change.max(0.0));
+            self.loss.run((-change).max(0.0));
+        }
+        self.previous = Some(input);
+        self.samples += 1;
+    }
+
+    pub fn get(&self) -> Number {
+        self.try_get().expect("No value assigned to RSI.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        let gain = self.gain.try_get()?;
+        let loss = self.loss.try_get()?;
+
+        Some(if gain + loss == 0.0 {
+            50.0
+        } else {
+            100.0 * gain / (gain + loss)
+        })
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.gain.is_ready() && self.loss.is_ready()
+    }
+}
+
+impl Indicator for Rsi {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Rsi::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Rsi::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Rsi::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|rsi| vec![("rsi", rsi)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut rsi = Rsi::new(2.0);
+        rsi.run(10.0);
+        assert_eq!(rsi.try_get(), None);
+        rsi.run(10.0);
+        assert_eq!(rsi.get(), 50.0);
+        rsi.run(14.0);
+        // gain = 2, loss = 0
+        assert_eq!(rsi.get(), 100.0);
+        rsi.run(12.0);
+        // gain = 1, loss = 1
+        assert_eq!(rsi.get(), 50.0);
+        rsi.run(8.0);
+        // gain = 0.5, loss = 2.5
+        assert!((rsi.get() - 100.0 / 6.0).abs() < 1e-4);
+    }
+}
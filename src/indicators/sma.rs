@@ -0,0 +1,107 @@
+use super::Indicator;
+use crate::Number;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sma {
+    window: VecDeque<Number>,
+    period: usize,
+    sum: Number,
+    samples: u64,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Self {
+        debug_assert!(period >= 1);
+
+        Self {
+            window: VecDeque::with_capacity(period + 1),
+            period,
+            sum: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, input: Number) {
+        self.window.push_back(input);
+        self.sum += input;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap();
+        }
+        self.samples += 1;
+
+        // Recompute once per period, so rounding errors don't accumulate.
+        if self.samples % self.period as u64 == 0 {
+            self.sum = self.window.iter().sum();
+        }
+    }
+
+    pub fn get(&self) -> Number {
+        self.try_get().expect("No value assigned to SMA.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        if self.window.is_empty() {
+            None
+        } else {
+            Some(self.sum / self.window.len() as Number)
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.window.len() == self.period
+    }
+}
+
+impl Indicator for Sma {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Sma::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Sma::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Sma::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|sma| vec![("sma", sma)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut sma = Sma::new(3);
+        assert_eq!(sma.try_get(), None);
+        sma.run(2.0);
+        assert_eq!(sma.get(), 2.0);
+        sma.run(4.0);
+        assert_eq!(sma.get(), 3.0);
+        assert!(!sma.is_ready());
+        sma.run(6.0);
+        assert_eq!(sma.get(), 4.0);
+        assert!(sma.is_ready());
+        sma.run(14.0);
+        assert_eq!(sma.get(), 8.0);
+        // Counts all inputs, not only the ones in the window.
+        assert_eq!(sma.samples(), 4);
+    }
+}
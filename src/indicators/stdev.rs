@@ -1,27 +1,72 @@
-use super::Ema;
+use super::{Ema, Indicator};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+/// Exponentially weighted standard deviation, using the incremental update by Welford and West,
+/// which measures each input against the mean before it is updated.
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Stdev {
-    var: Ema,
     mean: Ema,
+    var: Number,
+    alpha: Number,
 }
 
 impl Stdev {
     pub fn new(period: Number) -> Self {
         Self {
-            var: Ema::new(period),
             mean: Ema::new(period),
+            var: 0.0,
+            alpha: 2.0 / (1.0 + period),
         }
     }
 
     pub fn run(&mut self, input: Number) {
+        if let Some(mean) = self.mean.try_get() {
+            let diff = input - mean;
+            self.var = ((1.0 - self.alpha) * (self.var + self.alpha * diff * diff)).max(0.0);
+        }
         self.mean.run(input);
-        self.var.run((input - self.mean.get()).powi(2));
     }
 
     pub fn get(&self) -> Number {
-        self.var.get().sqrt()
+        self.try_get().expect("No value assigned to standard deviation.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.mean.try_get().map(|_| self.var.sqrt())
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.mean.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.mean.is_ready()
+    }
+}
+
+impl Indicator for Stdev {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Stdev::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Stdev::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Stdev::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|stdev| vec![("stdev", stdev)]).unwrap_or_default()
     }
 }
 
@@ -35,6 +80,26 @@ mod tests {
         stdev.run(0.0);
         assert_eq!(stdev.get(), 0.0);
         stdev.run(16.0);
-        assert_eq!(stdev.get(), (32.0 as Number).sqrt());
+        assert_eq!(stdev.get(), 8.0);
+        stdev.run(12.0);
+        assert_eq!(stdev.get(), 6.0);
+    }
+
+    #[test]
+    fn constant() {
+        let mut stdev = Stdev::new(2000.0);
+        for _ in 0..1_000_000 {
+            stdev.run(57_123.45);
+        }
+        assert_eq!(stdev.get(), 0.0);
+    }
+
+    #[test]
+    fn long_run() {
+        let mut stdev = Stdev::new(1000.0);
+        for i in 0..5_000_000 {
+            stdev.run(if i % 2 == 0 { 50_000.01 } else { 49_999.99 });
+        }
+        assert!((stdev.get() - 0.01).abs() < 1e-6);
     }
 }
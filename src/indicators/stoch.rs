@@ -0,0 +1,146 @@
+use super::{Indicator, Sma};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Stochastic oscillator, %K is the position of the close within the high-low range of the last
+/// `period` inputs and %D is its simple moving average.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Stoch {
+    /// Candidates for the highest high and lowest low of the window, with the sample they came
+    /// from. Highs are decreasing and lows increasing, so the extremes are at the front.
+    highs: VecDeque<(u64, Number)>,
+    lows: VecDeque<(u64, Number)>,
+    period: usize,
+    samples: u64,
+    k: Option<Number>,
+    d: Sma,
+}
+
+impl Stoch {
+    pub fn new(period: usize, smoothing: usize) -> Self {
+        debug_assert!(period >= 1);
+
+        Self {
+            highs: VecDeque::with_capacity(period + 1),
+            lows: VecDeque::with_capacity(period + 1),
+            period,
+            samples: 0,
+            k: None,
+            d: Sma::new(smoothing),
+        }
+    }
+
+    pub fn run(&mut self, high: Number, low: Number, close: Number) {
+        let sample = self.samples;
+        self.samples += 1;
+
+        while self.highs.back().map_or(false, |&(_, back)| back <= high) {
+            self.highs.pop_back();
+        }
+        self.highs.push_back((sample, high));
+        while self.lows.back().map_or(false, |&(_, back)| back >= low) {
+            self.lows.pop_back();
+        }
+        self.lows.push_back((sample, low));
+
+        let first = self.samples.saturating_sub(self.period as u64);
+        while self.highs.front().map_or(false, |&(sample, _)| sample < first) {
+            self.highs.pop_front();
+        }
+        while self.lows.front().map_or(false, |&(sample, _)| sample < first) {
+            self.lows.pop_front();
+        }
+
+        let highest = self.highs.front().unwrap().1;
+        let lowest = self.lows.front().unwrap().1;
+
+        let k = if highest > lowest {
+            100.0 * (close - lowest) / (highest - lowest)
+        } else {
+            50.0
+        };
+        self.k = Some(k);
+        self.d.run(k);
+    }
+
+    pub fn get_k(&self) -> Number {
+        self.k.expect("No value assigned to stochastic oscillator.")
+    }
+
+    pub fn get_d(&self) -> Number {
+        self.d.get()
+    }
+
+    pub fn try_get_k(&self) -> Option<Number> {
+        self.k
+    }
+
+    pub fn try_get_d(&self) -> Option<Number> {
+        self.d.try_get()
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.samples >= self.period as u64 && self.d.is_ready()
+    }
+}
+
+impl Indicator for Stoch {
+    type Input = (Number, Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (high, low, close): (Number, Number, Number)) {
+        Stoch::run(self, high, low, close)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get_k()
+    }
+
+    fn is_ready(&self) -> bool {
+        Stoch::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Stoch::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match (self.try_get_k(), self.try_get_d()) {
+            (Some(k), Some(d)) => vec![("k", k), ("d", d)],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut stoch = Stoch::new(3, 2);
+        stoch.run(10.0, 10.0, 10.0);
+        assert_eq!(stoch.get_k(), 50.0);
+        stoch.run(12.0, 8.0, 11.0);
+        assert_eq!(stoch.get_k(), 75.0);
+        assert_eq!(stoch.get_d(), 62.5);
+        stoch.run(13.0, 9.0, 9.0);
+        assert_eq!(stoch.get_k(), 20.0);
+        assert_eq!(stoch.get_d(), 47.5);
+        // The first input drops out of the window, the low of 8 remains.
+        stoch.run(14.0, 12.0, 14.0);
+        assert_eq!(stoch.get_k(), 100.0);
+        assert!(stoch.is_ready());
+        // The high of 14 stays the highest until it drops out of the window.
+        stoch.run(11.0, 11.0, 11.0);
+        assert_eq!(stoch.get_k(), 40.0);
+        stoch.run(10.0, 10.0, 10.0);
+        assert_eq!(stoch.get_k(), 0.0);
+        assert_eq!(stoch.samples(), 6);
+    }
+}
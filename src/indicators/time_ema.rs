@@ -0,0 +1,135 @@
+use super::Indicator;
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// An EMA that decays by elapsed time instead of by sample count. `period` is given in
+/// milliseconds and matches the horizon of an `Ema` with the same period sampled once per
+/// millisecond, independent of how often samples actually arrive.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct TimeEma {
+    output: Option<Number>,
+    // Output before the sample(s) of the current timestamp were applied.
+    base: Number,
+    alpha: Number,
+    tau: Number,
+    period: i64,
+    first: i64,
+    timestamp: i64,
+    samples: u64,
+}
+
+impl TimeEma {
+    pub fn new(period: i64) -> Self {
+        debug_assert!(period >= 1);
+
+        Self {
+            output: None,
+            base: 0.0,
+            alpha: 1.0,
+            tau: period as Number / 2.0,
+            period,
+            first: 0,
+            timestamp: 0,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, input: Number, timestamp: i64) {
+        if self.output.is_some() {
+            // Several samples with the same timestamp don't decay the average further, the last
+            // one replaces the earlier ones instead.
+            if timestamp > self.timestamp {
+                self.base = self.output.unwrap();
+                self.alpha = 1.0 - (-((timestamp - self.timestamp) as Number) / self.tau).exp();
+                self.timestamp = timestamp;
+            }
+            self.output = Some(input * self.alpha + self.base * (1.0 - self.alpha));
+        } else {
+            self.output = Some(input);
+            self.first = timestamp;
+            self.timestamp = timestamp;
+        }
+        self.samples += 1;
+    }
+
+    pub fn get(&self) -> Number {
+        self.output.expect("No value assigned to time EMA.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.output
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// A time EMA is considered converged once it has covered at least `period` milliseconds.
+    pub fn is_ready(&self) -> bool {
+        self.output.is_some() && self.timestamp - self.first >= self.period
+    }
+}
+
+impl Indicator for TimeEma {
+    type Input = (Number, i64);
+    type Output = Number;
+
+    fn run(&mut self, (input, timestamp): (Number, i64)) {
+        TimeEma::run(self, input, timestamp)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        TimeEma::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        TimeEma::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|ema| vec![("ema", ema)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut ema = TimeEma::new(2000);
+        ema.run(0.0, 0);
+        assert_eq!(ema.get(), 0.0);
+        ema.run(1.0, 1000);
+        assert_eq!(ema.get(), 1.0 - (-1.0 as Number).exp());
+        ema.run(1.0, 1000);
+        assert_eq!(ema.get(), 1.0 - (-1.0 as Number).exp());
+        ema.run(0.0, 1000);
+        assert_eq!(ema.get(), 0.0);
+        assert!(!ema.is_ready());
+        ema.run(0.0, 2000);
+        assert!(ema.is_ready());
+    }
+
+    #[test]
+    fn frequency_independent() {
+        let mut sparse = TimeEma::new(60_000);
+        let mut dense = TimeEma::new(60_000);
+        sparse.run(0.0, 0);
+        dense.run(0.0, 0);
+
+        for i in 1..=60 {
+            sparse.run(1.0, i * 1000);
+        }
+        for i in 1..=6000 {
+            dense.run(1.0, i * 10);
+        }
+
+        assert!((sparse.get() - dense.get()).abs() < 1e-4);
+        assert!((sparse.get() - (1.0 - (-2.0 as Number).exp())).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,86 @@
+use super::{Indicator, TimeEma};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct TimeLsq {
+    aa: TimeEma,
+    ab: TimeEma,
+    lsq: Number,
+}
+
+impl TimeLsq {
+    pub fn new(period: i64) -> Self {
+        Self {
+            aa: TimeEma::new(period),
+            ab: TimeEma::new(period),
+            lsq: 0.0,
+        }
+    }
+
+    pub fn run(&mut self, a: Number, b: Number, timestamp: i64) {
+        self.aa.run(a.powi(2), timestamp);
+        self.ab.run(a * b, timestamp);
+
+        // Without any variance in `a` there is no slope to estimate, keep the last one instead.
+        let lsq = self.ab.get() / self.aa.get();
+        if self.aa.get() > Number::EPSILON && lsq.is_finite() {
+            self.lsq = lsq;
+        }
+    }
+
+    pub fn get(&self) -> Number {
+        self.lsq
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.aa.try_get().map(|_| self.lsq)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.aa.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.aa.is_ready() && self.ab.is_ready()
+    }
+}
+
+impl Indicator for TimeLsq {
+    type Input = (Number, Number, i64);
+    type Output = Number;
+
+    fn run(&mut self, (a, b, timestamp): (Number, Number, i64)) {
+        TimeLsq::run(self, a, b, timestamp)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        TimeLsq::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        TimeLsq::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|lsq| vec![("lsq", lsq)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut lsq = TimeLsq::new(1000);
+        lsq.run(2.0, 4.0, 0);
+        assert_eq!(lsq.get(), 2.0);
+        lsq.run(-1.0, -2.0, 1000);
+        assert_eq!(lsq.get(), 2.0);
+    }
+}
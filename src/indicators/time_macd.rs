@@ -0,0 +1,99 @@
+use super::{Indicator, TimeEma};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct TimeMacd {
+    fast: TimeEma,
+    slow: TimeEma,
+    signal: TimeEma,
+}
+
+impl TimeMacd {
+    pub fn new(fast_period: i64, slow_period: i64, signal_period: i64) -> Self {
+        Self {
+            fast: TimeEma::new(fast_period),
+            slow: TimeEma::new(slow_period),
+            signal: TimeEma::new(signal_period),
+        }
+    }
+
+    pub fn run(&mut self, input: Number, timestamp: i64) {
+        self.fast.run(input, timestamp);
+        self.slow.run(input, timestamp);
+        self.signal.run(self.get(), timestamp);
+    }
+
+    pub fn get(&self) -> Number {
+        self.fast.get() - self.slow.get()
+    }
+
+    pub fn get_signal(&self) -> Number {
+        self.signal.get()
+    }
+
+    pub fn get_hist(&self) -> Number {
+        self.get() - self.get_signal()
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        Some(self.fast.try_get()? - self.slow.try_get()?)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.slow.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.fast.is_ready() && self.slow.is_ready() && self.signal.is_ready()
+    }
+}
+
+impl Indicator for TimeMacd {
+    type Input = (Number, i64);
+    type Output = Number;
+
+    fn run(&mut self, (input, timestamp): (Number, i64)) {
+        TimeMacd::run(self, input, timestamp)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        TimeMacd::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        TimeMacd::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        match self.try_get() {
+            Some(macd) => vec![
+                ("macd", macd),
+                ("signal", self.get_signal()),
+                ("hist", self.get_hist()),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut macd = TimeMacd::new(1000, 4000, 1000);
+        macd.run(0.0, 0);
+        assert_eq!(macd.get(), 0.0);
+        macd.run(1.0, 1000);
+        let fast = 1.0 - (-2.0 as Number).exp();
+        let slow = 1.0 - (-0.5 as Number).exp();
+        assert_eq!(macd.get(), fast - slow);
+        assert_eq!(macd.get_signal(), (fast - slow) * fast);
+    }
+}
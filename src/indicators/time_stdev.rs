@@ -0,0 +1,115 @@
+use super::{Indicator, TimeEma};
+use crate::Number;
+use serde::{Deserialize, Serialize};
+
+/// Standard deviation that decays by elapsed time like `TimeEma`, using the incremental update by
+/// Welford and West, which measures each input against the mean before it is updated.
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct TimeStdev {
+    mean: TimeEma,
+    var: Number,
+    // Mean and variance before the sample(s) of the current timestamp were applied.
+    base: (Number, Number),
+    alpha: Number,
+    tau: Number,
+    timestamp: i64,
+}
+
+impl TimeStdev {
+    pub fn new(period: i64) -> Self {
+        Self {
+            mean: TimeEma::new(period),
+            var: 0.0,
+            base: (0.0, 0.0),
+            alpha: 1.0,
+            tau: period as Number / 2.0,
+            timestamp: 0,
+        }
+    }
+
+    pub fn run(&mut self, input: Number, timestamp: i64) {
+        if let Some(mean) = self.mean.try_get() {
+            // Like the mean, several samples with the same timestamp replace each other.
+            if timestamp > self.timestamp {
+                self.base = (mean, self.var);
+                self.alpha = 1.0 - (-((timestamp - self.timestamp) as Number) / self.tau).exp();
+                self.timestamp = timestamp;
+            }
+            let (mean, var) = self.base;
+            let diff = input - mean;
+            self.var = ((1.0 - self.alpha) * (var + self.alpha * diff * diff)).max(0.0);
+        } else {
+            self.timestamp = timestamp;
+        }
+        self.mean.run(input, timestamp);
+    }
+
+    pub fn get(&self) -> Number {
+        self.try_get().expect("No value assigned to time standard deviation.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.mean.try_get().map(|_| self.var.sqrt())
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.mean.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.mean.is_ready()
+    }
+}
+
+impl Indicator for TimeStdev {
+    type Input = (Number, i64);
+    type Output = Number;
+
+    fn run(&mut self, (input, timestamp): (Number, i64)) {
+        TimeStdev::run(self, input, timestamp)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        TimeStdev::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        TimeStdev::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|stdev| vec![("stdev", stdev)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut stdev = TimeStdev::new(1000);
+        stdev.run(4.0, 0);
+        assert_eq!(stdev.get(), 0.0);
+        for i in 1..=100 {
+            stdev.run(4.0, i * 100);
+        }
+        assert_eq!(stdev.get(), 0.0);
+        assert!(stdev.is_ready());
+    }
+
+    #[test]
+    fn alternating() {
+        let mut stdev = TimeStdev::new(10000);
+        for i in 0..1000 {
+            stdev.run(if i % 2 == 0 { 0.0 } else { 2.0 }, i * 100);
+            // A second sample at the same time replaces the first one.
+            stdev.run(if i % 2 == 0 { 0.0 } else { 2.0 }, i * 100);
+        }
+        assert!((stdev.get() - 1.0).abs() < 0.01);
+    }
+}
@@ -1,7 +1,8 @@
-use super::{Cum, Ema, Lsq};
+use super::{Cum, Ema, Indicator, Lsq};
 use crate::Number;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Val {
     mov: Cum,
     mov_ema: Ema,
@@ -30,16 +31,49 @@ impl Val {
         let price_aligned = price - self.price_ema.get();
         self.marginal_price.run(mov_aligned, price_aligned);
 
-        if !self.marginal_price.get().is_nan() {
-            self.val = self.price_ema.get() + mov_aligned * self.marginal_price.get();
-        } else {
-            self.val = self.price_ema.get()
-        }
+        self.val = self.price_ema.get() + mov_aligned * self.marginal_price.get();
     }
 
     pub fn get(&self) -> Number {
         self.val
     }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.price_ema.try_get().map(|_| self.val)
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.price_ema.samples()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.mov_ema.is_ready() && self.price_ema.is_ready() && self.marginal_price.is_ready()
+    }
+}
+
+impl Indicator for Val {
+    type Input = (Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (quantity, price): (Number, Number)) {
+        Val::run(self, quantity, price)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Val::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Val::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|val| vec![("val", val)]).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
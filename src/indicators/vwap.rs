@@ -0,0 +1,121 @@
+use super::Indicator;
+use crate::Number;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Volume weighted average price over the last `period` trades. The sign of the quantity
+/// (buy or sell side) is ignored.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Vwap {
+    window: VecDeque<(Number, Number)>,
+    period: usize,
+    volume: Number,
+    turnover: Number,
+    vwap: Option<Number>,
+    samples: u64,
+}
+
+impl Vwap {
+    pub fn new(period: usize) -> Self {
+        debug_assert!(period >= 1);
+
+        Self {
+            window: VecDeque::with_capacity(period + 1),
+            period,
+            volume: 0.0,
+            turnover: 0.0,
+            vwap: None,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, quantity: Number, price: Number) {
+        self.window.push_back((quantity.abs(), price));
+        self.volume += quantity.abs();
+        self.turnover += quantity.abs() * price;
+        if self.window.len() > self.period {
+            let (quantity, price) = self.window.pop_front().unwrap();
+            self.volume -= quantity;
+            self.turnover -= quantity * price;
+        }
+        self.samples += 1;
+
+        // Recompute once per period, so rounding errors don't accumulate.
+        if self.samples % self.period as u64 == 0 {
+            let (volume, turnover) =
+                self.window
+                    .iter()
+                    .fold((0.0, 0.0), |(volume, turnover), (quantity, price)| {
+                        (volume + quantity, turnover + quantity * price)
+                    });
+            self.volume = volume;
+            self.turnover = turnover;
+        }
+
+        if self.volume > 0.0 {
+            self.vwap = Some(self.turnover / self.volume);
+        } else if self.vwap.is_none() {
+            self.vwap = Some(price);
+        }
+    }
+
+    pub fn get(&self) -> Number {
+        self.vwap.expect("No value assigned to VWAP.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        self.vwap
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.window.len() == self.period
+    }
+}
+
+impl Indicator for Vwap {
+    type Input = (Number, Number);
+    type Output = Number;
+
+    fn run(&mut self, (quantity, price): (Number, Number)) {
+        Vwap::run(self, quantity, price)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Vwap::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Vwap::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|vwap| vec![("vwap", vwap)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut vwap = Vwap::new(2);
+        vwap.run(0.0, 5.0);
+        assert_eq!(vwap.get(), 5.0);
+        vwap.run(1.0, 10.0);
+        assert_eq!(vwap.get(), 10.0);
+        vwap.run(-3.0, 20.0);
+        assert_eq!(vwap.get(), 17.5);
+        vwap.run(1.0, 40.0);
+        assert_eq!(vwap.get(), 25.0);
+        assert_eq!(vwap.samples(), 4);
+    }
+}
@@ -0,0 +1,120 @@
+use super::Indicator;
+use crate::Number;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Linearly weighted moving average, the most recent input has weight `period`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Wma {
+    window: VecDeque<Number>,
+    period: usize,
+    /// Sum of the inputs in the window, and the sum weighted by position.
+    sum: Number,
+    weighted: Number,
+    samples: u64,
+}
+
+impl Wma {
+    pub fn new(period: usize) -> Self {
+        debug_assert!(period >= 1);
+
+        Self {
+            window: VecDeque::with_capacity(period + 1),
+            period,
+            sum: 0.0,
+            weighted: 0.0,
+            samples: 0,
+        }
+    }
+
+    pub fn run(&mut self, input: Number) {
+        self.window.push_back(input);
+        if self.window.len() > self.period {
+            // Every input moves down one weight, the oldest one drops out.
+            self.weighted += self.period as Number * input - self.sum;
+            self.sum += input - self.window.pop_front().unwrap();
+        } else {
+            self.weighted += self.window.len() as Number * input;
+            self.sum += input;
+        }
+        self.samples += 1;
+
+        // Recompute once per period, so rounding errors don't accumulate.
+        if self.samples % self.period as u64 == 0 {
+            self.sum = self.window.iter().sum();
+            self.weighted = self
+                .window
+                .iter()
+                .enumerate()
+                .map(|(i, input)| (i + 1) as Number * input)
+                .sum();
+        }
+    }
+
+    pub fn get(&self) -> Number {
+        self.try_get().expect("No value assigned to WMA.")
+    }
+
+    pub fn try_get(&self) -> Option<Number> {
+        if self.window.is_empty() {
+            None
+        } else {
+            let n = self.window.len() as Number;
+            Some(self.weighted / (n * (n + 1.0) / 2.0))
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.window.len() == self.period
+    }
+}
+
+impl Indicator for Wma {
+    type Input = Number;
+    type Output = Number;
+
+    fn run(&mut self, input: Number) {
+        Wma::run(self, input)
+    }
+
+    fn get(&self) -> Option<Number> {
+        self.try_get()
+    }
+
+    fn is_ready(&self) -> bool {
+        Wma::is_ready(self)
+    }
+
+    fn samples(&self) -> u64 {
+        Wma::samples(self)
+    }
+
+    fn outputs(&self) -> Vec<(&'static str, Number)> {
+        self.try_get().map(|wma| vec![("wma", wma)]).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut wma = Wma::new(3);
+        wma.run(3.0);
+        assert_eq!(wma.get(), 3.0);
+        wma.run(6.0);
+        assert_eq!(wma.get(), 5.0);
+        wma.run(9.0);
+        assert_eq!(wma.get(), 7.0);
+        wma.run(0.0);
+        assert_eq!(wma.get(), 4.0);
+        wma.run(3.0);
+        assert_eq!(wma.get(), 3.0);
+        assert_eq!(wma.samples(), 5);
+    }
+}
@@ -58,6 +58,18 @@ impl Database {
                     .execute(&pool)
                     .await?;
                 },
+                Message::Adjust(position) => {
+                    sqlx::query!(
+                        r#"
+                            UPDATE positions SET stop_loss = $3 WHERE timestamp = $1 AND market = $2;
+                        "#,
+                        position.timestamp,
+                        position.market,
+                        position.stop_loss,
+                    )
+                    .execute(&pool)
+                    .await?;
+                },
                 Message::Close(position) => {
                     sqlx::query!(
                         r#"
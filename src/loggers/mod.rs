@@ -1,7 +1,7 @@
 mod telegram;
 mod database;
 
-use crate::exchanges::binance::Position;
+use crate::exchanges::live::Position;
 use async_trait::async_trait;
 pub use telegram::Telegram;
 pub use database::Database;
@@ -25,6 +25,8 @@ impl From<UnboundedSender<Message>> for Sender {
 
 pub enum Message {
     Open(Position),
+    /// The stop loss of an open position was moved.
+    Adjust(Position),
     Close(Position),
 }
 
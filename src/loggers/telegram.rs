@@ -1,5 +1,5 @@
 use super::{Logger, Message, Sender};
-use crate::exchanges::binance::Position;
+use crate::exchanges::live::Position;
 use async_trait::async_trait;
 use rust_decimal::prelude::*;
 use std::env;
@@ -63,6 +63,8 @@ impl Telegram {
             match message {
                 Message::Open(Position {
                     market,
+                    base,
+                    quote,
                     quantity,
                     buy_price,
                     take_profit,
@@ -72,44 +74,61 @@ impl Telegram {
                     log::info!("Logger received open position");
 
                     let base_quantity = quantity / buy_price;
-                    let usdt_offset = market.find("USDT").unwrap();
-                    let asset: String = market.clone().drain(..usdt_offset).collect();
 
                     api.send(SendMessage::new(
                         self.channel_id,
                         format!(
-                                "🔵 Opened Position {}\n\nQuantity: {:.4} {} ({:.2} USDT)\nBuy Price: {:.4} USDT\nTake Profit: {:.4} USDT\nStop Loss: {:.4} USDT",
-                                market, base_quantity, asset, quantity, buy_price, take_profit, stop_loss
+                                "🔵 Opened Position {}\n\nQuantity: {:.4} {} ({:.2} {})\nBuy Price: {:.4} {}\nTake Profit: {:.4} {}\nStop Loss: {:.4} {}",
+                                market, base_quantity, base, quantity, quote, buy_price, quote, take_profit, quote, stop_loss, quote
+                            ),
+                    )).await?;
+                },
+                Message::Adjust(Position {
+                    market,
+                    quote,
+                    buy_price,
+                    stop_loss,
+                    ..
+                }) => {
+                    log::info!("Logger received adjusted position.");
+
+                    api.send(SendMessage::new(
+                        self.channel_id,
+                        format!(
+                                "🟡 Adjusted Position {}\n\nBuy Price: {:.4} {}\nStop Loss: {:.4} {}",
+                                market, buy_price, quote, stop_loss, quote
                             ),
                     )).await?;
                 },
                 Message::Close(
                     Position {
                         market,
+                        base,
+                        quote,
                         quantity,
                         buy_price,
                         take_profit,
                         stop_loss,
                         profitable,
+                        fees,
                         ..
                     }
                 ) => {
                     log::info!("Logger received close position.");
 
                     let base_quantity = quantity / buy_price;
-                    let usdt_offset = market.find("USDT").unwrap();
-                    let asset: String = market.clone().drain(..usdt_offset).collect();
 
                     if profitable.unwrap() {
                         let profit = take_profit / buy_price - Decimal::one();
                         api.send(SendMessage::new(
                             self.channel_id,
                             format!(
-                                    "🟢 Closed Position {}\n\nQuantity:\t {:.4} {}\t ({:.2} USDT)\nSell Price:\t {:.4} USDT\nProfit:\t {:+.2}%\t ({:+.2} USDT)",
+                                    "🟢 Closed Position {}\n\nQuantity:\t {:.4} {}\t ({:.2} {})\nSell Price:\t {:.4} {}\nProfit:\t {:+.2}%\t ({:+.2} {})\nFees:\t {:.4} {}",
                                     market,
-                                    base_quantity, asset, quantity,
-                                    take_profit,
-                                    profit * Decimal::new(100, 0), profit * quantity
+                                    base_quantity, base, quantity, quote,
+                                    take_profit, quote,
+                                    profit * Decimal::new(100, 0), profit * quantity, quote,
+                                    fees, quote
                                 ),
                         )).await?;
                     } else {
@@ -117,11 +136,12 @@ impl Telegram {
                         api.send(SendMessage::new(
                             self.channel_id,
                             format!(
-                                    "🔴 Closed Position {}\n\nQuantity:\t {:.4} {}\t ({:.2} USDT)\nSell Price:\t {:.4} USDT\nLoss:\t {:+.2}%\t ({:+.2} USDT)",
+                                    "🔴 Closed Position {}\n\nQuantity:\t {:.4} {}\t ({:.2} {})\nSell Price:\t {:.4} {}\nLoss:\t {:+.2}%\t ({:+.2} {})\nFees:\t {:.4} {}",
                                     market,
-                                    base_quantity, asset, quantity,
-                                    stop_loss,
-                                    loss * Decimal::new(100, 0), loss * quantity
+                                    base_quantity, base, quantity, quote,
+                                    stop_loss, quote,
+                                    loss * Decimal::new(100, 0), loss * quantity, quote,
+                                    fees, quote
                                 ),
                         )).await?;
                     }
@@ -5,16 +5,19 @@ mod error;
 pub mod exchanges;
 pub mod indicators;
 pub mod loggers;
+pub mod risk;
+pub mod sizing;
 pub mod strategies;
 
 pub use error::Error;
 
 use exchanges::*;
+use risk::RiskManager;
 use strategies::*;
 
 use chrono::{TimeZone, Utc};
 
-type Number = f32;
+type Number = f64;
 type Market = String;
 
 #[tokio::main]
@@ -25,7 +28,8 @@ async fn main() {
     log::info!("Starting trader.");
 
     #[allow(unused_mut)]
-    let mut strategy = Duplicated::new(Interval::new(Custom::new(), 1000 * 60));
+    let mut strategy =
+        Duplicated::new(Interval::new(Custom::new(), 1000 * 60)).with_venue("Binance");
     let markets = vec![
         "BTCUSDT",
         "ETHUSDT",
@@ -48,13 +52,31 @@ async fn main() {
         "VETUSDT",
     ];
 
-    #[cfg(not(feature = "live"))]
+    #[cfg(feature = "record")]
+    {
+        log::info!("Recording trades.");
+
+        Recorder::new(Binance::new(false).await, &markets)
+            .await
+            .run()
+            .await;
+    }
+
+    #[cfg(not(any(feature = "live", feature = "record")))]
     {
         log::warn!("Trading in simulated environment.");
 
         let mut simulated = Multi::new()
             .with(Simulated::new(Hold::new(), 0.001, 13))
-            .with(Simulated::new(strategy, 0.001, 2));
+            .with(
+                Simulated::new(strategy, 0.001, 2).with_risk(
+                    RiskManager::new()
+                        .with_max_open_positions(2)
+                        .with_cool_down(2, 1000 * 60 * 60 * 24)
+                        .with_entry_cool_down(1000 * 60 * 60 * 12),
+                ),
+            )
+            .parallel(4096);
 
         Historical::new(
             &markets,
@@ -71,22 +93,51 @@ async fn main() {
         simulated.plot();
     }
 
-    #[cfg(feature = "live")]
+    #[cfg(all(feature = "live", not(feature = "record")))]
     {
-        use chrono::Duration;
-
         log::warn!("Trading on live exchange.");
 
-        
-        
-        Historical::new(
-            &markets,
-            Utc.ymd(2021, 4, 1).and_hms(0, 0, 0),
-            Utc::now(),
-            false
-        )
+        const SNAPSHOT: &str = "strategy.bin";
+
+        match Snapshot::load(SNAPSHOT) {
+            Ok((snapshot, timestamp)) => {
+                log::info!("Restored strategy from snapshot, catching up.");
+                strategy = snapshot;
+
+                // Replay what happened while the strategy was stopped.
+                Historical::new(
+                    &markets,
+                    Utc.timestamp_millis(timestamp),
+                    Utc::now(),
+                    false
+                )
+                    .without_finish()
+                    .run(&mut strategy)
+                    .await;
+            }
+            Err(err) => {
+                log::warn!("Couldn't restore strategy from snapshot ({}), warming up.", err);
+
+                Historical::new(
+                    &markets,
+                    Utc.ymd(2021, 4, 1).and_hms(0, 0, 0),
+                    Utc::now(),
+                    false
+                )
+                    .without_finish()
+                    .run(&mut strategy)
+                    .await;
+            }
+        }
+
+        if !strategy.is_ready() {
+            log::warn!("Strategy hasn't warmed up yet, no orders will be placed until it has.");
+        }
+
+        let mut strategy = Checkpoint::new(strategy, SNAPSHOT, 1000 * 60 * 10);
+        Live::new(Binance::new(false).await, &markets)
+            .await
             .run(&mut strategy)
             .await;
-        Binance::new(&markets, false).await.run(&mut strategy).await;
     }
 }
@@ -0,0 +1,380 @@
+use crate::{Market, Number, Order};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+const DAY: i64 = 1000 * 60 * 60 * 24;
+
+/// The state of the account an order is checked against. Values are in quote asset, or in any
+/// other unit as long as it is the same for all of them.
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub total_value: Number,
+    pub cash: Number,
+    pub open_positions: usize,
+    /// Value already invested in the asset the order wants to buy.
+    pub exposure: Number,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    MaxOpenPositions,
+    MaxExposure,
+    CashReserve,
+    DailyLossLimit,
+    MaxDrawdown,
+    CoolDown { until: i64 },
+    EntryCoolDown { until: i64 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxOpenPositions => write!(f, "too many open positions"),
+            Self::MaxExposure => write!(f, "already invested in this asset"),
+            Self::CashReserve => write!(f, "no cash left above the reserve"),
+            Self::DailyLossLimit => write!(f, "daily loss limit reached"),
+            Self::MaxDrawdown => write!(f, "maximum drawdown reached, trading halted"),
+            Self::CoolDown { until } => write!(f, "cooling down after losses until {}", until),
+            Self::EntryCoolDown { until } => {
+                write!(f, "cooling down after last entry until {}", until)
+            }
+        }
+    }
+}
+
+/// Portfolio level rules every order has to pass, shared by simulations and live trading. All
+/// rules are disabled until configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskManager {
+    max_open_positions: Option<usize>,
+    max_exposure: Option<Number>,
+    cash_reserve: Number,
+    daily_loss_limit: Option<Number>,
+    max_drawdown: Option<Number>,
+    cool_down: Option<(u8, i64)>,
+    entry_cool_down: Option<i64>,
+
+    consecutive_losses: u8,
+    paused_until: i64,
+    entries: HashMap<Market, i64>,
+    peak: Number,
+    day: Option<i64>,
+    day_start: Number,
+    halted: bool,
+}
+
+impl RiskManager {
+    pub fn new() -> Self {
+        Self {
+            max_open_positions: None,
+            max_exposure: None,
+            cash_reserve: 0.0,
+            daily_loss_limit: None,
+            max_drawdown: None,
+            cool_down: None,
+            entry_cool_down: None,
+
+            consecutive_losses: 0,
+            paused_until: 0,
+            entries: HashMap::new(),
+            peak: 0.0,
+            day: None,
+            day_start: 0.0,
+            halted: false,
+        }
+    }
+
+    pub fn with_max_open_positions(mut self, max_open_positions: usize) -> Self {
+        self.max_open_positions = Some(max_open_positions);
+        self
+    }
+
+    /// Keeps every asset, including the order, within `fraction` of the total value. Orders are
+    /// made smaller to fit, and rejected once the asset is at the limit.
+    pub fn with_max_exposure(mut self, fraction: Number) -> Self {
+        self.max_exposure = Some(fraction);
+        self
+    }
+
+    /// Keeps `fraction` of the total value in cash.
+    pub fn with_cash_reserve(mut self, fraction: Number) -> Self {
+        self.cash_reserve = fraction;
+        self
+    }
+
+    /// Stops opening positions for the rest of the day once the value fell by `fraction` since
+    /// the start of the day. Days are in UTC.
+    pub fn with_daily_loss_limit(mut self, fraction: Number) -> Self {
+        self.daily_loss_limit = Some(fraction);
+        self
+    }
+
+    /// Stops opening positions for good once the value fell by `fraction` from its peak.
+    pub fn with_max_drawdown(mut self, fraction: Number) -> Self {
+        self.max_drawdown = Some(fraction);
+        self
+    }
+
+    /// Pauses for `duration` milliseconds after `losses` consecutive losses.
+    pub fn with_cool_down(mut self, losses: u8, duration: i64) -> Self {
+        self.cool_down = Some((losses, duration));
+        self
+    }
+
+    /// Waits `duration` milliseconds after an entry before entering the same market again.
+    pub fn with_entry_cool_down(mut self, duration: i64) -> Self {
+        self.entry_cool_down = Some(duration);
+        self
+    }
+
+    /// Rejects all orders before `timestamp`.
+    pub fn pause_until(&mut self, timestamp: i64) {
+        self.paused_until = self.paused_until.max(timestamp);
+    }
+
+    pub fn max_open_positions(&self) -> Option<usize> {
+        self.max_open_positions
+    }
+
+    /// The amount of cash that must not be invested.
+    pub fn cash_reserve(&self, portfolio: &Portfolio) -> Number {
+        portfolio.total_value * self.cash_reserve
+    }
+
+    /// The most an order may invest, the cash above the reserve as long as the asset stays within
+    /// the maximum exposure.
+    pub fn available(&self, portfolio: &Portfolio) -> Number {
+        let cash = portfolio.cash - self.cash_reserve(portfolio);
+        match self.max_exposure {
+            Some(max_exposure) => {
+                cash.min(portfolio.total_value * max_exposure - portfolio.exposure)
+            }
+            None => cash,
+        }
+    }
+
+    /// Tracks the total value for the drawdown and daily loss rules.
+    pub fn update(&mut self, total_value: Number, timestamp: i64) {
+        if self.day != Some(timestamp / DAY) {
+            self.day = Some(timestamp / DAY);
+            self.day_start = total_value;
+        }
+        self.peak = self.peak.max(total_value);
+
+        if let Some(max_drawdown) = self.max_drawdown {
+            if !self.halted && total_value < self.peak * (1.0 - max_drawdown) {
+                log::warn!(
+                    "Value fell from {} to {}, halting trading.",
+                    self.peak,
+                    total_value
+                );
+                self.halted = true;
+            }
+        }
+    }
+
+    pub fn opened(&mut self, market: &Market, timestamp: i64) {
+        self.entries.insert(market.clone(), timestamp);
+    }
+
+    pub fn closed(&mut self, profitable: bool, timestamp: i64) {
+        if profitable {
+            self.consecutive_losses = 0;
+            return;
+        }
+
+        self.consecutive_losses = self.consecutive_losses.saturating_add(1);
+        if let Some((losses, duration)) = self.cool_down {
+            if self.consecutive_losses >= losses {
+                self.consecutive_losses = 0;
+                self.pause_until(timestamp + duration);
+            }
+        }
+    }
+
+    /// Checks `order` against all rules and logs why it was rejected.
+    pub fn check(
+        &self,
+        order: &Order,
+        portfolio: &Portfolio,
+        timestamp: i64,
+    ) -> Result<(), Rejection> {
+        let result = self.check_rules(order, portfolio, timestamp);
+        if let Err(rejection) = &result {
+            log::info!("Rejected order {}: {}.", order, rejection);
+        }
+
+        result
+    }
+
+    fn check_rules(
+        &self,
+        order: &Order,
+        portfolio: &Portfolio,
+        timestamp: i64,
+    ) -> Result<(), Rejection> {
+        if self.halted {
+            return Err(Rejection::MaxDrawdown);
+        }
+        if timestamp < self.paused_until {
+            return Err(Rejection::CoolDown {
+                until: self.paused_until,
+            });
+        }
+        if let Some(daily_loss_limit) = self.daily_loss_limit {
+            if self.day == Some(timestamp / DAY)
+                && portfolio.total_value < self.day_start * (1.0 - daily_loss_limit)
+            {
+                return Err(Rejection::DailyLossLimit);
+            }
+        }
+        if let Some(max_open_positions) = self.max_open_positions {
+            if portfolio.open_positions >= max_open_positions {
+                return Err(Rejection::MaxOpenPositions);
+            }
+        }
+        if let Some(max_exposure) = self.max_exposure {
+            if portfolio.exposure >= portfolio.total_value * max_exposure {
+                return Err(Rejection::MaxExposure);
+            }
+        }
+        if let Some(duration) = self.entry_cool_down {
+            if let Some(&entry) = self.entries.get(&order.market) {
+                if timestamp < entry + duration {
+                    return Err(Rejection::EntryCoolDown {
+                        until: entry + duration,
+                    });
+                }
+            }
+        }
+        if portfolio.cash <= self.cash_reserve(portfolio) {
+            return Err(Rejection::CashReserve);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RiskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Side;
+    use rust_decimal::Decimal;
+
+    fn order() -> Order {
+        Order {
+            market: "BTCUSDT".to_owned(),
+            price: Decimal::new(100, 0),
+            take_profit: None,
+            stop_loss: None,
+            tranches: Vec::new(),
+            size: None,
+            side: Side::Buy,
+        }
+    }
+
+    fn portfolio(total_value: Number) -> Portfolio {
+        Portfolio {
+            total_value,
+            cash: total_value,
+            open_positions: 0,
+            exposure: 0.0,
+        }
+    }
+
+    #[test]
+    fn positions_and_exposure() {
+        let risk = RiskManager::new()
+            .with_max_open_positions(2)
+            .with_max_exposure(0.1)
+            .with_cash_reserve(0.2);
+
+        assert_eq!(risk.check(&order(), &portfolio(100.0), 0), Ok(()));
+
+        let mut full = portfolio(100.0);
+        full.open_positions = 2;
+        assert_eq!(
+            risk.check(&order(), &full, 0),
+            Err(Rejection::MaxOpenPositions)
+        );
+
+        let mut invested = portfolio(100.0);
+        invested.exposure = 11.0;
+        assert_eq!(
+            risk.check(&order(), &invested, 0),
+            Err(Rejection::MaxExposure)
+        );
+
+        let mut broke = portfolio(100.0);
+        broke.cash = 20.0;
+        assert_eq!(risk.check(&order(), &broke, 0), Err(Rejection::CashReserve));
+    }
+
+    #[test]
+    fn available() {
+        let risk = RiskManager::new().with_cash_reserve(0.2);
+        assert_eq!(risk.available(&portfolio(100.0)), 80.0);
+
+        // The order is made smaller so the asset stays within 10% of the value.
+        let risk = risk.with_max_exposure(0.1);
+        let mut invested = portfolio(100.0);
+        invested.exposure = 4.0;
+        assert_eq!(risk.available(&invested), 6.0);
+    }
+
+    #[test]
+    fn cool_down() {
+        let mut risk = RiskManager::new()
+            .with_cool_down(2, 1000)
+            .with_entry_cool_down(500);
+
+        risk.opened(&"BTCUSDT".to_owned(), 0);
+        assert_eq!(
+            risk.check(&order(), &portfolio(100.0), 100),
+            Err(Rejection::EntryCoolDown { until: 500 })
+        );
+
+        risk.closed(false, 1000);
+        risk.closed(true, 1000);
+        risk.closed(false, 1000);
+        assert_eq!(risk.check(&order(), &portfolio(100.0), 1500), Ok(()));
+
+        risk.closed(false, 2000);
+        assert_eq!(
+            risk.check(&order(), &portfolio(100.0), 2500),
+            Err(Rejection::CoolDown { until: 3000 })
+        );
+        assert_eq!(risk.check(&order(), &portfolio(100.0), 3001), Ok(()));
+    }
+
+    #[test]
+    fn losses() {
+        let mut risk = RiskManager::new()
+            .with_daily_loss_limit(0.05)
+            .with_max_drawdown(0.2);
+
+        risk.update(100.0, 0);
+        risk.update(94.0, 1000);
+        assert_eq!(
+            risk.check(&order(), &portfolio(94.0), 1000),
+            Err(Rejection::DailyLossLimit)
+        );
+
+        // A new day starts from the current value.
+        risk.update(94.0, DAY);
+        assert_eq!(risk.check(&order(), &portfolio(94.0), DAY), Ok(()));
+
+        risk.update(79.0, DAY + 1000);
+        risk.update(120.0, 2 * DAY);
+        assert_eq!(
+            risk.check(&order(), &portfolio(120.0), 2 * DAY),
+            Err(Rejection::MaxDrawdown)
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{
+    indicators::{Atr, Stdev},
+    risk::Portfolio,
+    Bar, ClosedPosition, Market, Number, Order, Trade,
+};
+use rust_decimal::prelude::*;
+use std::{collections::HashMap, fmt};
+
+/// Decides how much to invest in an order, shared by simulations and live trading.
+pub trait PositionSizer: fmt::Display + Send {
+    /// The value to invest in `order`, in the same unit as `portfolio`, or `None` if it can't be
+    /// sized yet.
+    fn size(&self, order: &Order, portfolio: &Portfolio) -> Option<Number>;
+    /// Receives every trade, for sizers that depend on the market.
+    fn trade(&mut self, _trade: &Trade) {}
+    /// Receives every closed position, for sizers that depend on past results.
+    fn closed(&mut self, _position: &ClosedPosition) {}
+}
+
+/// The value to invest in `order`, at most `available`. A size suggested by the order itself
+/// takes precedence over the sizer. Returns `None` if nothing should be invested.
+pub fn investment(
+    sizer: &dyn PositionSizer,
+    order: &Order,
+    portfolio: &Portfolio,
+    available: Number,
+) -> Option<Number> {
+    let size = match order.size {
+        Some(fraction) => portfolio.total_value * fraction.to_f64()?,
+        None => sizer.size(order, portfolio)?,
+    }
+    .min(available);
+
+    if size > 0.0 {
+        Some(size)
+    } else {
+        log::info!("Not investing in order {}, its size is {}.", order, size);
+        None
+    }
+}
+
+/// Invests the same value in every order.
+pub struct FixedQuote(pub Number);
+
+impl PositionSizer for FixedQuote {
+    fn size(&self, _order: &Order, _portfolio: &Portfolio) -> Option<Number> {
+        Some(self.0)
+    }
+}
+
+impl fmt::Display for FixedQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fixed {}", self.0)
+    }
+}
+
+/// Invests a fraction of the total value in every order, 0.1 is 10%.
+pub struct FixedFraction(pub Number);
+
+impl PositionSizer for FixedFraction {
+    fn size(&self, _order: &Order, portfolio: &Portfolio) -> Option<Number> {
+        Some(portfolio.total_value * self.0)
+    }
+}
+
+impl fmt::Display for FixedFraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}% of total value", self.0 * 100.0)
+    }
+}
+
+/// Risks a fraction of the total value on every order, so that hitting the stop loss loses
+/// exactly that fraction. Orders without a stop loss aren't sized.
+pub struct RiskPerTrade(pub Number);
+
+impl PositionSizer for RiskPerTrade {
+    fn size(&self, order: &Order, portfolio: &Portfolio) -> Option<Number> {
+        let price = order.price.to_f64()?;
+        let stop_loss = order.stop_loss?.to_f64()?;
+        if stop_loss >= price {
+            return None;
+        }
+
+        Some(portfolio.total_value * self.0 * price / (price - stop_loss))
+    }
+}
+
+impl fmt::Display for RiskPerTrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "risking {}% per trade", self.0 * 100.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Volatility {
+    /// Standard deviation of the returns from bar to bar.
+    Stdev,
+    /// Average true range relative to the latest price.
+    Atr,
+}
+
+struct Estimate {
+    /// Of the returns from close to close.
+    stdev: Stdev,
+    atr: Atr,
+    bar: Bar,
+    /// Close of the last completed bar.
+    close: Option<Number>,
+}
+
+impl Estimate {
+    fn volatility(&self, volatility: Volatility) -> Option<Number> {
+        let value = match volatility {
+            Volatility::Stdev if self.stdev.is_ready() => self.stdev.get(),
+            Volatility::Atr if self.atr.is_ready() => self.atr.get() / self.bar.close.to_f64()?,
+            _ => return None,
+        };
+
+        if value > 0.0 {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+/// Sizes orders so that every position moves the total value by about the same fraction per bar
+/// of `interval` milliseconds. Orders aren't sized until the volatility of their market has
+/// warmed up.
+pub struct VolatilityTarget {
+    target: Number,
+    volatility: Volatility,
+    period: Number,
+    interval: i64,
+    estimates: HashMap<Market, Estimate>,
+}
+
+impl VolatilityTarget {
+    /// `target` is the volatility of a position relative to the total value, 0.01 is 1%.
+    pub fn new(target: Number, volatility: Volatility, period: Number, interval: i64) -> Self {
+        Self {
+            target,
+            volatility,
+            period,
+            interval,
+            estimates: HashMap::new(),
+        }
+    }
+}
+
+impl PositionSizer for VolatilityTarget {
+    fn size(&self, order: &Order, portfolio: &Portfolio) -> Option<Number> {
+        let volatility = self
+            .estimates
+            .get(&order.market)?
+            .volatility(self.volatility)?;
+
+        Some(portfolio.total_value * self.target / volatility)
+    }
+
+    fn trade(&mut self, trade: &Trade) {
+        let (period, interval) = (self.period, self.interval);
+        let estimate = self
+            .estimates
+            .entry(trade.market.clone())
+            .or_insert_with(|| Estimate {
+                stdev: Stdev::new(period),
+                atr: Atr::new(period),
+                bar: Bar::new(trade, interval),
+                close: None,
+            });
+
+        if estimate.bar.timestamp == trade.timestamp / interval * interval {
+            estimate.bar.add(trade);
+            return;
+        }
+
+        let (high, low, close) = (
+            estimate.bar.high.to_f64().unwrap(),
+            estimate.bar.low.to_f64().unwrap(),
+            estimate.bar.close.to_f64().unwrap(),
+        );
+        estimate.atr.run(high, low, close);
+        if let Some(previous) = estimate.close {
+            estimate.stdev.run(close / previous - 1.0);
+        }
+        estimate.close = Some(close);
+        estimate.bar = Bar::new(trade, interval);
+    }
+}
+
+impl fmt::Display for VolatilityTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "targeting {}% volatility ({:?})",
+            self.target * 100.0,
+            self.volatility
+        )
+    }
+}
+
+/// Invests `fraction` of the Kelly criterion, computed from the positions closed so far. Until
+/// `min_trades` positions were closed, `prior` of the total value is invested instead.
+pub struct Kelly {
+    fraction: Number,
+    prior: Number,
+    min_trades: usize,
+    wins: Vec<Number>,
+    losses: Vec<Number>,
+}
+
+impl Kelly {
+    pub fn new(fraction: Number, prior: Number, min_trades: usize) -> Self {
+        Self {
+            fraction,
+            prior,
+            min_trades,
+            wins: Vec::new(),
+            losses: Vec::new(),
+        }
+    }
+
+    fn criterion(&self) -> Option<Number> {
+        if self.wins.len() + self.losses.len() < self.min_trades {
+            return None;
+        }
+        if self.losses.is_empty() {
+            return Some(1.0);
+        }
+        if self.wins.is_empty() {
+            return Some(0.0);
+        }
+
+        let mean = |returns: &[Number]| returns.iter().sum::<Number>() / returns.len() as Number;
+        let win_rate = self.wins.len() as Number / (self.wins.len() + self.losses.len()) as Number;
+        let ratio = mean(&self.wins) / mean(&self.losses);
+
+        Some((win_rate - (1.0 - win_rate) / ratio).max(0.0).min(1.0))
+    }
+}
+
+impl PositionSizer for Kelly {
+    fn size(&self, _order: &Order, portfolio: &Portfolio) -> Option<Number> {
+        let fraction = match self.criterion() {
+            Some(criterion) => criterion * self.fraction,
+            None => self.prior,
+        };
+
+        Some(portfolio.total_value * fraction)
+    }
+
+    fn closed(&mut self, position: &ClosedPosition) {
+        let change = (position.sell_price / position.buy_price - Decimal::one())
+            .to_f64()
+            .unwrap();
+
+        if position.is_profitable() {
+            self.wins.push(change);
+        } else {
+            self.losses.push(-change);
+        }
+    }
+}
+
+impl fmt::Display for Kelly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} Kelly", self.fraction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Side;
+
+    fn order(price: i64, stop_loss: Option<i64>) -> Order {
+        Order {
+            market: "BTCUSDT".to_owned(),
+            price: Decimal::new(price, 0),
+            take_profit: None,
+            stop_loss: stop_loss.map(|stop_loss| Decimal::new(stop_loss, 0)),
+            tranches: Vec::new(),
+            size: None,
+            side: Side::Buy,
+        }
+    }
+
+    fn portfolio() -> Portfolio {
+        Portfolio {
+            total_value: 1000.0,
+            cash: 1000.0,
+            open_positions: 0,
+            exposure: 0.0,
+        }
+    }
+
+    fn trade(price: i64, timestamp: i64) -> Trade {
+        Trade {
+            market: "BTCUSDT".to_owned(),
+            quantity: Decimal::new(1, 0),
+            price: Decimal::new(price, 0),
+            timestamp,
+            venue: None,
+        }
+    }
+
+    fn closed(buy_price: i64, sell_price: i64) -> ClosedPosition {
+        ClosedPosition {
+            market: "BTCUSDT".to_owned(),
+            buy_price: Decimal::new(buy_price, 0),
+            sell_price: Decimal::new(sell_price, 0),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn fixed() {
+        let order = order(100, None);
+        assert_eq!(FixedQuote(50.0).size(&order, &portfolio()), Some(50.0));
+        assert_eq!(FixedFraction(0.1).size(&order, &portfolio()), Some(100.0));
+
+        // The order's own size wins, but never exceeds what is available.
+        let suggested = Order {
+            size: Some(Decimal::new(5, 1)),
+            ..order
+        };
+        assert_eq!(
+            investment(&FixedQuote(50.0), &suggested, &portfolio(), 300.0),
+            Some(300.0)
+        );
+        assert_eq!(
+            investment(&FixedQuote(50.0), &suggested, &portfolio(), 0.0),
+            None
+        );
+    }
+
+    #[test]
+    fn risk_per_trade() {
+        let sizer = RiskPerTrade(0.01);
+        assert_eq!(sizer.size(&order(100, Some(95)), &portfolio()), Some(200.0));
+        assert_eq!(sizer.size(&order(100, None), &portfolio()), None);
+        assert_eq!(sizer.size(&order(100, Some(100)), &portfolio()), None);
+    }
+
+    #[test]
+    fn volatility_target() {
+        let mut sizer = VolatilityTarget::new(0.01, Volatility::Atr, 1.0, 1000);
+        assert_eq!(sizer.size(&order(100, None), &portfolio()), None);
+
+        sizer.trade(&trade(100, 0));
+        sizer.trade(&trade(95, 500));
+        sizer.trade(&trade(100, 1000));
+        // The first bar ranged from 95 to 100, 5% of the latest price.
+        assert_eq!(sizer.size(&order(100, None), &portfolio()), Some(200.0));
+
+        let mut sizer = VolatilityTarget::new(0.01, Volatility::Stdev, 2.0, 1000);
+        for (price, timestamp) in [(100, 0), (150, 1000), (110, 1500), (99, 2000), (50, 3000)] {
+            sizer.trade(&trade(price, timestamp));
+        }
+        // Closes of 100, 110 and 99 are returns of 10% and -10%, the trades within and after the
+        // bars don't count.
+        assert_eq!(
+            sizer
+                .size(&order(100, None), &portfolio())
+                .map(|size| size.round()),
+            Some(106.0)
+        );
+    }
+
+    #[test]
+    fn kelly() {
+        let mut sizer = Kelly::new(0.5, 0.1, 4);
+        assert_eq!(sizer.size(&order(100, None), &portfolio()), Some(100.0));
+
+        sizer.closed(&closed(100, 120));
+        sizer.closed(&closed(100, 120));
+        sizer.closed(&closed(100, 120));
+        sizer.closed(&closed(100, 90));
+        // Wins 75% of the time and wins twice what it loses: 0.75 - 0.25 / 2 = 0.625.
+        assert_eq!(sizer.size(&order(100, None), &portfolio()), Some(312.5));
+    }
+}
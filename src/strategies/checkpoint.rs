@@ -0,0 +1,82 @@
+use super::{first_order, MarketEvent, Order, Snapshot, Strategy, Trade};
+use crate::{Account, Intent};
+use std::{fmt, path::PathBuf};
+
+/// Periodically saves a snapshot of the wrapped strategy, `interval` is measured in milliseconds
+/// of event time.
+pub struct Checkpoint<S: Snapshot> {
+    strategy: S,
+    path: PathBuf,
+    interval: i64,
+    saved_at: Option<i64>,
+    /// Timestamp of the last event, saved with the snapshot.
+    timestamp: Option<i64>,
+}
+
+impl<S: Snapshot> Checkpoint<S> {
+    pub fn new<P: Into<PathBuf>>(strategy: S, path: P, interval: i64) -> Self {
+        Self {
+            strategy,
+            path: path.into(),
+            interval,
+            saved_at: None,
+            timestamp: None,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.strategy
+    }
+
+    fn save(&mut self, timestamp: i64) {
+        log::debug!("Saving strategy snapshot to {:?}.", self.path);
+
+        if let Err(err) = self.strategy.save(&self.path, timestamp) {
+            log::error!("Couldn't save strategy snapshot: {}", err);
+        }
+        self.saved_at = Some(timestamp);
+    }
+}
+
+impl<S: Snapshot> Strategy for Checkpoint<S> {
+    fn run(&mut self, trade: Trade) -> Option<Order> {
+        first_order(self.event(MarketEvent::Trade(trade), &Account::default()))
+    }
+
+    fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+        let timestamp = event.timestamp();
+        let intents = self.strategy.event(event, account);
+        self.timestamp = Some(timestamp);
+
+        match self.saved_at {
+            Some(saved_at) if saved_at + self.interval > timestamp => {}
+            // Don't write a snapshot on the very first event, start counting from there instead.
+            None => self.saved_at = Some(timestamp),
+            _ => self.save(timestamp),
+        }
+
+        intents
+    }
+
+    fn finish(&mut self) {
+        self.strategy.finish();
+        if let Some(timestamp) = self.timestamp {
+            self.save(timestamp);
+        }
+    }
+
+    fn is_ready(&self) -> bool {
+        self.strategy.is_ready()
+    }
+
+    #[cfg(feature = "plot")]
+    fn plot(&self) {
+        self.strategy.plot()
+    }
+}
+
+impl<S: Snapshot> fmt::Display for Checkpoint<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.strategy)
+    }
+}
@@ -4,16 +4,18 @@ use crate::{
     indicators::*,
     Number,
 };
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 struct Data {
     timestamp: i64,
     price: Number,
     val: Number,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Custom {
     val: Val,
     diff: Ema,
@@ -21,7 +23,6 @@ pub struct Custom {
     macd_long: Macd,
     
     was_undervalued: bool,
-    bought_at: i64,
 
     data: Vec<Data>,
     market: Option<String>,
@@ -36,7 +37,6 @@ impl Custom {
             macd_long: Macd::new(1000.0, 1300.0, 1.0),
             diff_stdev: Stdev::new(2000.0),
             was_undervalued: false,
-            bought_at: 0,
 
             data: Vec::new(),
             market: None,
@@ -51,10 +51,15 @@ impl Strategy for Custom {
         Trade {
             market,
             quantity,
-            price,
+            price: exact_price,
+            #[cfg(feature = "plot")]
             timestamp,
+            ..
         }: Trade,
     ) -> Option<Order> {
+        let quantity = quantity.to_f64().unwrap();
+        let price = exact_price.to_f64().unwrap();
+
         self.val.run(quantity, price);
         self.diff.run(price - self.val.get());
         self.diff_stdev.run(self.diff.get());
@@ -66,7 +71,7 @@ impl Strategy for Custom {
         let mean_reversal = !is_undervalued && self.was_undervalued;
         let worth_it = window > price * 0.01;
         let is_bullish = trend > 0.0;
-        let no_backoff = self.bought_at + 1000 * 60 * 60 * 12 < timestamp;
+        let is_ready = self.is_ready();
 
         #[cfg(feature = "plot")]
         {
@@ -92,18 +97,19 @@ impl Strategy for Custom {
         */
 
         let action = if
+            is_ready &&
             mean_reversal &&
             worth_it &&
             //has_momentum &&
-            is_bullish &&
-            no_backoff
+            is_bullish
         {
-            self.bought_at = timestamp;
             Some(Order {
                 market,
-                price,
-                take_profit: Some(price + window),
-                stop_loss: Some(price - window),
+                price: exact_price,
+                take_profit: Decimal::from_f64(price + window),
+                stop_loss: Decimal::from_f64(price - window),
+                tranches: Vec::new(),
+                size: None,
                 side: Side::Buy,
             })
         } else {
@@ -115,6 +121,13 @@ impl Strategy for Custom {
         action
     }
 
+    fn is_ready(&self) -> bool {
+        self.val.is_ready()
+            && self.diff.is_ready()
+            && self.diff_stdev.is_ready()
+            && self.macd_long.is_ready()
+    }
+
     #[cfg(feature = "plot")]
     fn plot(&self) {
         log::info!("Generating plot ...");
@@ -1,30 +1,89 @@
-use super::{Order, Strategy, Trade};
-use crate::Market;
+use super::{first_order, MarketEvent, Order, Strategy, Trade};
+use crate::{Account, Intent, Market};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 
+/// Runs a copy of the strategy per market and venue.
+#[derive(Serialize, Deserialize)]
 pub struct Duplicated<S: Strategy + Clone> {
     strategy: S,
-    strategies: HashMap<Market, S>,
+    /// The venue orders go to.
+    venue: Option<String>,
+    // Ordered, so snapshots of the same state are the same. Keyed by the venue of the trades,
+    // none for the venue orders go to.
+    strategies: BTreeMap<(Option<String>, Market), S>,
 }
 
 impl<S: Strategy + Clone> Duplicated<S> {
     pub fn new(strategy: S) -> Self {
         Self {
             strategy,
-            strategies: HashMap::new(),
+            venue: None,
+            strategies: BTreeMap::new(),
         }
     }
+
+    /// Orders go to `venue`, so its trades reach the same copy as the fills and closed positions
+    /// of the market. By default that's only trades without a venue.
+    pub fn with_venue(mut self, venue: &str) -> Self {
+        self.venue = Some(venue.to_owned());
+        self
+    }
+
+    /// Trades of other venues get copies of their own, without any say in orders.
+    fn key(&self, event: &MarketEvent) -> Option<(Option<String>, Market)> {
+        let venue = event
+            .venue()
+            .filter(|venue| Some(*venue) != self.venue.as_deref());
+        Some((venue.map(str::to_owned), event.market()?.clone()))
+    }
 }
 
 #[async_trait]
 impl<S: Strategy + Clone> Strategy for Duplicated<S> {
     fn run(&mut self, trade: Trade) -> Option<Order> {
-        self.strategies
-            .entry(trade.market.clone())
-            .or_insert(self.strategy.clone())
-            .run(trade)
+        first_order(self.event(MarketEvent::Trade(trade), &Account::default()))
+    }
+
+    fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+        match self.key(&event) {
+            Some(key) => {
+                let routed = key.0.is_none();
+                let strategy = self.strategies.entry(key).or_insert(self.strategy.clone());
+                let intents = strategy.event(event, account);
+                if routed {
+                    intents
+                } else {
+                    Vec::new()
+                }
+            }
+            // Events without a market, like timers, go to every market.
+            None => self
+                .strategies
+                .iter_mut()
+                .flat_map(|((venue, _), strategy)| {
+                    let intents = strategy.event(event.clone(), account);
+                    if venue.is_none() {
+                        intents
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn finish(&mut self) {
+        for strategy in self.strategies.values_mut() {
+            strategy.finish();
+        }
+    }
+
+    /// Not before any market has traded.
+    fn is_ready(&self) -> bool {
+        !self.strategies.is_empty() && self.strategies.values().all(Strategy::is_ready)
     }
 
     #[cfg(feature = "plot")]
@@ -40,3 +99,84 @@ impl<S: Strategy + Clone> fmt::Display for Duplicated<S> {
         write!(f, "{}", self.strategy)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::{Fill, Side};
+    use rust_decimal::Decimal;
+
+    /// Records the events it receives and cancels something for every one of them.
+    #[derive(Clone, Default)]
+    struct Record(Vec<String>);
+
+    impl Strategy for Record {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            None
+        }
+
+        fn event(&mut self, event: MarketEvent, _account: &Account) -> Vec<Intent> {
+            self.0.push(match &event {
+                MarketEvent::Trade(trade) => format!("trade at {}", trade.price),
+                MarketEvent::OrderFilled(fill) => format!("filled at {}", fill.price),
+                event => format!("{}", event.timestamp()),
+            });
+            vec![Intent::Cancel("BTCUSDT".to_owned())]
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Record {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "record")
+        }
+    }
+
+    fn trade(venue: Option<&str>, price: i64) -> MarketEvent {
+        MarketEvent::Trade(Trade {
+            market: "BTCUSDT".to_owned(),
+            quantity: Decimal::new(1, 0),
+            price: Decimal::new(price, 0),
+            timestamp: 1000,
+            venue: venue.map(str::to_owned),
+        })
+    }
+
+    #[test]
+    fn venues() {
+        let mut duplicated = Duplicated::new(Record::default()).with_venue("Binance");
+        let account = Account::default();
+
+        let intents = [
+            duplicated.event(trade(Some("Binance"), 100), &account),
+            duplicated.event(trade(Some("Coinbase"), 200), &account),
+            duplicated.event(trade(None, 101), &account),
+            duplicated.event(
+                MarketEvent::OrderFilled(Fill {
+                    market: "BTCUSDT".to_owned(),
+                    side: Side::Buy,
+                    price: Decimal::new(100, 0),
+                    timestamp: 2000,
+                }),
+                &account,
+            ),
+            duplicated.event(MarketEvent::Timer(3000), &account),
+        ];
+
+        // Only the copy of the venue orders go to has a say.
+        assert_eq!(
+            intents.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![1, 0, 1, 1, 1]
+        );
+        assert_eq!(
+            duplicated.strategies[&(None, "BTCUSDT".to_owned())].0,
+            vec!["trade at 100", "trade at 101", "filled at 100", "3000"]
+        );
+        assert_eq!(
+            duplicated.strategies[&(Some("Coinbase".to_owned()), "BTCUSDT".to_owned())].0,
+            vec!["trade at 200", "3000"]
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use super::Strategy;
 use crate::exchanges::{Order, Side, Trade};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Serialize, Deserialize)]
 pub struct Hold {}
 
 impl Hold {
@@ -18,6 +19,8 @@ impl Strategy for Hold {
             price,
             take_profit: None,
             stop_loss: None,
+            tranches: Vec::new(),
+            size: None,
             side: Side::Buy,
         })
     }
@@ -1,12 +1,15 @@
-use super::{Order, Strategy, Trade};
+use super::{first_order, MarketEvent, Order, Strategy, Trade};
+use crate::{Account, Bar, Intent};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Interval<S: Strategy + Clone> {
     strategy: S,
     interval: i64,
     trade: Option<Trade>,
+    bar: Option<Bar>,
 }
 
 impl<S: Strategy + Clone> Interval<S> {
@@ -15,6 +18,19 @@ impl<S: Strategy + Clone> Interval<S> {
             strategy,
             interval,
             trade: None,
+            bar: None,
+        }
+    }
+
+    /// Passes on the bar and the aggregated trade of the pending interval, if there is one.
+    fn flush(&mut self, account: &Account) -> Vec<Intent> {
+        match (self.bar.take(), self.trade.take()) {
+            (Some(bar), Some(trade)) => {
+                let mut intents = self.strategy.event(MarketEvent::Bar(bar), account);
+                intents.extend(self.strategy.event(MarketEvent::Trade(trade), account));
+                intents
+            }
+            _ => Vec::new(),
         }
     }
 }
@@ -22,23 +38,63 @@ impl<S: Strategy + Clone> Interval<S> {
 #[async_trait]
 impl<S: Strategy + Clone> Strategy for Interval<S> {
     fn run(&mut self, trade: Trade) -> Option<Order> {
-        if let Some(old) = &mut self.trade {
+        first_order(self.event(MarketEvent::Trade(trade), &Account::default()))
+    }
+
+    /// Trades are aggregated per interval, so they should be of one market on one venue, like
+    /// `Duplicated` passes them. When an interval is over, the strategy first receives its bar and
+    /// then the aggregated trade. Timers after the interval close it as well, so bars don't wait
+    /// for the next trade. All other events are passed on as they are.
+    fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+        let trade = match event {
+            MarketEvent::Trade(trade) => trade,
+            MarketEvent::Timer(timestamp) => {
+                let mut intents = match &self.trade {
+                    Some(old) if old.timestamp / self.interval < timestamp / self.interval => {
+                        self.flush(account)
+                    }
+                    _ => Vec::new(),
+                };
+                intents.extend(self.strategy.event(event, account));
+                return intents;
+            }
+            event => return self.strategy.event(event, account),
+        };
+
+        if let (Some(old), Some(bar)) = (&mut self.trade, &mut self.bar) {
             if old.timestamp / self.interval != trade.timestamp / self.interval {
                 let output = old.clone();
+                let bar_output = bar.clone();
+                *bar = Bar::new(&trade, self.interval);
                 *old = trade;
-                self.strategy.run(output)
+
+                let mut intents = self.strategy.event(MarketEvent::Bar(bar_output), account);
+                intents.extend(self.strategy.event(MarketEvent::Trade(output), account));
+                intents
             } else {
+                bar.add(&trade);
                 old.timestamp = trade.timestamp;
                 old.price = trade.price;
                 old.quantity += trade.quantity;
-                None
+                Vec::new()
             }
         } else {
+            self.bar = Some(Bar::new(&trade, self.interval));
             self.trade = Some(trade);
-            None
+            Vec::new()
         }
     }
 
+    /// The last interval is passed on before the strategy finishes, even though it isn't over.
+    fn finish(&mut self) {
+        self.flush(&Account::default());
+        self.strategy.finish()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.strategy.is_ready()
+    }
+
     #[cfg(feature = "plot")]
     fn plot(&self) {
         self.strategy.plot()
@@ -50,3 +106,97 @@ impl<S: Strategy + Clone> fmt::Display for Interval<S> {
         write!(f, "{}", self.strategy)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[derive(Clone, Default)]
+    struct Record(Vec<MarketEvent>);
+
+    impl Strategy for Record {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            None
+        }
+
+        fn event(&mut self, event: MarketEvent, _account: &Account) -> Vec<Intent> {
+            self.0.push(event);
+            Vec::new()
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Record {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "record")
+        }
+    }
+
+    fn trade(price: i64, timestamp: i64) -> Trade {
+        Trade {
+            market: "BTCUSDT".to_owned(),
+            quantity: Decimal::new(1, 0),
+            price: Decimal::new(price, 0),
+            timestamp,
+            venue: None,
+        }
+    }
+
+    #[test]
+    fn bars() {
+        let mut interval = Interval::new(Record::default(), 1000);
+        for (price, timestamp) in &[(10, 0), (12, 300), (9, 600), (11, 900), (20, 1000)] {
+            interval.run(trade(*price, *timestamp));
+        }
+        interval.event(MarketEvent::Timer(1100), &Account::default());
+
+        let events = &interval.strategy.0;
+        assert_eq!(events.len(), 3);
+        match &events[0] {
+            MarketEvent::Bar(bar) => {
+                assert_eq!(bar.open, Decimal::new(10, 0));
+                assert_eq!(bar.high, Decimal::new(12, 0));
+                assert_eq!(bar.low, Decimal::new(9, 0));
+                assert_eq!(bar.close, Decimal::new(11, 0));
+                assert_eq!(bar.volume, Decimal::new(4, 0));
+                assert_eq!(bar.timestamp, 0);
+            }
+            event => panic!("expected a bar, got {:?}", event),
+        }
+        match &events[1] {
+            MarketEvent::Trade(trade) => {
+                assert_eq!(trade.quantity, Decimal::new(4, 0));
+                assert_eq!(trade.timestamp, 900);
+            }
+            event => panic!("expected a trade, got {:?}", event),
+        }
+        assert_eq!(events[2].timestamp(), 1100);
+
+        // A timer after the interval closes it without waiting for the next trade.
+        interval.event(MarketEvent::Timer(2000), &Account::default());
+        let events = &interval.strategy.0;
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[3].timestamp(), 1000);
+        assert_eq!(events[4].timestamp(), 1000);
+        assert_eq!(events[5].timestamp(), 2000);
+    }
+
+    #[test]
+    fn finish() {
+        let mut interval = Interval::new(Record::default(), 1000);
+        interval.run(trade(10, 0));
+        interval.run(trade(12, 300));
+        interval.finish();
+
+        let events = &interval.strategy.0;
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            MarketEvent::Bar(bar) => assert_eq!(bar.close, Decimal::new(12, 0)),
+            event => panic!("expected a bar, got {:?}", event),
+        }
+        assert_eq!(events[1].timestamp(), 300);
+    }
+}
@@ -1,3 +1,4 @@
+mod checkpoint;
 mod custom;
 mod duplicated;
 mod hold;
@@ -5,7 +6,9 @@ mod interval;
 mod multi;
 mod random;
 mod simulated;
+mod snapshot;
 
+pub use checkpoint::Checkpoint;
 pub use custom::Custom;
 pub use duplicated::Duplicated;
 pub use hold::Hold;
@@ -13,12 +16,37 @@ pub use interval::Interval;
 pub use multi::Multi;
 pub use random::Random;
 pub use simulated::Simulated;
+pub use snapshot::Snapshot;
 
-use crate::{Order, Trade};
+use crate::{Account, Intent, MarketEvent, Order, Trade};
 use std::fmt::Display;
 
 pub trait Strategy: Display + Send + 'static {
     fn run(&mut self, trade: Trade) -> Option<Order>;
+    /// Receives every market event together with the current positions and open orders, and
+    /// returns what should be done. By default, trades are passed on to `run` and everything else
+    /// is ignored, so strategies that only look at trades don't need to care.
+    fn event(&mut self, event: MarketEvent, _account: &Account) -> Vec<Intent> {
+        match event {
+            MarketEvent::Trade(trade) => self.run(trade).map(Intent::Place).into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// Called once after the last trade, so buffering strategies can process what is left.
+    fn finish(&mut self) {}
+    /// Whether all indicators the strategy depends on have warmed up. Strategies must not emit
+    /// orders before that.
+    fn is_ready(&self) -> bool {
+        true
+    }
     #[cfg(feature = "plot")]
     fn plot(&self);
 }
+
+/// The first order placed by `intents`, for wrappers that implement `run` through `event`.
+fn first_order(intents: Vec<Intent>) -> Option<Order> {
+    intents.into_iter().find_map(|intent| match intent {
+        Intent::Place(order) => Some(order),
+        _ => None,
+    })
+}
@@ -1,15 +1,21 @@
-use super::{Order, Strategy, Trade};
+use super::{MarketEvent, Order, Strategy, Trade};
+use crate::{Account, Intent};
 use async_trait::async_trait;
+use rayon::prelude::*;
 use std::fmt;
 
 pub struct Multi {
     strategies: Vec<Box<dyn Strategy>>,
+    batch: Vec<(MarketEvent, Account)>,
+    batch_size: usize,
 }
 
 impl Multi {
     pub fn new() -> Self {
         Self {
             strategies: Vec::new(),
+            batch: Vec::new(),
+            batch_size: 1,
         }
     }
 
@@ -17,16 +23,63 @@ impl Multi {
         self.strategies.push(Box::new(strategy));
         self
     }
+
+    /// Buffers events and runs each batch of `batch_size` events on all strategies in parallel.
+    /// Every strategy still sees the events in order, so the results match sequential execution.
+    pub fn parallel(mut self, batch_size: usize) -> Self {
+        debug_assert!(batch_size >= 1);
+
+        self.batch_size = batch_size;
+        self.batch.reserve(batch_size);
+        self
+    }
+
+    fn flush(&mut self) {
+        let batch = &self.batch;
+
+        self.strategies.par_iter_mut().for_each(|strategy| {
+            for (event, account) in batch {
+                strategy.event(event.clone(), account);
+            }
+        });
+
+        self.batch.clear();
+    }
 }
 
 #[async_trait]
 impl Strategy for Multi {
     fn run(&mut self, trade: Trade) -> Option<Order> {
+        self.event(MarketEvent::Trade(trade), &Account::default());
+
+        None
+    }
+
+    fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+        if self.batch_size > 1 {
+            self.batch.push((event, account.clone()));
+            if self.batch.len() >= self.batch_size {
+                self.flush();
+            }
+        } else {
+            for strategy in &mut self.strategies {
+                strategy.event(event.clone(), account);
+            }
+        }
+
+        Vec::new()
+    }
+
+    fn finish(&mut self) {
+        self.flush();
+
         for strategy in &mut self.strategies {
-            strategy.run(trade.clone());
+            strategy.finish();
         }
+    }
 
-        None
+    fn is_ready(&self) -> bool {
+        self.strategies.iter().all(|strategy| strategy.is_ready())
     }
 
     #[cfg(feature = "plot")]
@@ -46,3 +99,60 @@ impl fmt::Display for Multi {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        strategies::{Custom, Duplicated, Hold, Interval, Simulated},
+        Number,
+    };
+    use rust_decimal::prelude::*;
+
+    fn multi() -> Multi {
+        Multi::new()
+            .with(Simulated::new(Hold::new(), 0.001, 3))
+            .with(Simulated::new(
+                Duplicated::new(Interval::new(Custom::new(), 1000 * 60)),
+                0.001,
+                2,
+            ))
+            .with(Simulated::new(Duplicated::new(Custom::new()), 0.001, 1))
+    }
+
+    fn trades() -> impl Iterator<Item = Trade> {
+        (0..20000).flat_map(|i| {
+            ["BTCUSDT", "ETHUSDT", "ADAUSDT"]
+                .iter()
+                .enumerate()
+                .map(move |(j, market)| Trade {
+                    market: market.to_string(),
+                    quantity: Decimal::from((i * 7 + j * 3) % 11) - Decimal::new(5, 0),
+                    price: Decimal::from_f64(
+                        100.0
+                            + 10.0 * ((i + j * 100) as Number / 300.0).sin()
+                            + ((i * 13 + j) % 17) as Number * 0.1,
+                    )
+                    .unwrap()
+                    .round_dp(4),
+                    timestamp: i as i64 * 1000 * 20,
+                    venue: None,
+                })
+        })
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let mut sequential = multi();
+        let mut parallel = multi().parallel(1000);
+
+        for trade in trades() {
+            sequential.run(trade.clone());
+            parallel.run(trade);
+        }
+        sequential.finish();
+        parallel.finish();
+
+        assert_eq!(sequential.to_string(), parallel.to_string());
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     exchanges::{Order, Side, Trade},
     indicators::*,
 };
+use rust_decimal::prelude::*;
 use std::fmt;
 
 #[derive(Clone)]
@@ -20,14 +21,16 @@ impl Random {
 
 impl Strategy for Random {
     fn run(&mut self, Trade { market, price, .. }: Trade) -> Option<Order> {
-        self.stdev.run(price);
+        self.stdev.run(price.to_f64().unwrap());
 
         if rand::random::<f32>() < 1f32 {
             Some(Order {
                 market,
                 price,
-                take_profit: Some(price * 1.01),
-                stop_loss: Some(price * 0.99),
+                take_profit: Some(price * Decimal::new(101, 2)),
+                stop_loss: Some(price * Decimal::new(99, 2)),
+                tranches: Vec::new(),
+                size: None,
                 side: Side::Buy,
             })
         } else {
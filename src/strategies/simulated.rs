@@ -1,55 +1,413 @@
-use super::{Order, Strategy, Trade};
-use crate::Number;
+use super::{first_order, MarketEvent, Order, Strategy, Trade};
+use crate::{
+    risk::{Portfolio, RiskManager},
+    sizing::{self, FixedQuote, PositionSizer},
+    Account, ClosedPosition, Entry, Fees, Fill, Intent, Liquidity, Market, Number, OpenPosition,
+    Side, Stops,
+};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use std::fmt;
+use rust_decimal::prelude::*;
+use std::{collections::HashMap, fmt};
 
 fn format_timestamp(timestamp: i64) -> String {
     let date_time = NaiveDateTime::from_timestamp(timestamp / 1000, 0);
     format!("{}", date_time.format("%c"))
 }
 
+#[derive(Clone)]
 struct OrderHistory {
     order: Order,
+    /// Fraction of the original position.
+    quantity: Number,
+    /// Fraction of the starting capital the original position was bought with.
+    size: Number,
+    stops: Stops,
     buy_price: Number,
     buy_time: i64,
     sell_price: Number,
     sell_time: i64,
+    /// Fee rates paid on buying and selling. Open positions are assumed to be sold at market.
+    entry_fee: Number,
+    exit_fee: Number,
+}
+
+impl OrderHistory {
+    /// The change in value of the position after fees, 0.01 is 1%.
+    fn change(&self) -> Number {
+        self.sell_price / self.buy_price * (1.0 - self.exit_fee) - 1.0 - self.entry_fee
+    }
+
+    /// The fees paid, as a fraction of the starting capital.
+    fn fees(&self) -> Number {
+        (self.entry_fee + self.sell_price / self.buy_price * self.exit_fee)
+            * self.quantity
+            * self.size
+    }
+}
+
+/// A limit order waiting to be filled.
+struct Pending {
+    order: Order,
+    placed: i64,
 }
 
 pub struct Simulated<S> {
     strategy: S,
+    /// The venue orders go to.
+    venue: Option<String>,
+    entry: Entry,
+    pending: Vec<Pending>,
     open: Vec<OrderHistory>,
     closed: Vec<OrderHistory>,
-    concurrency: usize,
-    fee: f32,
-    consecutive_losses: u8,
-    wait_until: i64,
+    /// The return of all closed positions, as a fraction of the starting capital.
+    realized: Number,
+    fees: Fees,
+    risk: RiskManager,
+    sizer: Box<dyn PositionSizer>,
+    /// The price a market buy would be filled at, the last trade or best ask, whichever is newer.
+    prices: HashMap<Market, Number>,
 }
 
 impl<S: Strategy> Simulated<S> {
-    pub fn new(strategy: S, fee: f32, concurrency: usize) -> Self {
+    pub fn new(strategy: S, fee: Number, concurrency: usize) -> Self {
         Self {
             strategy,
+            venue: None,
+            entry: Entry::Market,
+            pending: Vec::new(),
             open: Vec::new(),
             closed: Vec::new(),
-            concurrency,
-            fee,
-            consecutive_losses: 0,
-            wait_until: 0,
+            realized: 0.0,
+            fees: Fees::new(Decimal::from_f64(fee).unwrap(), Decimal::from_f64(fee).unwrap()),
+            risk: RiskManager::new()
+                .with_max_open_positions(concurrency)
+                .with_cool_down(2, 1000 * 60 * 60 * 24),
+            sizer: Box::new(FixedQuote(1.0 / concurrency as Number)),
+            prices: HashMap::new(),
         }
     }
-}
 
-#[async_trait]
-impl<S: Strategy> Strategy for Simulated<S> {
-    fn run(&mut self, trade: Trade) -> Option<Order> {
-        let price = trade.price;
+    /// Orders go to `venue`, so only its trades fill them and move positions. Trades of other
+    /// venues are only passed to the strategy. By default that's all trades without a venue.
+    pub fn with_venue(mut self, venue: &str) -> Self {
+        self.venue = Some(venue.to_owned());
+        self
+    }
+
+    /// Simulates entries of the given kind. Limit orders are filled completely by the first trade
+    /// at or below their price, and only when they are filled do they count against concurrency.
+    pub fn with_entry(mut self, entry: Entry) -> Self {
+        self.entry = entry;
+        self
+    }
+
+    /// Charges makers and takers different fees instead of the flat `fee`.
+    pub fn with_fees(mut self, fees: Fees) -> Self {
+        self.fees = fees;
+        self
+    }
+
+    fn fee(&self, liquidity: Liquidity) -> Number {
+        self.fees.rate(liquidity).to_f64().unwrap()
+    }
+
+    /// Replaces the default risk rules, which allow `concurrency` open positions and pause for a
+    /// day after two losses in a row.
+    pub fn with_risk(mut self, risk: RiskManager) -> Self {
+        self.risk = risk;
+        self
+    }
+
+    /// Replaces the default sizing, which invests 1/`concurrency` of the starting capital in every
+    /// position. Values are fractions of the starting capital.
+    pub fn with_sizer<P: PositionSizer + 'static>(mut self, sizer: P) -> Self {
+        self.sizer = Box::new(sizer);
+        self
+    }
+
+    /// The return of a position, as a fraction of the starting capital.
+    fn profit(&self, history: &OrderHistory) -> Number {
+        history.change() * history.quantity * history.size
+    }
+
+    /// The return of all positions so far, as a fraction of the starting capital.
+    fn total_profit(&self) -> Number {
+        self.realized
+            + self
+                .open
+                .iter()
+                .map(|history| self.profit(history))
+                .sum::<Number>()
+    }
+
+    /// Keeps a closed position, or a closed part of one.
+    fn record(&mut self, history: OrderHistory) {
+        self.realized += self.profit(&history);
+        self.closed.push(history);
+    }
+
+    /// The simulated account as a fraction of the starting capital.
+    fn portfolio(&self, market: &Market) -> Portfolio {
+        let invested = |history: &OrderHistory| {
+            history.sell_price / history.buy_price * history.quantity * history.size
+        };
+
+        let total_value = 1.0 + self.total_profit();
+
+        Portfolio {
+            total_value,
+            cash: total_value - self.open.iter().map(invested).sum::<Number>(),
+            open_positions: self.open.len(),
+            exposure: self
+                .open
+                .iter()
+                .filter(|history| history.order.market == *market)
+                .map(invested)
+                .sum(),
+        }
+    }
+
+    fn account(&self) -> Account {
+        Account {
+            positions: self
+                .open
+                .iter()
+                .map(|history| OpenPosition {
+                    market: history.order.market.clone(),
+                    quantity: Decimal::from_f64(history.quantity).unwrap(),
+                    entry_price: Decimal::from_f64(history.buy_price).unwrap(),
+                    take_profit: history.order.take_profit,
+                    stop_loss: history.order.stop_loss,
+                    timestamp: history.buy_time,
+                })
+                .collect(),
+            orders: self
+                .pending
+                .iter()
+                .map(|Pending { order, .. }| order.clone())
+                .collect(),
+        }
+    }
+
+    /// Passes an event to the strategy and executes everything it asks for.
+    fn send(&mut self, event: MarketEvent) {
+        let timestamp = event.timestamp();
+        let account = self.account();
+        let intents = self.strategy.event(event, &account);
+
+        for intent in intents {
+            match intent {
+                Intent::Place(order) => self.place(order, timestamp),
+                Intent::Cancel(market) => self
+                    .pending
+                    .retain(|Pending { order, .. }| order.market != market),
+                Intent::AmendStop { market, stop_loss } => {
+                    for history in self.open.iter_mut().filter(|h| h.order.market == market) {
+                        history.order.stop_loss = Some(stop_loss);
+                    }
+                }
+                Intent::TrailStop { market, trail } => {
+                    for history in self.open.iter_mut().filter(|h| h.order.market == market) {
+                        history.stops.trail(trail);
+                    }
+                }
+                Intent::BreakEven { market, after } => {
+                    for history in self.open.iter_mut().filter(|h| h.order.market == market) {
+                        history.stops.break_even(after);
+                    }
+                }
+                Intent::Close { market, fraction } => {
+                    self.close(&market, fraction.to_f64().unwrap(), timestamp)
+                }
+            }
+        }
+    }
+
+    fn place(&mut self, order: Order, timestamp: i64) {
+        // No entries before the strategy has warmed up.
+        if !self.strategy.is_ready() {
+            return;
+        }
+        let price = match self.prices.get(&order.market) {
+            Some(&price) => price,
+            None => return,
+        };
+        // Limit orders at or above the current price would be filled immediately.
+        let crosses = order.price.to_f64().unwrap() >= price;
+
+        match self.entry {
+            Entry::Market => self.open(order, price, timestamp, Liquidity::Taker),
+            Entry::Limit { .. } | Entry::ImmediateOrCancel if crosses => {
+                self.open(order, price, timestamp, Liquidity::Taker)
+            }
+            // Nothing could be filled immediately, so everything is cancelled.
+            Entry::ImmediateOrCancel => {}
+            // Would take liquidity, so the exchange rejects it.
+            Entry::PostOnly { .. } if crosses => {}
+            Entry::Limit { .. } | Entry::PostOnly { .. } => self.pending.push(Pending {
+                order,
+                placed: timestamp,
+            }),
+        }
+    }
+
+    /// Fills the pending orders `trade` reached and cancels the ones that timed out.
+    fn fill(&mut self, trade: &Trade) {
+        let timeout = match self.entry {
+            Entry::Limit { timeout } | Entry::PostOnly { timeout } => timeout,
+            _ => return,
+        };
+
+        let (filled, pending): (Vec<Pending>, Vec<Pending>) = self
+            .pending
+            .drain(..)
+            .filter(|Pending { placed, .. }| trade.timestamp - placed < timeout)
+            .partition(|Pending { order, .. }| {
+                order.market == trade.market && trade.price <= order.price
+            });
+
+        self.pending = pending;
+        for Pending { order, .. } in filled {
+            let price = order.price.to_f64().unwrap();
+            self.open(order, price, trade.timestamp, Liquidity::Maker);
+        }
+    }
+
+    fn open(&mut self, order: Order, price: Number, timestamp: i64, liquidity: Liquidity) {
+        let already_open = self
+            .open
+            .iter()
+            .any(|OrderHistory { order: open, .. }| open.market == order.market);
+
+        if already_open {
+            return;
+        }
+
+        let portfolio = self.portfolio(&order.market);
+        if self.risk.check(&order, &portfolio, timestamp).is_err() {
+            return;
+        }
+
+        let available = self.risk.available(&portfolio);
+        if let Some(size) = sizing::investment(&*self.sizer, &order, &portfolio, available) {
+            let market = order.market.clone();
+            self.risk.opened(&market, timestamp);
+            self.open.push(OrderHistory {
+                order,
+                quantity: 1.0,
+                size,
+                stops: Stops::new(Decimal::from_f64(price).unwrap()),
+                buy_price: price,
+                buy_time: timestamp,
+                sell_price: price,
+                sell_time: timestamp,
+                entry_fee: self.fee(liquidity),
+                exit_fee: self.fee(Liquidity::Taker),
+            });
+
+            self.send(MarketEvent::OrderFilled(Fill {
+                market,
+                side: Side::Buy,
+                price: Decimal::from_f64(price).unwrap(),
+                timestamp,
+            }));
+        }
+    }
+
+    fn close(&mut self, market: &Market, fraction: Number, timestamp: i64) {
+        let price = match self.prices.get(market) {
+            Some(&price) => price,
+            None => return,
+        };
+        let index = match self.open.iter().position(|h| h.order.market == *market) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let history = &mut self.open[index];
+        history.sell_price = price;
+        history.sell_time = timestamp;
+
+        if fraction >= 1.0 {
+            let history = self.open.remove(index);
+            self.record_close(history, timestamp);
+        } else if fraction > 0.0 {
+            let mut part = history.clone();
+            part.quantity *= fraction;
+            history.quantity -= part.quantity;
+            self.record(part);
+        }
+    }
+
+    fn record_close(&mut self, history: OrderHistory, timestamp: i64) {
+        self.risk
+            .closed(history.buy_price <= history.sell_price, timestamp);
+
+        let position = ClosedPosition {
+            market: history.order.market.clone(),
+            buy_price: Decimal::from_f64(history.buy_price).unwrap(),
+            sell_price: Decimal::from_f64(history.sell_price).unwrap(),
+            timestamp,
+        };
+        self.sizer.closed(&position);
+        self.record(history);
+        self.send(MarketEvent::PositionClosed(position));
+    }
+
+    /// Sells the take profit tranches `price` reached.
+    fn sell_tranches(&mut self, market: &Market, price: Decimal) {
+        let maker = self.fee(Liquidity::Maker);
+        let mut parts = Vec::new();
+        for history in self.open.iter_mut().filter(|h| h.order.market == *market) {
+            while history
+                .order
+                .tranches
+                .first()
+                .map_or(false, |tranche| price >= tranche.price)
+            {
+                let tranche = history.order.tranches.remove(0);
+                let fraction = tranche.fraction.to_f64().unwrap();
+
+                if fraction >= history.quantity - Number::EPSILON {
+                    // Selling the rest of the position is just a take profit.
+                    history.order.take_profit = Some(tranche.price);
+                    break;
+                }
+
+                let mut part = history.clone();
+                part.quantity = fraction;
+                part.sell_price = tranche.price.to_f64().unwrap();
+                part.exit_fee = maker;
+                history.quantity -= fraction;
+                if let Some(stop_loss) = tranche.stop_loss {
+                    history.order.stop_loss = Some(stop_loss);
+                }
+                parts.push(part);
+            }
+        }
+        for part in parts {
+            self.record(part);
+        }
+    }
+
+    fn trade(&mut self, trade: Trade) {
+        if trade.venue.is_some() && trade.venue != self.venue {
+            self.send(MarketEvent::Trade(trade));
+            return;
+        }
+
+        let exact_price = trade.price;
+        let price = exact_price.to_f64().unwrap();
         let market = trade.market.clone();
         let timestamp = trade.timestamp;
-        let mut already_open = false;
+        self.prices.insert(market.clone(), price);
+        self.sizer.trade(&trade);
+        self.fill(&trade);
 
         for OrderHistory {
+            order,
+            stops,
             sell_price,
             sell_time,
             ..
@@ -60,51 +418,74 @@ impl<S: Strategy> Strategy for Simulated<S> {
         {
             *sell_price = price;
             *sell_time = trade.timestamp;
-            already_open = true;
-        }
-
-        if let Some(order) = self.strategy.run(trade) {
-            assert_eq!(order.market, market);
-            // TODO: Check if price is in range.
 
-            if self.open.len() < self.concurrency && !already_open && self.wait_until < timestamp {
-                self.open.push(OrderHistory {
-                    order,
-                    buy_price: price,
-                    buy_time: timestamp,
-                    sell_price: price,
-                    sell_time: timestamp,
-                });
+            // Stops are moved tick by tick, before checking whether they were hit.
+            if let Some(stop_loss) = stops.update(&trade, order.stop_loss) {
+                order.stop_loss = Some(stop_loss);
             }
         }
 
-        let (mut closed, mut open): (Vec<OrderHistory>, Vec<OrderHistory>) =
+        self.risk
+            .update(self.portfolio(&market).total_value, timestamp);
+        self.send(MarketEvent::Trade(trade));
+        self.sell_tranches(&market, exact_price);
+
+        let (closed, open): (Vec<OrderHistory>, Vec<OrderHistory>) =
             self.open.drain(..).partition(|OrderHistory { order, .. }| {
                 order.market == market
                     && (order
                         .take_profit
-                        .map_or(false, |take_profit| price >= take_profit)
+                        .map_or(false, |take_profit| exact_price >= take_profit)
                         || order
                             .stop_loss
-                            .map_or(false, |stop_loss| price <= stop_loss))
+                            .map_or(false, |stop_loss| exact_price <= stop_loss))
             });
 
-        for order in &closed {
-            if order.buy_price > order.sell_price {
-                self.consecutive_losses += 1;
-                if self.consecutive_losses >= 2 {
-                    self.consecutive_losses = 0;
-                    self.wait_until = timestamp + 1000 * 60 * 60 * 24;
+        self.open = open;
+        for mut history in closed {
+            // Take profits rest in the book, stop losses cross it once triggered.
+            if history
+                .order
+                .take_profit
+                .map_or(false, |take_profit| exact_price >= take_profit)
+            {
+                history.exit_fee = self.fee(Liquidity::Maker);
+            }
+            self.record_close(history, timestamp);
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Strategy> Strategy for Simulated<S> {
+    fn run(&mut self, trade: Trade) -> Option<Order> {
+        first_order(self.event(MarketEvent::Trade(trade), &Account::default()))
+    }
+
+    /// Executes the strategy's intents against its own simulated account, nothing is passed on.
+    fn event(&mut self, event: MarketEvent, _account: &Account) -> Vec<Intent> {
+        match event {
+            MarketEvent::Trade(trade) => self.trade(trade),
+            MarketEvent::BookUpdate(update) => {
+                // Orders triggered by the book are filled at the best ask.
+                if let Some(level) = update.best_ask() {
+                    self.prices
+                        .insert(update.market.clone(), level.price.to_f64().unwrap());
                 }
-            } else {
-                self.consecutive_losses = 0;
+                self.send(MarketEvent::BookUpdate(update));
             }
+            event => self.send(event),
         }
 
-        self.open.append(&mut open);
-        self.closed.append(&mut closed);
+        Vec::new()
+    }
 
-        None
+    fn finish(&mut self) {
+        self.strategy.finish()
+    }
+
+    fn is_ready(&self) -> bool {
+        self.strategy.is_ready()
     }
 
     #[cfg(feature = "plot")]
@@ -117,60 +498,33 @@ impl<S: Strategy> fmt::Display for Simulated<S> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(
             f,
-            "type: simulation\nfees: {}%\nfraction of investment per trade: 1/{}\nstrategy: {}",
-            self.fee * 100.0,
-            self.concurrency,
+            "type: simulation\nfees: {}\nsizing: {}\nstrategy: {}",
+            self.fees,
+            self.sizer,
             self.strategy
         )?;
 
-        for OrderHistory {
-            order,
-            buy_price,
-            buy_time,
-            sell_price,
-            sell_time,
-        } in &self.closed
-        {
+        for history in &self.closed {
             writeln!(
                 f,
                 "{}:\t {:+.2}%\t (CLOSED)\t {}\t - {}",
-                order.market,
-                (sell_price / buy_price * (1.0 - 2.0 * self.fee) - 1.0) * 100.0,
-                format_timestamp(*buy_time),
-                format_timestamp(*sell_time),
+                history.order.market,
+                history.change() * 100.0,
+                format_timestamp(history.buy_time),
+                format_timestamp(history.sell_time),
             )?;
         }
-        for OrderHistory {
-            order,
-            buy_price,
-            sell_price,
-            buy_time,
-            ..
-        } in &self.open
-        {
+        for history in &self.open {
             writeln!(
                 f,
                 "{}:\t {:+.2}%\t (OPEN)\t {}",
-                order.market,
-                (sell_price / buy_price * (1.0 - 2.0 * self.fee) - 1.0) * 100.0,
-                format_timestamp(*buy_time),
+                history.order.market,
+                history.change() * 100.0,
+                format_timestamp(history.buy_time),
             )?;
         }
 
-        let total = self
-            .closed
-            .iter()
-            .chain(self.open.iter())
-            .map(
-                |OrderHistory {
-                     buy_price,
-                     sell_price,
-                     ..
-                 }| (sell_price / buy_price * (1.0 - 2.0 * self.fee) - 1.0),
-            )
-            .sum::<f32>()
-            / self.concurrency as f32
-            * 100.0;
+        let total = self.total_profit() * 100.0;
 
         let wins = self
             .closed
@@ -198,13 +552,316 @@ impl<S: Strategy> fmt::Display for Simulated<S> {
 
         writeln!(
             f,
-            "TOTAL:  \t {:+.2}%\n{:+.2}% per trade\n{} trades ({:.2}% profitable)",
+            "TOTAL:  \t {:+.2}%\n{:+.2}% per trade\n{} trades ({:.2}% profitable)\nfees paid: {:.2}%",
             total,
-            total / (self.open.len() + self.closed.len()) as f32,
+            total / (self.open.len() + self.closed.len()) as Number,
             wins + losses,
-            wins as f32 / (wins + losses) as f32 * 100.0,
+            wins as Number / (wins + losses) as Number * 100.0,
+            self.closed
+                .iter()
+                .chain(self.open.iter())
+                .map(OrderHistory::fees)
+                .sum::<Number>()
+                * 100.0,
         )?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Trail, Tranche};
+
+    /// Buys on the first trade, trails the stop once filled and sells half at 120.
+    #[derive(Default)]
+    struct Scripted {
+        filled: usize,
+        closed: usize,
+    }
+
+    impl Strategy for Scripted {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            None
+        }
+
+        fn event(&mut self, event: MarketEvent, account: &Account) -> Vec<Intent> {
+            match event {
+                MarketEvent::Trade(trade) if account.position(&trade.market).is_none() => {
+                    vec![Intent::Place(Order {
+                        market: trade.market,
+                        price: trade.price,
+                        take_profit: Some(Decimal::new(200, 0)),
+                        stop_loss: None,
+                        tranches: Vec::new(),
+                        size: None,
+                        side: Side::Buy,
+                    })]
+                }
+                MarketEvent::Trade(trade) if trade.price == Decimal::new(120, 0) => {
+                    vec![Intent::Close {
+                        market: trade.market,
+                        fraction: Decimal::new(5, 1),
+                    }]
+                }
+                MarketEvent::OrderFilled(fill) => {
+                    self.filled += 1;
+                    vec![Intent::TrailStop {
+                        market: fill.market,
+                        trail: Trail::Distance(Decimal::new(5, 0)),
+                    }]
+                }
+                MarketEvent::PositionClosed(_) => {
+                    self.closed += 1;
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            }
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Scripted {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "scripted")
+        }
+    }
+
+    /// Places a single order on the first trade.
+    struct Once(Option<Order>);
+
+    impl Strategy for Once {
+        fn run(&mut self, _trade: Trade) -> Option<Order> {
+            self.0.take()
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Once {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "once")
+        }
+    }
+
+    /// Places an order on every trade, but is only ready from the third trade on.
+    struct Warming(usize);
+
+    impl Strategy for Warming {
+        fn run(&mut self, trade: Trade) -> Option<Order> {
+            self.0 += 1;
+            Some(order(trade.price.to_i64().unwrap()))
+        }
+
+        fn is_ready(&self) -> bool {
+            self.0 >= 3
+        }
+
+        #[cfg(feature = "plot")]
+        fn plot(&self) {}
+    }
+
+    impl fmt::Display for Warming {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "warming")
+        }
+    }
+
+    fn order(price: i64) -> Order {
+        Order {
+            market: "BTCUSDT".to_owned(),
+            price: Decimal::new(price, 0),
+            take_profit: None,
+            stop_loss: None,
+            tranches: Vec::new(),
+            size: None,
+            side: Side::Buy,
+        }
+    }
+
+    fn run_once(order: Order, entry: Entry, prices: &[i64]) -> Simulated<Once> {
+        let mut simulated = Simulated::new(Once(Some(order)), 0.0, 1).with_entry(entry);
+        for (i, price) in prices.iter().enumerate() {
+            simulated.run(Trade {
+                market: "BTCUSDT".to_owned(),
+                quantity: Decimal::new(1, 0),
+                price: Decimal::new(*price, 0),
+                timestamp: (i as i64 + 1) * 1000,
+                venue: None,
+            });
+        }
+        simulated
+    }
+
+    #[test]
+    fn entries() {
+        let limit = run_once(order(95), Entry::Limit { timeout: 5000 }, &[100, 97, 94]);
+        assert_eq!(limit.open.len(), 1);
+        assert_eq!(limit.open[0].buy_price, 95.0);
+        assert_eq!(limit.open[0].buy_time, 3000);
+
+        let timed_out = run_once(order(95), Entry::Limit { timeout: 1500 }, &[100, 97, 94]);
+        assert!(timed_out.open.is_empty());
+        assert!(timed_out.pending.is_empty());
+
+        let crossing = run_once(order(101), Entry::Limit { timeout: 5000 }, &[100, 97, 94]);
+        assert_eq!(crossing.open[0].buy_price, 100.0);
+
+        let post_only = run_once(
+            order(101),
+            Entry::PostOnly { timeout: 5000 },
+            &[100, 97, 94],
+        );
+        assert!(post_only.open.is_empty());
+        assert!(post_only.pending.is_empty());
+
+        let ioc = run_once(order(95), Entry::ImmediateOrCancel, &[100, 97, 94]);
+        assert!(ioc.open.is_empty());
+        assert!(ioc.pending.is_empty());
+    }
+
+    #[test]
+    fn venues() {
+        let order = Order {
+            take_profit: Some(Decimal::new(110, 0)),
+            ..order(95)
+        };
+        let mut simulated = Simulated::new(Once(Some(order)), 0.0, 1)
+            .with_venue("Binance")
+            .with_entry(Entry::Limit { timeout: 10000 });
+        for (i, (venue, price)) in [
+            ("Binance", 100),
+            ("Feed", 90),
+            ("Binance", 94),
+            ("Feed", 200),
+        ]
+        .iter()
+        .enumerate()
+        {
+            simulated.run(Trade {
+                market: "BTCUSDT".to_owned(),
+                quantity: Decimal::new(1, 0),
+                price: Decimal::new(*price, 0),
+                timestamp: (i as i64 + 1) * 1000,
+                venue: Some(venue.to_string()),
+            });
+        }
+
+        // Only the trades of the venue fill the order and reach its exits.
+        assert_eq!(simulated.open.len(), 1);
+        assert_eq!(simulated.open[0].buy_time, 3000);
+        assert_eq!(simulated.open[0].sell_price, 94.0);
+        assert!(simulated.closed.is_empty());
+    }
+
+    #[test]
+    fn warm_up() {
+        let mut simulated = Simulated::new(Warming(0), 0.0, 1);
+        for (i, price) in [100, 101, 102].iter().enumerate() {
+            simulated.run(Trade {
+                market: "BTCUSDT".to_owned(),
+                quantity: Decimal::new(1, 0),
+                price: Decimal::new(*price, 0),
+                timestamp: (i as i64 + 1) * 1000,
+                venue: None,
+            });
+        }
+
+        assert_eq!(simulated.open.len(), 1);
+        assert_eq!(simulated.open[0].buy_price, 102.0);
+    }
+
+    #[test]
+    fn tranches() {
+        let mut order = order(100);
+        order.take_profit = Some(Decimal::new(120, 0));
+        order.stop_loss = Some(Decimal::new(90, 0));
+        order.tranches = vec![Tranche {
+            price: Decimal::new(110, 0),
+            fraction: Decimal::new(5, 1),
+            stop_loss: Some(Decimal::new(100, 0)),
+        }];
+
+        let simulated = run_once(order, Entry::Market, &[100, 111, 105, 100]);
+        assert!(simulated.open.is_empty());
+        assert_eq!(simulated.closed.len(), 2);
+        assert_eq!(simulated.closed[0].quantity, 0.5);
+        assert_eq!(simulated.closed[0].sell_price, 110.0);
+        assert_eq!(simulated.closed[1].quantity, 0.5);
+        assert_eq!(simulated.closed[1].sell_price, 100.0);
+        assert!(simulated.to_string().contains("TOTAL:  \t +5.00%"));
+    }
+
+    #[test]
+    fn intents() {
+        let mut simulated = Simulated::new(Scripted::default(), 0.0, 1);
+        for (i, price) in [100, 110, 120, 114].iter().enumerate() {
+            simulated.run(Trade {
+                market: "BTCUSDT".to_owned(),
+                quantity: Decimal::new(1, 0),
+                price: Decimal::new(*price, 0),
+                timestamp: (i as i64 + 1) * 1000,
+                venue: None,
+            });
+        }
+
+        assert_eq!(simulated.strategy.filled, 1);
+        assert_eq!(simulated.strategy.closed, 1);
+        assert!(simulated.open.is_empty());
+        assert_eq!(simulated.closed.len(), 2);
+        assert_eq!(
+            simulated.closed[1].order.stop_loss,
+            Some(Decimal::new(115, 0))
+        );
+        assert!(simulated.to_string().contains("TOTAL:  \t +17.00%"));
+    }
+
+    #[test]
+    fn fees() {
+        let fees = Fees::new(Decimal::new(1, 3), Decimal::new(2, 3));
+        let run = |order: Order, entry: Entry, prices: &[i64]| {
+            let mut simulated = Simulated::new(Once(Some(order)), 0.0, 1)
+                .with_entry(entry)
+                .with_fees(fees);
+            for (i, price) in prices.iter().enumerate() {
+                simulated.run(Trade {
+                    market: "BTCUSDT".to_owned(),
+                    quantity: Decimal::new(1, 0),
+                    price: Decimal::new(*price, 0),
+                    timestamp: (i as i64 + 1) * 1000,
+                    venue: None,
+                });
+            }
+            simulated
+        };
+
+        // Filled limit orders and take profits are makers.
+        let maker = run(
+            Order {
+                take_profit: Some(Decimal::new(100, 0)),
+                ..order(95)
+            },
+            Entry::Limit { timeout: 5000 },
+            &[100, 95, 100],
+        );
+        assert_eq!(maker.closed[0].entry_fee, 0.001);
+        assert_eq!(maker.closed[0].exit_fee, 0.001);
+
+        // Market orders and stop losses are takers.
+        let taker = run(
+            Order {
+                stop_loss: Some(Decimal::new(90, 0)),
+                ..order(100)
+            },
+            Entry::Market,
+            &[100, 90],
+        );
+        assert_eq!(taker.closed[0].entry_fee, 0.002);
+        assert_eq!(taker.closed[0].exit_fee, 0.002);
+        assert!(taker.to_string().contains("-10.38%"));
+    }
+}
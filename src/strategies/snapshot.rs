@@ -0,0 +1,79 @@
+use super::Strategy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+/// Strategies whose state can be written to disk and restored later, so warmed-up indicators
+/// survive a restart. Snapshots keep the timestamp of the last event the strategy has seen, so
+/// the trades since can be replayed after restoring.
+pub trait Snapshot: Strategy + Serialize + DeserializeOwned {
+    fn snapshot(&self, timestamp: i64) -> Vec<u8> {
+        bincode::serialize(&(timestamp, self)).expect("Couldn't serialize strategy.")
+    }
+
+    /// The strategy and the timestamp it was saved at.
+    fn restore(bin: &[u8]) -> io::Result<(Self, i64)> {
+        let (timestamp, strategy) = bincode::deserialize(bin)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        Ok((strategy, timestamp))
+    }
+
+    fn save<P: AsRef<Path>>(&self, path: P, timestamp: i64) -> io::Result<()> {
+        // Write to a temporary file first, so a crash never leaves a truncated snapshot behind.
+        let tmp = path.as_ref().with_extension("tmp");
+        fs::write(&tmp, self.snapshot(timestamp))?;
+        fs::rename(tmp, path)
+    }
+
+    fn load<P: AsRef<Path>>(path: P) -> io::Result<(Self, i64)> {
+        Self::restore(&fs::read(path)?[..])
+    }
+}
+
+impl<S: Strategy + Serialize + DeserializeOwned> Snapshot for S {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        exchanges::Trade,
+        strategies::{Custom, Duplicated, Interval},
+        Number,
+    };
+    use rust_decimal::prelude::*;
+
+    #[test]
+    fn restore() {
+        let trades = (0..5000).map(|i| Trade {
+            market: if i % 2 == 0 { "BTCUSDT" } else { "ETHUSDT" }.to_owned(),
+            quantity: Decimal::from(i % 7) - Decimal::new(3, 0),
+            price: Decimal::from_f64(100.0 + (i as Number / 50.0).sin())
+                .unwrap()
+                .round_dp(4),
+            timestamp: i * 1000 * 30,
+            venue: None,
+        });
+
+        let mut strategy = Duplicated::new(Interval::new(Custom::new(), 1000 * 60));
+        for trade in trades.clone().take(4000) {
+            strategy.run(trade);
+        }
+
+        let (mut restored, timestamp) =
+            Duplicated::<Interval<Custom>>::restore(&strategy.snapshot(3999 * 1000 * 30)).unwrap();
+        assert_eq!(timestamp, 3999 * 1000 * 30);
+        assert_eq!(restored.snapshot(0), strategy.snapshot(0));
+
+        for trade in trades.skip(4000) {
+            assert_eq!(
+                strategy.run(trade.clone()).map(|order| order.to_string()),
+                restored.run(trade).map(|order| order.to_string())
+            );
+        }
+        // Every indicator of every market ends up in the same state.
+        assert_eq!(restored.snapshot(0), strategy.snapshot(0));
+    }
+}